@@ -9,14 +9,21 @@ fn main() {
     let mut outfile: String = "".to_string();
     let mut options = config::DedElfOps::no_ops();
     match config::parse_args(&mut infile, &mut outfile, &mut options) {
-        Ok(()) => match dedelf::run(infile, options, outfile) {
-            Err(err) => {
-                println!("\nDEDelf: Exiting due to error: {:?}", err);
-                process::exit(1);
-            }
-            Ok(()) => {
-                println!("\nDEDelf: successful byte edits written to file, exiting.");
-                process::exit(0);
+        Ok(()) => {
+            let list_only = options.list_only();
+            match dedelf::run(infile, options, outfile) {
+                Err(err) => {
+                    println!("\nDEDelf: Exiting due to error: {:?}", err);
+                    process::exit(1);
+                }
+                Ok(()) if list_only => {
+                    println!("\nDEDelf: no output file written, exiting.");
+                    process::exit(0);
+                }
+                Ok(()) => {
+                    println!("\nDEDelf: successful byte edits written to file, exiting.");
+                    process::exit(0);
+                }
             }
         },
         Err(err) => {
@@ -1,18 +1,25 @@
 use std::str::FromStr;
-use argparse::{ArgumentParser, StoreTrue, Store, StoreOption};//,StoreFalse, List};
+use std::fs::File;
+use argparse::{ArgumentParser, StoreTrue, Store, StoreOption, Collect};//,StoreFalse, List};
+use serde_json::Value;
+
+use crate::section::{SH_Type, match_sh_type_as_str, match_sh_flags_as_str};
+use crate::segment::{PT_type, P_flag, match_p_type_as_str, match_p_flag_as_str};
 
 /* Default injection mode values */
 const INJ_DEFAULT_SIZE: usize = 0x1000;
 const INJ_DEFAULT_EXT: Option<&'static str> = Some(".text");
-const INJ_DEFAULT_ENTRY: Option<u64> = None;
-const INJ_DEFAULT_OFFSET: Option<u64> = None;
+const INJ_DEFAULT_ENTRY: Option<Location> = None;
+const INJ_DEFAULT_OFFSET: Option<Location> = None;
 const INJ_DEFAULT_REPLACE: bool = false;
+const INJ_DEFAULT_STRATEGY: InjStrategy = InjStrategy::EXTEND;
+const INJ_DEFAULT_TRAMPOLINE: bool = false;
 /*
 * Parse args such that a user can enter args in any order. All args other than input file
 * and modification type are optional for injection mode; for modification mode, all args are
-* required.
-*
-* TODO: implement support for config file parsing
+* required. If a config file is provided with --file-config, the mode, infile and all
+* injection/modification options are pulled from the config file instead (see
+* parse_config_file)
 */
 pub fn parse_args(infile: &mut String,
                   outfile: &mut String,
@@ -20,33 +27,51 @@ pub fn parse_args(infile: &mut String,
 
     let mut size: Option<String> = None;//Some(INJ_DEFAULT_SIZE);
     let mut entry: Option<String> = None;//INJ_DEFAULT_ENTRY;
-    let mut extend: Option<String> = None;
+    let mut positions: Vec<String> = Vec::new();
     let mut offset: Option<String> = None;//INJ_DEFAULT_OFFSET;
     let mut replace = INJ_DEFAULT_REPLACE;
+    let mut strategy = INJ_DEFAULT_STRATEGY;
+    let mut list_caves = false;
+    let mut trampoline = INJ_DEFAULT_TRAMPOLINE;
+    let mut thumb = false;
     let mut inj_file: String = " ".to_string();
     let mut toutfile: String = " ".to_string();
-    let mut field: String = " ".to_string();
-    let mut replace_field: String = " ".to_string();
-    let mut mod_mode: ModOps = ModOps::EXEC;
-
-    let mut default_mode: Mode = Mode::INJECT;
+    let mut fields: Vec<String> = Vec::new();
+    let mut replace_fields: Vec<String> = Vec::new();
+    let mut mod_modes: Vec<ModOps> = Vec::new();
+    let mut add_needed: Vec<String> = Vec::new();
+    let mut remove_needed: Vec<String> = Vec::new();
+    let mut replace_needed: Vec<String> = Vec::new();
+    let mut set_rpath: Option<String> = None;
+    let mut set_runpath: Option<String> = None;
+    let mut set_soname: Option<String> = None;
+    let mut set_interpreter: Option<String> = None;
+    let mut rename_symbol: Vec<String> = Vec::new();
+    let mut rename_section: Vec<String> = Vec::new();
+
+    let mut run_mode: Option<Mode> = None;
+    let mut cfg_file: Option<String> = None;
 
     {
         let mut parser = ArgumentParser::new();
 
-        parser.refer(&mut default_mode).required().add_argument("mode", Store,
-                                                                r#"Specify run mode: either `inject` or `modify`"#);
+        parser.refer(&mut run_mode).add_argument("mode", StoreOption,
+                                                 r#"Specify run mode: either `inject` or `modify` (not needed if --file-config is used)"#);
+
+        parser.refer(infile).add_argument("infile", Store,
+                                          r#"File to modify or inject (not needed if --file-config is used)"#);
 
-        parser.refer(infile).required().add_argument("infile", Store,
-                                                     r#"File to modify or inject"#);
+        parser.refer(&mut cfg_file)
+            .add_option(&["--file-config"], StoreOption,
+                        r#"CONFIG MODE: Provide a .json config file specifying the mode, infile, outfile and all injection and/or modification options"#);
 
         parser.refer(&mut inj_file)
             .add_option(&["-i", "--infile"], Store,
-                          r#"INJECTION MODE: Provide a file containing the bytes to inject. File should be trimmed to contain ony desired bytes"#);
+                          r#"INJECTION MODE: Provide a file containing the bytes to inject. File should be trimmed to contain ony desired bytes, or given as `file:section` or `file:symbol` to read the bytes of a section or symbol of an ELF file"#);
 
-        parser.refer(&mut mod_mode)
-            .add_option(&["-m", "--mod"], Store,
-                        r#"MODIFY MODE: Provide a file containing the bytes to inject. File should be trimmed to contain ony desired bytes"#);
+        parser.refer(&mut mod_modes)
+            .add_option(&["-m", "--mod"], Collect,
+                        r#"MODIFY MODE: Specify modification type: `exec_header`, `sec_header`, `prog_header`, `relocation`, `symbol` or `dynamic`. May be repeated to apply several modifications in order"#);
 
         parser.refer(&mut toutfile)
             .add_option(&["-o", "--outfile"], Store,
@@ -57,30 +82,95 @@ pub fn parse_args(infile: &mut String,
                         r#"INJECTION MODE: Specify byte size (will be rounded to next closest size in pages (4k))"#);
         parser.refer(&mut entry)
             .add_option(&["-e", "--entry"], StoreOption,
-                        r#"INJECTION MODE: Specify if entry point in exec header should be modified to provided byte offset"#);
+                        r#"INJECTION MODE: Specify if entry point in exec header should be modified to provided byte offset, given in hex or as `<symbol>+0x<off>`"#);
 
-        parser.refer(&mut extend)
-            .add_option(&["-p", "--position"], StoreOption,
-                        r#"INJECTION or MODIFY MODE: Specify section. If injection mode, this will specify if bytes should be injected at end of provided section (can be name or index). If modify mode, must be used for either sec_header or prog_header options. Use this to specify the name (for sections) or index (for either sections or segments) of the header to be modified"#);
+        parser.refer(&mut positions)
+            .add_option(&["-p", "--position"], Collect,
+                        r#"INJECTION or MODIFY MODE: Specify section. If injection mode, this will specify if bytes should be injected at end of provided section (can be name or index). If modify mode, must be used for either sec_header or prog_header options. Use this to specify the name (for sections) or index (for either sections or segments) of the header to be modified, or `<symbol>+0x<off>` for the section or PT_LOAD segment containing that address. For relocation modifications, use `<section>` (name or index of the relocation section) optionally followed by `:<entry>`, where entry is a hex entry index or the name of the symbol the entries refer to. For symbol modifications, use `[<table>:]<symbol>`, where table is the name or index of .symtab or .dynsym and symbol is a 0x prefixed entry index or a symbol name (just `<table>` when adding a symbol). Repeat once per sec_header, prog_header, relocation or symbol modification"#);
 
         parser.refer(&mut offset)
             .add_option(&["-b", "--offset"], StoreOption,
-                        r#"INJECTION MODE: Specify if bytes should be injected at exact byte offset provided"#);
+                        r#"INJECTION MODE: Specify if bytes should be injected at exact byte offset provided, given in hex or as `<symbol>+0x<off>` (the file offset of that address)"#);
 
         parser.refer(&mut replace)
             .add_option(&["--overwrite"], StoreTrue,
                         r#"INJECTION MODE: Specify if bytes should be injected to replace the entire section"#);
 
-        parser.refer(&mut field)
-            .add_option(&["-f", "--field"], Store,
-                        r#"MODIFY MODE: Specify header field to modify"#);
-        parser.refer(&mut replace_field)
-            .add_option(&["-r", "--replace"], Store,
-                        r#"MODIFY MODE: Specify value to replace field with"#);
+        parser.refer(&mut strategy)
+            .add_option(&["--strategy"], Store,
+                        r#"INJECTION MODE: Specify injection strategy: `extend` (default, grow the segment containing the injection site), `cave` (overwrite a code cave without changing the file layout) `note` (convert a PT_NOTE segment into a PT_LOAD segment at the end of the file), `padding` (use the page padding after the text segment) or `reverse` (grow the text segment downward by whole pages)"#);
+
+        parser.refer(&mut list_caves)
+            .add_option(&["--list-caves"], StoreTrue,
+                        r#"INJECTION MODE: List the code caves large enough for the injection file and exit without writing"#);
+
+        parser.refer(&mut trampoline)
+            .add_option(&["--trampoline"], StoreTrue,
+                        r#"INJECTION MODE: Wrap the injected bytes in a trampoline returning to the original entry point, and point the entry point at it (cannot be combined with -e)"#);
+
+        parser.refer(&mut thumb)
+            .add_option(&["--thumb"], StoreTrue,
+                        r#"INJECTION MODE: Build the trampoline from Thumb instructions (ARM only)"#);
+
+        parser.refer(&mut fields)
+            .add_option(&["-f", "--field"], Collect,
+                        r#"MODIFY MODE: Specify header field to modify. Repeat once per modification"#);
+        parser.refer(&mut replace_fields)
+            .add_option(&["-r", "--replace"], Collect,
+                        r#"MODIFY MODE: Specify value to replace field with. Repeat once per modification"#);
+
+        parser.refer(&mut add_needed)
+            .add_option(&["--add-needed"], Collect,
+                        r#"MODIFY MODE: Add a DT_NEEDED entry for the provided library, after the existing ones. Shorthand for `-m dynamic -f add_needed -r <lib>`, applied after any `-m` modifications"#);
+        parser.refer(&mut remove_needed)
+            .add_option(&["--remove-needed"], Collect,
+                        r#"MODIFY MODE: Remove the DT_NEEDED entry for the provided library"#);
+        parser.refer(&mut replace_needed)
+            .add_option(&["--replace-needed"], Collect,
+                        r#"MODIFY MODE: Replace a needed library, given as `<old>:<new>`"#);
+        parser.refer(&mut set_rpath)
+            .add_option(&["--set-rpath"], StoreOption,
+                        r#"MODIFY MODE: Set the DT_RPATH string, adding the entry if there is none. Shorthand for `-m dynamic -f set_rpath -r <path>`"#);
+        parser.refer(&mut set_runpath)
+            .add_option(&["--set-runpath"], StoreOption,
+                        r#"MODIFY MODE: Set the DT_RUNPATH string, adding the entry if there is none"#);
+        parser.refer(&mut set_soname)
+            .add_option(&["--set-soname"], StoreOption,
+                        r#"MODIFY MODE: Set the DT_SONAME string, adding the entry if there is none"#);
+        parser.refer(&mut set_interpreter)
+            .add_option(&["--set-interpreter"], StoreOption,
+                        r#"MODIFY MODE: Set the program interpreter path in PT_INTERP and .interp. Shorthand for `-m dynamic -f set_interpreter -r <path>`"#);
+        parser.refer(&mut rename_symbol)
+            .add_option(&["--rename-symbol"], Collect,
+                        r#"MODIFY MODE: Rename a symbol, given as `<old>:<new>` (old may be `<table>:<symbol>`). Shorthand for `-m symbol -p <old> -f rename -r <new>`"#);
+        parser.refer(&mut rename_section)
+            .add_option(&["--rename-section"], Collect,
+                        r#"MODIFY MODE: Rename a section, given as `<old>:<new>`. Shorthand for `-m sec_header -p <old> -f rename -r <new>`"#);
 
         parser.parse_args_or_exit();
     }
 
+    if let Some(cfg_file) = cfg_file {
+        *options = parse_config_file(cfg_file, infile, outfile)?;
+        //an outfile provided on the command line takes precedence over the config file
+        if toutfile != " ".to_string() {
+            *outfile = toutfile.clone();
+        }
+        return Ok(())
+    }
+
+    let default_mode = match run_mode {
+        Some(mode) => mode,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "No run mode provided; use either `inject` or \
+                                               `modify`, or provide a config file")),
+    };
+
+    if infile.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                       "No input file provided"))
+    }
+
     //Set default outfile name if none provided. Default outfile name is _inj
     // appended to original file name
     if toutfile == " ".to_string(){
@@ -95,25 +185,407 @@ pub fn parse_args(infile: &mut String,
 
     match default_mode{
         Mode::INJECT => {
-            if extend == None && offset == None {
+            if positions.len() > 1 {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid CLI options provided: only one injection \
+                                               position may be provided"))
+            }
+            let mut extend = positions.pop();
+            if extend == None && offset == None && strategy == InjStrategy::EXTEND && !list_caves {
                 println!("Setting default inject section to {:?}", INJ_DEFAULT_EXT);
                 extend = Some(INJ_DEFAULT_EXT.unwrap().to_string());
             }
-            *options = DedElfOps::parse_inj_ops(size, extend, entry, replace, offset, inj_file)?;
+            *options = DedElfOps::parse_inj_ops(size, extend, entry, replace, offset, inj_file,
+                                                strategy, list_caves, trampoline, thumb)?;
             return Ok(())
         }
         Mode::MODIFY => {
-            *options = DedElfOps::parse_mod_ops(mod_mode,field, replace_field, extend)?;
+            //the patchelf style options are modifications applied after the `-m` ones
+            let mut extra_mods: Vec<ModCfg> = Vec::new();
+            for (field, vals) in vec![("add_needed", add_needed),
+                                      ("remove_needed", remove_needed),
+                                      ("replace_needed", replace_needed),
+                                      ("set_rpath", set_rpath.into_iter().collect()),
+                                      ("set_runpath", set_runpath.into_iter().collect()),
+                                      ("set_soname", set_soname.into_iter().collect()),
+                                      ("set_interpreter", set_interpreter.into_iter().collect())] {
+                for val in vals {
+                    extra_mods.push(DedElfOps::parse_mod_op(ModOps::DYNAMIC, field.to_string(),
+                                                            val, None)?);
+                }
+            }
+            for (op, vals) in vec![(ModOps::SYMBOL, rename_symbol),
+                                   (ModOps::SECTION, rename_section)] {
+                for val in vals {
+                    let (old, new) = match val.rfind(':') {
+                        Some(pos) => (val[..pos].to_string(), val[pos + 1..].to_string()),
+                        None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                               "Invalid CLI options provided: \
+                                                               renames must be given as \
+                                                               <old>:<new>")),
+                    };
+                    extra_mods.push(DedElfOps::parse_mod_op(op.clone(), "rename".to_string(),
+                                                            new, Some(old))?);
+                }
+            }
+            *options = DedElfOps::parse_mod_ops(mod_modes, fields, replace_fields, positions,
+                                                extra_mods)?;
             return Ok(())
         }
         _ => {
             return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                           "Invalid CLI options provided"))
+                                           "Invalid CLI options provided: dual mode is only \
+                                           supported with a config file"))
         }
 
     }
 }
 
+/*
+* Parse a .json config file (see tests/dedelf.json for the full schema) into the injection
+* and/or modification options. Every value in the config file is a string, and an empty
+* string is treated the same as an option not provided on the command line. The `mode` key
+* selects which option blocks are parsed: `inject`, `modify`, or `dual` for both injection
+* and modification in the same pass. Each modify options block may be a list, and the
+* `modifications` list may mix modification types, applied in the order listed.
+*/
+pub fn parse_config_file(cfg_file: String,
+                         infile: &mut String,
+                         outfile: &mut String) -> Result<DedElfOps, std::io::Error> {
+    let fp = match File::open(cfg_file.clone()) {
+        Err(why) => {
+            println!("Could not open config file: {}: {}", cfg_file, why);
+            return Err(why)
+        }
+        Ok(fp) => fp,
+    };
+
+    let cfg: Value = match serde_json::from_reader(fp) {
+        Err(why) => {
+            println!("Could not parse config file: {}: {}", cfg_file, why);
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid config file: not valid json"))
+        }
+        Ok(cfg) => cfg,
+    };
+
+    let mode = match get_cfg_str(&cfg, "mode") {
+        Some(mode) => Mode::from_str(mode.as_str())?,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid config file: no mode provided")),
+    };
+
+    *infile = match get_cfg_str(&cfg, "infile") {
+        Some(file) => file,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid config file: no infile provided")),
+    };
+
+    //Same default as CLI mode: _inj appended to original file name
+    *outfile = match get_cfg_str(&cfg, "outfile") {
+        Some(file) => file,
+        None => {
+            let mut tfile = infile.clone();
+            tfile.push_str("_inj");
+            tfile
+        }
+    };
+
+    let (inj, modify) = match mode {
+        Mode::INJECT => (true, false),
+        Mode::MODIFY => (false, true),
+        Mode::DUAL => (true, true),
+    };
+
+    let mut options = DedElfOps::no_ops();
+
+    if inj {
+        let inj_cfg = match cfg.get("injection-options") {
+            Some(inj_cfg) => inj_cfg,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid config file: no injection options provided")),
+        };
+        let file = match get_cfg_str(inj_cfg, "file") {
+            Some(file) => file,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid config file: no injection file provided")),
+        };
+        let replace = match get_cfg_str(inj_cfg, "replace") {
+            Some(replace) => match replace.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                    "Invalid config file: replace must be `true` or `false`")),
+            },
+            None => INJ_DEFAULT_REPLACE,
+        };
+        let strategy = match get_cfg_str(inj_cfg, "strategy") {
+            Some(strategy) => InjStrategy::from_str(strategy.as_str())?,
+            None => INJ_DEFAULT_STRATEGY,
+        };
+        let list_caves = match get_cfg_str(inj_cfg, "list-caves") {
+            Some(list_caves) => match list_caves.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                    "Invalid config file: list-caves must be `true` or `false`")),
+            },
+            None => false,
+        };
+        let trampoline = match get_cfg_str(inj_cfg, "trampoline") {
+            Some(trampoline) => match trampoline.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                    "Invalid config file: trampoline must be `true` or `false`")),
+            },
+            None => INJ_DEFAULT_TRAMPOLINE,
+        };
+        let thumb = match get_cfg_str(inj_cfg, "thumb") {
+            Some(thumb) => match thumb.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                    "Invalid config file: thumb must be `true` or `false`")),
+            },
+            None => false,
+        };
+
+        options.injection = DedElfOps::parse_inj_ops(get_cfg_str(inj_cfg, "size"),
+                                                     get_cfg_str(inj_cfg, "section"),
+                                                     get_cfg_str(inj_cfg, "entry"),
+                                                     replace,
+                                                     get_cfg_str(inj_cfg, "byte-offset"),
+                                                     file, strategy, list_caves,
+                                                     trampoline, thumb)?.injection;
+    }
+
+    if modify {
+        let mod_cfg = match cfg.get("modify-options") {
+            Some(mod_cfg) => mod_cfg,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid config file: no modify options provided")),
+        };
+
+        let mut mods: Vec<ModCfg> = Vec::new();
+
+        //New sections and segments are added first so that later modifications may refer to them
+        for new_sec_cfg in get_cfg_list(mod_cfg, "new-sec-options") {
+            if let Some(cfg) = parse_cfg_new_sec(new_sec_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for new_seg_cfg in get_cfg_list(mod_cfg, "new-seg-options") {
+            if let Some(cfg) = parse_cfg_new_seg(new_seg_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for exec_cfg in get_cfg_list(mod_cfg, "exec-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::EXEC, exec_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for sec_cfg in get_cfg_list(mod_cfg, "header-sec-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::SECTION, sec_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for seg_cfg in get_cfg_list(mod_cfg, "header-seg-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::SEGMENT, seg_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for rel_cfg in get_cfg_list(mod_cfg, "relocation-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::RELOC, rel_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for sym_cfg in get_cfg_list(mod_cfg, "symbol-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::SYMBOL, sym_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        for dyn_cfg in get_cfg_list(mod_cfg, "dynamic-options") {
+            if let Some(cfg) = parse_cfg_mod(ModOps::DYNAMIC, dyn_cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        //Mixed modification types applied in exactly the order listed
+        for cfg in get_cfg_list(mod_cfg, "modifications") {
+            let op = match get_cfg_str(cfg, "type") {
+                Some(op) => ModOps::from_str(op.as_str())?,
+                None => continue,
+            };
+            if let Some(cfg) = parse_cfg_mod(op, cfg)? {
+                mods.push(cfg);
+            }
+        }
+
+        if mods.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid config file: no modify options provided"))
+        }
+        options.modify = Some(ModModeOps {
+            mods: mods,
+        });
+    }
+
+    Ok(options)
+}
+
+/* Parse a single modification from a config file object. Returns None if no field is
+* provided, so that unused blocks of the config file template can be left empty
+*/
+fn parse_cfg_mod(op: ModOps, cfg: &Value) -> Result<Option<ModCfg>, std::io::Error> {
+    let field = match get_cfg_str(cfg, "field") {
+        Some(field) => field,
+        None => return Ok(None),
+    };
+    let replacement = get_cfg_str(cfg, "replacement").unwrap_or(" ".to_string());
+    let placement = match op {
+        ModOps::EXEC | ModOps::DYNAMIC => None,
+        //a section name takes precedence over a section index if both are provided
+        ModOps::SECTION => match get_cfg_str(cfg, "name") {
+            Some(name) => Some(name),
+            None => get_cfg_str(cfg, "index"),
+        },
+        ModOps::SEGMENT => get_cfg_str(cfg, "index"),
+        //relocation entries are selected as section[:entry], as on the command line
+        ModOps::RELOC => match (get_cfg_str(cfg, "section"), get_cfg_str(cfg, "entry")) {
+            (Some(sec), Some(entry)) => Some(format!("{}:{}", sec, entry)),
+            (sec, _) => sec,
+        },
+        //symbols are selected as [table:]symbol, as on the command line
+        ModOps::SYMBOL => match (get_cfg_str(cfg, "table"), get_cfg_str(cfg, "symbol")) {
+            (Some(table), Some(sym)) => Some(format!("{}:{}", table, sym)),
+            (None, sym) => sym,
+            (table, None) => table,
+        },
+    };
+    Ok(Some(DedElfOps::parse_mod_op(op, field, replacement, placement)?))
+}
+
+/* Parse a new section from a config file object. Returns None if no name is provided */
+fn parse_cfg_new_sec(cfg: &Value) -> Result<Option<ModCfg>, std::io::Error> {
+    let name = match get_cfg_str(cfg, "name") {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+
+    let hdr = match cfg.get("sec-header-options") {
+        Some(hdr) => hdr,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid config file: no sec-header-options \
+                                               provided for new section")),
+    };
+
+    let sh_type = match get_cfg_str(hdr, "sh_type") {
+        Some(sh_type) => match_sh_type_as_str(sh_type)?,
+        None => SH_Type::SHT_PROGBITS as u32,
+    };
+    let sh_flags = match get_cfg_str(hdr, "sh_flags") {
+        Some(sh_flags) => match_sh_flags_as_str(sh_flags)?,
+        None => 0,
+    };
+
+    Ok(Some(ModCfg::NewSec(NewSecCfg {
+        name: name,
+        file: get_cfg_str(cfg, "file"),
+        seg_idx: parse_cfg_hex(cfg, "segment")?.map(|idx| idx as usize),
+        sh_type: sh_type,
+        sh_flags: sh_flags,
+        sh_size: parse_cfg_hex(hdr, "sh_size")?,
+        sh_link: parse_cfg_hex(hdr, "sh_link")?.unwrap_or(0) as u32,
+        sh_info: parse_cfg_hex(hdr, "sh_info")?.unwrap_or(0) as u32,
+        sh_addralign: parse_cfg_hex(hdr, "sh_addralign")?.unwrap_or(1),
+        sh_entsize: parse_cfg_hex(hdr, "sh_entsize")?.unwrap_or(0),
+    })))
+}
+
+/* Parse a new segment from a config file object. Returns None if no options are provided */
+fn parse_cfg_new_seg(cfg: &Value) -> Result<Option<ModCfg>, std::io::Error> {
+    let hdr = match cfg.get("prog-header-options") {
+        Some(hdr) => hdr,
+        None => return Ok(None),
+    };
+    let file = get_cfg_str(cfg, "file");
+    let p_type = get_cfg_str(hdr, "p_type");
+    let p_flags = get_cfg_str(hdr, "p_flags");
+    if file.is_none() && p_type.is_none() && p_flags.is_none() {
+        return Ok(None)
+    }
+
+    let p_type = match p_type {
+        Some(p_type) => match_p_type_as_str(p_type)?,
+        None => PT_type::PT_LOAD as u32,
+    };
+    let p_flags = match p_flags {
+        Some(p_flags) => match_p_flag_as_str(p_flags)?,
+        None => P_flag::PF_R as u32,
+    };
+
+    Ok(Some(ModCfg::NewSeg(NewSegCfg {
+        file: file,
+        p_type: p_type,
+        p_flags: p_flags,
+        p_vaddr: parse_cfg_hex(hdr, "p_vaddr")?,
+        p_memsz: parse_cfg_hex(hdr, "p_memsz")?,
+        p_align: parse_cfg_hex(hdr, "p_align")?.unwrap_or(INJ_DEFAULT_SIZE as u64),
+    })))
+}
+
+/* Parse a hex value within a config file object, if provided */
+fn parse_cfg_hex(cfg: &Value, key: &str) -> Result<Option<u64>, std::io::Error> {
+    match get_cfg_str(cfg, key) {
+        Some(val) => {
+            let trimmed = val.trim_start_matches("0x");
+            match u64::from_str_radix(trimmed, 16) {
+                Ok(val) => Ok(Some(val)),
+                Err(_) => {
+                    println!("Invalid hex value for config file option {}: {}", key, val);
+                    Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid config file: did you provide a valid hex value?"))
+                }
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/* Options blocks may be provided as either a single object or a list of objects */
+fn get_cfg_list<'a>(cfg: &'a Value, key: &str) -> Vec<&'a Value> {
+    match cfg.get(key) {
+        Some(Value::Array(vals)) => vals.iter().collect(),
+        Some(val) => vec![val],
+        None => Vec::new(),
+    }
+}
+
+/* Return the string value for key within a config file object, or None if the key is
+* missing or its value is an empty string
+*/
+fn get_cfg_str(cfg: &Value, key: &str) -> Option<String> {
+    match cfg.get(key) {
+        Some(Value::String(val)) => {
+            let val = val.trim();
+            if val.is_empty() {
+                None
+            } else {
+                Some(val.to_string())
+            }
+        }
+        _ => None,
+    }
+}
+
 
 #[derive(Debug)]
 pub struct DedElfOps {
@@ -129,12 +601,28 @@ impl DedElfOps {
         entry: Option<String>,
         replace: bool,
         b_offset: Option<String>,
-        file: String) -> Result<DedElfOps, std::io::Error> {
+        file: String,
+        strategy: InjStrategy,
+        list_caves: bool,
+        trampoline: bool,
+        thumb: bool) -> Result<DedElfOps, std::io::Error> {
+        //the trampoline decides the entry point, and only ARM has a choice of instruction set
+        if trampoline && entry.is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid injection mode options: -e cannot be combined \
+                                           with --trampoline, which points the entry point at the \
+                                           trampoline"))
+        }
+        if thumb && !trampoline {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid injection mode options: --thumb requires \
+                                           --trampoline"))
+        }
         let mut op_flag = false;
         let mut new_size: usize = INJ_DEFAULT_SIZE;
-        let mut new_entry: Option<u64> = INJ_DEFAULT_ENTRY;
+        let mut new_entry: Option<Location> = INJ_DEFAULT_ENTRY;
         let mut new_extend: Option<String> = None;//Some(INJ_DEFAULT_EXT.unwrap().to_string());
-        let mut new_b_offset: Option<u64> = INJ_DEFAULT_OFFSET;
+        let mut new_b_offset: Option<Location> = INJ_DEFAULT_OFFSET;
 
         if let Some(size) = size {
             op_flag = true;
@@ -150,41 +638,47 @@ impl DedElfOps {
         }
         if let Some(entry) = entry {
             op_flag = true;
-            let trimmed = entry.trim_start_matches("0x");
-            let check = u64::from_str_radix(trimmed, 16);
-            if check.is_err() {
+            let check = parse_location(&entry);
+            if check.is_none() {
                 return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                "Invalid injection mode options (entry) provided"))
             }
 
-            new_entry = Some(check.unwrap());
+            new_entry = check;
         }
 
         if let Some(extend) = extend {
             op_flag = true;
             new_extend = Some(extend);
 
-        } else if extend.is_none() && b_offset.is_none() {
+        } else if extend.is_none() && b_offset.is_none() && strategy == InjStrategy::EXTEND {
             new_extend = Some(INJ_DEFAULT_EXT.unwrap().to_string());
         }
 
         if let Some(b_offset) = b_offset {
             op_flag = true;
-            let trimmed = b_offset.trim_start_matches("0x");
-            let check = u64::from_str_radix(trimmed, 16);
-            if check.is_err() {
+            let check = parse_location(&b_offset);
+            if check.is_none() {
                 return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                "Invalid injection mode options (byte offset) provided"))
             }
-            new_b_offset = Some(check.unwrap());
+            new_b_offset = check;
         }
 
 
 
         match op_flag {
             false => {
+                let mut inj = InjModeOps::default(file);
+                if strategy != InjStrategy::EXTEND {
+                    inj.extend = None;
+                }
+                inj.strategy = strategy;
+                inj.list_caves = list_caves;
+                inj.trampoline = trampoline;
+                inj.thumb = thumb;
                 return Ok(DedElfOps {
-                    injection: Some(InjModeOps::default(file)),
+                    injection: Some(inj),
                     modify: None
                 })
             }
@@ -198,6 +692,10 @@ impl DedElfOps {
                             new_entry: new_entry,
                             replace: replace,
                             b_offset: new_b_offset,
+                            strategy: strategy,
+                            list_caves: list_caves,
+                            trampoline: trampoline,
+                            thumb: thumb,
                         }),
                         modify: None,
                 })
@@ -212,34 +710,102 @@ impl DedElfOps {
         }
     }
 
-    pub fn parse_mod_ops(op: ModOps, field: String, replacement: String,
-                         placement: Option<String>) -> Result<DedElfOps, std::io::Error> {
-        if field == " ".to_string() || replacement == " ".to_string(){
+    /*
+    * Parse repeated modify mode options into an ordered list of modifications. Each `-m`
+    * option is paired with the `-f` and `-r` options in the same position, and section,
+    * program header, relocation and symbol modifications consume the `-p` options in the order provided
+    * (exec header and dynamic modifications do not take a `-p` option). If no `-m` options
+    * are provided, every modification is assumed to be an exec header modification. Already
+    * parsed modifications (e.g. from --add-needed or --rename-symbol) are applied after the others.
+    */
+    pub fn parse_mod_ops(ops: Vec<ModOps>, fields: Vec<String>, replacements: Vec<String>,
+                         placements: Vec<String>,
+                         extra_mods: Vec<ModCfg>) -> Result<DedElfOps, std::io::Error> {
+        let ops = if ops.is_empty() {
+            vec![ModOps::EXEC; fields.len()]
+        } else {
+            ops
+        };
+
+        if (ops.is_empty() && extra_mods.is_empty()) || ops.len() != fields.len() ||
+            ops.len() != replacements.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid mod mode options provided: each modification \
+                                           requires a field and a replacement"))
+        }
+
+        let mut placements = placements.into_iter();
+        let mut mods: Vec<ModCfg> = Vec::new();
+        for ((op, field), replacement) in ops.into_iter().zip(fields).zip(replacements) {
+            let placement = match op {
+                ModOps::EXEC | ModOps::DYNAMIC => None,
+                ModOps::SECTION | ModOps::SEGMENT | ModOps::RELOC |
+                ModOps::SYMBOL => placements.next(),
+            };
+            mods.push(DedElfOps::parse_mod_op(op, field, replacement, placement)?);
+        }
+
+        if placements.next().is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid mod mode options provided: more positions \
+                                           provided than section/program header, relocation and \
+                                           symbol modifications"))
+        }
+        mods.extend(extra_mods);
+
+        Ok(DedElfOps {
+            injection: None,
+            modify: Some(ModModeOps {
+                mods: mods,
+            }),
+        })
+    }
+
+    pub fn parse_mod_op(op: ModOps, field: String, replacement: String,
+                        placement: Option<String>) -> Result<ModCfg, std::io::Error> {
+        //deleting a relocation entry and removing or converting a dynamic string entry are
+        // the only modifications without a replacement value
+        let deleting = match op {
+            ModOps::RELOC => field == "delete".to_string(),
+            ModOps::DYNAMIC => match field.as_str() {
+                "remove_rpath" | "remove_runpath" | "remove_soname" |
+                "convert_rpath" | "convert_runpath" => true,
+                _ => false,
+            },
+            _ => false,
+        };
+        if field == " ".to_string() || (replacement == " ".to_string() && !deleting) {
             return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                            "Invalid mod mode options provided"))
         }
 
-        let mod_ops = match op {
+        let mod_cfg = match op {
             ModOps::EXEC => {
-                ModModeOps {
-                    exec: Some(ExecCfg {
-                        op_mode: parse_exec_mod_ops(field)?,
-                        replacement: replacement,
-                    }),
-                    sec: None,
-                    seg: None,
-                }
+                ModCfg::Exec(ExecCfg {
+                    op_mode: parse_exec_mod_ops(field)?,
+                    replacement: replacement,
+                })
             }
             ModOps::SECTION => {
                 if let Some(placement) = placement {
                     let cfg: SecCfg;
                     let trimmed = placement.trim_start_matches("0x");
                     let check = usize::from_str_radix(trimmed, 16);
-                    if check.is_err() {
+                    let location = parse_symbol_location(&placement);
+                    if location.is_some() {
+                        cfg = SecCfg {
+                            op_mode: parse_sec_mod_ops(field)?,
+                            sec_name: None,
+                            sec_idx: None,
+                            location: location,
+                            replacement: replacement,
+                        };
+                    } else if check.is_err() {
                         cfg = SecCfg {
                             op_mode: parse_sec_mod_ops(field)?,
                             sec_name: Some(placement),
                             sec_idx: None,
+                            location: None,
                             replacement: replacement,
                         };
                     } else {
@@ -247,15 +813,12 @@ impl DedElfOps {
                             op_mode: parse_sec_mod_ops(field)?,
                             sec_name: None,
                             sec_idx: Some(check.unwrap()),
+                            location: None,
                             replacement: replacement,
                         };
                     }
 
-                    ModModeOps {
-                        exec: None,
-                        sec: Some(cfg),
-                        seg: None,
-                    }
+                    ModCfg::Sec(cfg)
                 } else {
                     return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                    "Invalid mod mode options provided"))
@@ -264,32 +827,145 @@ impl DedElfOps {
             ModOps::SEGMENT => {
                 if let Some(placement) = placement {
 
+                    //a symbol location selects the PT_LOAD segment containing it
+                    if let Some(location) = parse_symbol_location(&placement) {
+                        return Ok(ModCfg::Seg(SegCfg {
+                            op_mode: parse_seg_mod_ops(field)?,
+                            seg_idx: 0,
+                            location: Some(location),
+                            replacement: replacement,
+                        }))
+                    }
                     let trimmed = placement.trim_start_matches("0x");
                     let check = usize::from_str_radix(trimmed, 16);
                     if check.is_err() {
                         return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                        "Invalid mod mode options provided"))
                     }
-                    ModModeOps {
-                        exec: None,
-                        sec: None,
-                        seg: Some(SegCfg {
-                            op_mode: parse_seg_mod_ops(field)?,
-                            seg_idx: check.unwrap(),
-                            replacement: replacement,
-                        }),
-                    }
+                    ModCfg::Seg(SegCfg {
+                        op_mode: parse_seg_mod_ops(field)?,
+                        seg_idx: check.unwrap(),
+                        location: None,
+                        replacement: replacement,
+                    })
                 } else {
                     return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                    "Invalid mod mode options provided"))
                 }
             }
+            ModOps::RELOC => {
+                let placement = match placement {
+                    Some(placement) => placement,
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "Invalid mod mode options provided: \
+                                                           relocation modifications require a \
+                                                           relocation section position")),
+                };
+                let (sec, entry) = match placement.find(':') {
+                    Some(pos) => (placement[..pos].to_string(),
+                                  Some(placement[pos + 1..].to_string())),
+                    None => (placement.clone(), None),
+                };
+
+                let (sec_name, sec_idx) =
+                    match usize::from_str_radix(sec.trim_start_matches("0x"), 16) {
+                        Ok(idx) => (None, Some(idx)),
+                        Err(_) => (Some(sec), None),
+                    };
+                //as for symbols, an index needs a 0x prefix unless it is all digits, since
+                // symbol names such as `add` are valid hex
+                let entry = match entry {
+                    Some(entry) => {
+                        let is_index = entry.starts_with("0x") ||
+                            entry.chars().all(|c| c.is_ascii_digit());
+                        match usize::from_str_radix(entry.trim_start_matches("0x"), 16) {
+                            Ok(idx) if is_index => Some(RelocEntry::Index(idx)),
+                            _ => Some(RelocEntry::Symbol(entry)),
+                        }
+                    }
+                    None => None,
+                };
+
+                let op_mode = parse_reloc_mod_ops(field)?;
+                match (op_mode, &entry) {
+                    (RelocModOps::ADD, _) | (_, Some(_)) => (),
+                    _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                        "Invalid mod mode options provided: \
+                                                        relocation modifications other than \
+                                                        `add` require an entry index or symbol")),
+                }
+                ModCfg::Reloc(RelocCfg {
+                    op_mode: op_mode,
+                    sec_name: sec_name,
+                    sec_idx: sec_idx,
+                    entry: entry,
+                    replacement: replacement,
+                })
+            }
+            ModOps::SYMBOL => {
+                let placement = match placement {
+                    Some(placement) => placement,
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "Invalid mod mode options provided: \
+                                                           symbol modifications require a \
+                                                           symbol position")),
+                };
+                let op_mode = parse_sym_mod_ops(field)?;
+                //new symbols are added to a table, so the position is just the table
+                let (table, entry) = match placement.find(':') {
+                    Some(pos) => (Some(placement[..pos].to_string()),
+                                  placement[pos + 1..].to_string()),
+                    None if op_mode == SymModOps::ADD => (Some(placement.clone()), String::new()),
+                    None => (None, placement.clone()),
+                };
+
+                let (sec_name, sec_idx) = match table {
+                    Some(table) => match usize::from_str_radix(table.trim_start_matches("0x"), 16) {
+                        Ok(idx) => (None, Some(idx)),
+                        Err(_) => (Some(table), None),
+                    },
+                    None => (None, None),
+                };
+                //symbol names such as `f3` are valid hex, so an index needs a 0x prefix unless
+                // it is all digits
+                let is_index = entry.starts_with("0x") || entry.chars().all(|c| c.is_ascii_digit());
+                let entry = match usize::from_str_radix(entry.trim_start_matches("0x"), 16) {
+                    _ if op_mode == SymModOps::ADD => None,
+                    Ok(idx) if is_index => Some(SymEntry::Index(idx)),
+                    _ => Some(SymEntry::Name(entry)),
+                };
+
+                ModCfg::Sym(SymCfg {
+                    op_mode: op_mode,
+                    sec_name: sec_name,
+                    sec_idx: sec_idx,
+                    entry: entry,
+                    replacement: replacement,
+                })
+            }
+            ModOps::DYNAMIC => {
+                let op_mode = parse_dyn_mod_ops(field)?;
+                if op_mode == DynModOps::REPLACE_NEEDED && !replacement.contains(':') {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid mod mode options provided: \
+                                                   replace_needed requires `<old>:<new>`"))
+                }
+                ModCfg::Dyn(DynCfg {
+                    op_mode: op_mode,
+                    replacement: replacement,
+                })
+            }
         };
 
-        Ok(DedElfOps {
-            injection: None,
-            modify: Some(mod_ops),
-        })
+        Ok(mod_cfg)
+    }
+
+    /* Only list information about the input file, without writing an output file */
+    pub fn list_only(&self) -> bool {
+        match &self.injection {
+            Some(inj) => inj.list_caves,
+            None => false,
+        }
     }
 
     pub fn get_inj_file(&self) -> Option<String> {
@@ -304,7 +980,7 @@ impl DedElfOps {
 pub enum Mode {
     MODIFY,
     INJECT,
-    DUAL, //NOTE: not yet supported
+    DUAL, //NOTE: only supported in config file mode
 }
 
 impl FromStr for Mode {
@@ -313,13 +989,42 @@ impl FromStr for Mode {
         match mode {
             "modify" => Ok(Mode::MODIFY),
             "inject" => Ok(Mode::INJECT),
+            "dual" => Ok(Mode::DUAL),
             _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                          "Invalid mode option; use either `inject` or `modify`"))
+                                          "Invalid mode option; use either `inject` or `modify` \
+                                          (or `dual` in a config file)"))
         }
     }
 }
 
 
+/* Injection strategies, see README for details */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InjStrategy {
+    EXTEND,
+    CAVE,
+    NOTE,
+    PADDING,
+    REVERSE,
+}
+
+impl FromStr for InjStrategy {
+    type Err = std::io::Error;
+    fn from_str(strategy: &str)->Result<InjStrategy,std::io::Error>{
+        match strategy {
+            "extend" => Ok(InjStrategy::EXTEND),
+            "cave" => Ok(InjStrategy::CAVE),
+            "note" => Ok(InjStrategy::NOTE),
+            "padding" => Ok(InjStrategy::PADDING),
+            "reverse" => Ok(InjStrategy::REVERSE),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Invalid injection strategy provided, use \
+                                                one of the following: `extend`, `cave`, `note`, `padding`, \
+                                                `reverse`"))
+        }
+    }
+}
+
 impl FromStr for ModOps {
     type Err = std::io::Error;
     fn from_str(mode: &str)->Result<ModOps,std::io::Error>{
@@ -327,13 +1032,17 @@ impl FromStr for ModOps {
             "exec_header" => Ok(ModOps::EXEC),
             "sec_header" => Ok(ModOps::SECTION),
             "prog_header" => Ok(ModOps::SEGMENT),
+            "relocation" => Ok(ModOps::RELOC),
+            "dynamic" => Ok(ModOps::DYNAMIC),
+            "symbol" => Ok(ModOps::SYMBOL),
 
 //            "new_seg" => Ok(Mode::INJECT),
 //            "new_sec" => Ok(Mode::MODIFY),
             _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                                 "Invalid modify option provided, use \
                                                 one of the following: `exec_header`, \
-                                                `sec_header`, `prog_header"))
+                                                `sec_header`, `prog_header`, `relocation`, \
+                                                `dynamic`, `symbol`"))
         }
     }
 }
@@ -343,9 +1052,13 @@ pub struct InjModeOps {
     file: String,
     size: usize,
     extend: Option<String>,
-    b_offset: Option<u64>,
+    b_offset: Option<Location>,
     replace: bool,
-    new_entry: Option<u64>,
+    new_entry: Option<Location>,
+    strategy: InjStrategy,
+    list_caves: bool,
+    trampoline: bool,
+    thumb: bool,
 }
 
 impl InjModeOps {
@@ -358,6 +1071,10 @@ impl InjModeOps {
             new_entry: INJ_DEFAULT_ENTRY,
             b_offset: INJ_DEFAULT_OFFSET,
             replace: INJ_DEFAULT_REPLACE,
+            strategy: INJ_DEFAULT_STRATEGY,
+            list_caves: false,
+            trampoline: INJ_DEFAULT_TRAMPOLINE,
+            thumb: false,
         }
     }
 
@@ -373,17 +1090,61 @@ impl InjModeOps {
         self.file.clone()
     }
 
+    /* The new entry point, once resolved if it was given relative to a symbol */
     pub fn get_entry(&self) -> Option<u64> {
-        self.new_entry
+        match self.new_entry {
+            Some(Location::Value(entry)) => Some(entry),
+            _ => None,
+        }
     }
 
+    /* The injection byte offset, once resolved if it was given relative to a symbol */
     pub fn get_offset(&self)->Option<u64>{
-        self.b_offset
+        match self.b_offset {
+            Some(Location::Value(offset)) => Some(offset),
+            _ => None,
+        }
+    }
+
+    pub fn get_entry_location(&self) -> Option<Location> {
+        self.new_entry.clone()
+    }
+
+    pub fn get_offset_location(&self) -> Option<Location> {
+        self.b_offset.clone()
+    }
+
+    pub fn set_entry(&mut self, entry: u64) {
+        self.new_entry = Some(Location::Value(entry));
+    }
+
+    pub fn set_offset(&mut self, offset: u64) {
+        self.b_offset = Some(Location::Value(offset));
+    }
+
+    pub fn set_extend(&mut self, section: String) {
+        self.extend = Some(section);
     }
 
     pub fn get_replace(&self)->bool{
         self.replace
     }
+
+    pub fn get_strategy(&self)->InjStrategy{
+        self.strategy
+    }
+
+    pub fn get_list_caves(&self)->bool{
+        self.list_caves
+    }
+
+    pub fn get_trampoline(&self)->bool{
+        self.trampoline
+    }
+
+    pub fn get_thumb(&self)->bool{
+        self.thumb
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -394,15 +1155,27 @@ pub enum ModOps {
     SEGMENT,
     //NEW_SEC,
     //NEW_SEG,
-    // SYMBOL,
-    // RELOC,
+    SYMBOL,
+    RELOC,
+    DYNAMIC,
 }
 
+/* Modifications are applied in the order they were provided */
 #[derive(Clone, Debug)]
 pub struct ModModeOps {
-    pub exec: Option<ExecCfg>,
-    pub sec: Option<SecCfg>,
-    pub seg: Option<SegCfg>,
+    pub mods: Vec<ModCfg>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ModCfg {
+    Exec(ExecCfg),
+    Sec(SecCfg),
+    Seg(SegCfg),
+    NewSec(NewSecCfg),
+    NewSeg(NewSegCfg),
+    Reloc(RelocCfg),
+    Sym(SymCfg),
+    Dyn(DynCfg),
 }
 
 #[derive(Clone, Debug)]
@@ -411,21 +1184,160 @@ pub struct ExecCfg {
     pub replacement: String,
 }
 
+/*
+* Options for modifying a section header, selected by name or index, or as the section
+* containing a `<symbol>+0x<off>` location
+*/
 #[derive(Clone, Debug)]
 pub struct SecCfg {
     pub op_mode: SecModOps,
     pub sec_name: Option<String>,
     pub sec_idx: Option<usize>,
+    pub location: Option<Location>,
     pub replacement: String,
 }
 
+/*
+* Options for modifying a program header, selected by index, or as the PT_LOAD segment
+* containing a `<symbol>+0x<off>` location
+*/
 #[derive(Clone, Debug)]
 pub struct SegCfg {
     pub op_mode: SegModOps,
     pub seg_idx: usize,
+    pub location: Option<Location>,
+    pub replacement: String,
+}
+
+/*
+* Options for modifying a relocation table. The entry selects either a single entry by index
+* or every entry referring to a symbol, and is not needed when adding a new entry.
+*/
+#[derive(Clone, Debug)]
+pub struct RelocCfg {
+    pub op_mode: RelocModOps,
+    pub sec_name: Option<String>,
+    pub sec_idx: Option<usize>,
+    pub entry: Option<RelocEntry>,
+    pub replacement: String,
+}
+
+/*
+* Options for modifying a symbol table entry. The table (.symtab or .dynsym, by name or index)
+* is optional: an entry given by name then selects every matching symbol of both tables, and
+* an entry given by index refers to .symtab, or to .dynsym if there is no .symtab. Adding a
+* new symbol requires the table and takes no entry.
+*/
+#[derive(Clone, Debug)]
+pub struct SymCfg {
+    pub op_mode: SymModOps,
+    pub sec_name: Option<String>,
+    pub sec_idx: Option<usize>,
+    pub entry: Option<SymEntry>,
     pub replacement: String,
 }
 
+/*
+* Options for modifying the .dynamic section. The replacement is the library name (or
+* `<old>:<new>` when replacing a needed library), the new RPATH/RUNPATH/SONAME string or
+* the new interpreter path or `|` separated DT_FLAGS/DT_FLAGS_1 names, and is not used when
+* removing or converting a string entry.
+*/
+#[derive(Clone, Debug)]
+pub struct DynCfg {
+    pub op_mode: DynModOps,
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum RelocEntry {
+    Index(usize),
+    Symbol(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum SymEntry {
+    Index(usize),
+    Name(String),
+}
+
+/*
+* An address or file offset given either as a hex value or relative to a symbol, as
+* `<symbol>+0x<off>` (or `<symbol>-0x<off>`). Symbols are resolved once the ELF is parsed, and
+* so is a bare name that is also a valid hex value (e.g. `add`), which may be either.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum Location {
+    Value(u64),
+    Symbol(String, i64),
+    HexOrSymbol(u64, String),
+}
+
+/* Parse an explicit `<symbol>+0x<off>` or `<symbol>-0x<off>` location */
+pub fn parse_symbol_location(val: &str) -> Option<Location> {
+    let pos = val.rfind(['+', '-'])?;
+    if pos == 0 {
+        return None
+    }
+    let off = i64::from_str_radix(val[pos + 1..].trim_start_matches("0x"), 16).ok()?;
+    let off = if val[pos..].starts_with('-') { -off } else { off };
+    Some(Location::Symbol(val[..pos].to_string(), off))
+}
+
+/*
+* Parse a hex value or a symbol location. A 0x prefixed value is always a value, and a bare name
+* that is also a valid hex value is left to be resolved against the symbols.
+*/
+pub fn parse_location(val: &str) -> Option<Location> {
+    if let Some(hex) = val.strip_prefix("0x") {
+        return u64::from_str_radix(hex, 16).ok().map(Location::Value)
+    }
+    if let Some(location) = parse_symbol_location(val) {
+        return Some(location)
+    }
+    if val.is_empty() || val.contains(char::is_whitespace) {
+        return None
+    }
+    match u64::from_str_radix(val, 16) {
+        Ok(hex) => Some(Location::HexOrSymbol(hex, val.to_string())),
+        Err(_) => Some(Location::Symbol(val.to_string(), 0)),
+    }
+}
+
+/*
+* Options for adding a new section (config file mode only). The contents are read from
+* file (if provided) and zero padded up to sh_size (if provided). sh_name, sh_offset and
+* sh_addr are computed when the section is added.
+*/
+#[derive(Clone, Debug)]
+pub struct NewSecCfg {
+    pub name: String,
+    pub file: Option<String>,
+    pub seg_idx: Option<usize>,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_size: Option<u64>,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+/*
+* Options for adding a new segment (config file mode only). The contents are read from file
+* (if provided). p_offset and p_filesz are computed when the segment is added, and p_vaddr is
+* chosen automatically if not provided.
+*/
+#[derive(Clone, Debug)]
+pub struct NewSegCfg {
+    pub file: Option<String>,
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_vaddr: Option<u64>,
+    pub p_memsz: Option<u64>,
+    pub p_align: u64,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum ExecModOps {
     IDENT,
@@ -531,6 +1443,7 @@ pub enum SecModOps {
     INFO,
     ADDRALIGN,
     ENTSIZE,
+    RENAME,
 }
 
 pub fn parse_sec_mod_ops(option: String) -> Result<SecModOps, std::io::Error> {
@@ -545,6 +1458,7 @@ pub fn parse_sec_mod_ops(option: String) -> Result<SecModOps, std::io::Error> {
         "sh_info" => Ok(SecModOps::INFO),
         "sh_addralign" => Ok(SecModOps::ADDRALIGN),
         "sh_entsize" => Ok(SecModOps::ENTSIZE),
+        "rename" => Ok(SecModOps::RENAME),
         _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
                                             "Invalid modify option provided for section header fields"))
     }
@@ -582,6 +1496,9 @@ pub fn get_sec_field(option: SecModOps) -> String {
         SecModOps::ENTSIZE => {
             "sh_entsize".to_string()
         }
+        SecModOps::RENAME => {
+            "rename".to_string()
+        }
     }
 }
 
@@ -642,3 +1559,164 @@ pub fn get_seg_field(option: SegModOps) -> String {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RelocModOps {
+    OFFSET,
+    INFO,
+    SYM,
+    TYPE,
+    ADDEND,
+    ADD,
+    DELETE,
+}
+
+pub fn parse_reloc_mod_ops(option: String) -> Result<RelocModOps, std::io::Error> {
+    match option.as_str() {
+        "r_offset" => Ok(RelocModOps::OFFSET),
+        "r_info" => Ok(RelocModOps::INFO),
+        "r_sym" => Ok(RelocModOps::SYM),
+        "r_type" => Ok(RelocModOps::TYPE),
+        "r_addend" => Ok(RelocModOps::ADDEND),
+        "add" => Ok(RelocModOps::ADD),
+        "delete" => Ok(RelocModOps::DELETE),
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid modify option provided for relocation fields"))
+    }
+}
+
+pub fn get_reloc_field(option: RelocModOps) -> String {
+    match option {
+        RelocModOps::OFFSET => {
+            "r_offset".to_string()
+        }
+        RelocModOps::INFO => {
+            "r_info".to_string()
+        }
+        RelocModOps::SYM => {
+            "r_sym".to_string()
+        }
+        RelocModOps::TYPE => {
+            "r_type".to_string()
+        }
+        RelocModOps::ADDEND => {
+            "r_addend".to_string()
+        }
+        RelocModOps::ADD => {
+            "add".to_string()
+        }
+        RelocModOps::DELETE => {
+            "delete".to_string()
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SymModOps {
+    NAME,
+    VALUE,
+    SIZE,
+    INFO,
+    BIND,
+    TYPE,
+    OTHER,
+    VISIBILITY,
+    SHNDX,
+    ADD,
+    RENAME,
+}
+
+pub fn parse_sym_mod_ops(option: String) -> Result<SymModOps, std::io::Error> {
+    match option.as_str() {
+        "st_name" => Ok(SymModOps::NAME),
+        "st_value" => Ok(SymModOps::VALUE),
+        "st_size" => Ok(SymModOps::SIZE),
+        "st_info" => Ok(SymModOps::INFO),
+        "st_bind" => Ok(SymModOps::BIND),
+        "st_type" => Ok(SymModOps::TYPE),
+        "st_other" => Ok(SymModOps::OTHER),
+        "st_visibility" => Ok(SymModOps::VISIBILITY),
+        "st_shndx" => Ok(SymModOps::SHNDX),
+        "add" => Ok(SymModOps::ADD),
+        "rename" => Ok(SymModOps::RENAME),
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid modify option provided for symbol fields"))
+    }
+}
+
+pub fn get_sym_field(option: SymModOps) -> String {
+    match option {
+        SymModOps::NAME => {
+            "st_name".to_string()
+        }
+        SymModOps::VALUE => {
+            "st_value".to_string()
+        }
+        SymModOps::SIZE => {
+            "st_size".to_string()
+        }
+        SymModOps::INFO => {
+            "st_info".to_string()
+        }
+        SymModOps::BIND => {
+            "st_bind".to_string()
+        }
+        SymModOps::TYPE => {
+            "st_type".to_string()
+        }
+        SymModOps::OTHER => {
+            "st_other".to_string()
+        }
+        SymModOps::VISIBILITY => {
+            "st_visibility".to_string()
+        }
+        SymModOps::SHNDX => {
+            "st_shndx".to_string()
+        }
+        SymModOps::ADD => {
+            "add".to_string()
+        }
+        SymModOps::RENAME => {
+            "rename".to_string()
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum DynModOps {
+    ADD_NEEDED,
+    REMOVE_NEEDED,
+    REPLACE_NEEDED,
+    SET_RPATH,
+    SET_RUNPATH,
+    SET_SONAME,
+    REMOVE_RPATH,
+    REMOVE_RUNPATH,
+    REMOVE_SONAME,
+    CONVERT_RPATH,
+    CONVERT_RUNPATH,
+    SET_INTERPRETER,
+    SET_FLAGS,
+    CLEAR_FLAGS,
+}
+
+pub fn parse_dyn_mod_ops(option: String) -> Result<DynModOps, std::io::Error> {
+    match option.as_str() {
+        "add_needed" => Ok(DynModOps::ADD_NEEDED),
+        "remove_needed" => Ok(DynModOps::REMOVE_NEEDED),
+        "replace_needed" => Ok(DynModOps::REPLACE_NEEDED),
+        "set_rpath" => Ok(DynModOps::SET_RPATH),
+        "set_runpath" => Ok(DynModOps::SET_RUNPATH),
+        "set_soname" => Ok(DynModOps::SET_SONAME),
+        "remove_rpath" => Ok(DynModOps::REMOVE_RPATH),
+        "remove_runpath" => Ok(DynModOps::REMOVE_RUNPATH),
+        "remove_soname" => Ok(DynModOps::REMOVE_SONAME),
+        "convert_rpath" => Ok(DynModOps::CONVERT_RPATH),
+        "convert_runpath" => Ok(DynModOps::CONVERT_RUNPATH),
+        "set_interpreter" => Ok(DynModOps::SET_INTERPRETER),
+        "set_flags" => Ok(DynModOps::SET_FLAGS),
+        "clear_flags" => Ok(DynModOps::CLEAR_FLAGS),
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid modify option provided for dynamic fields"))
+    }
+}
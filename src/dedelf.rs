@@ -1,7 +1,14 @@
 //use crate::bytes;
 use crate::parser;
-use crate::parser::ElfParser;
+use crate::parser::{ElfParser, InjSite};
+use crate::trampoline::{Arch, Trampoline};
+use crate::linker;
+use crate::linker::Linker;
 use crate::config;
+use crate::relocations;
+use crate::dynamic;
+use crate::symbols;
+use crate::dynamic::DynTag;
 use crate::header;
 use crate::header::*;
 
@@ -9,6 +16,7 @@ use crate::section::*;
 use crate::segment::*;
 
 use std::fs::File;
+use std::path::Path;
 use std::error::Error;
 
 use intervaltree;
@@ -59,287 +67,1052 @@ impl DedElf for Elf {
         Ok(())
     }
 
+    /*
+    * Inject the injection file using the requested strategy. If requested, the injected bytes
+    * are wrapped in a trampoline that returns to the original entry point, and the entry point
+    * is moved to the trampoline; otherwise it is only changed to a provided new entry point.
+    * Relocatable objects are linked first, and called by the trampoline rather than fallen
+    * through. Since the trampoline and the linked image depend on the final
+    * vaddr of the injected bytes, a placeholder of their size is injected first and overwritten
+    * afterwards.
+    */
     fn inject(&mut self) -> Result<(), std::io::Error> {
-        if let Some(inj) = &self.ops.injection {
-            let mut offset: u64 = 0;
-            let mut sec_size = None;
-            let mut tree_segs = vec![];
-            let replace = inj.get_replace();
-            /*generate interval tree using segment bytes as bounds*/
-            for i in 0..self.parser.segments.len() {
-                let _left = self.parser.segments[i].offset();
-
-                let left: u64 = match _left {
-                    PHTOffset::ThirtyTwo(left) => { left as u64 }
-                    PHTOffset::SixtyFour(left) => { left }
-                };
+        if let Some(mut inj) = self.ops.injection.clone() {
+            if inj.get_list_caves() {
+                return self.list_caves(&inj);
+            }
+            self.resolve_inj_locations(&mut inj)?;
 
-                let right = left + self.parser.segments[i].file_size() as u64;
-                tree_segs.push((left..right, i as u64));
+            let linker = load_inj_object(&inj.get_file())?;
+            let inj_bytes = match &linker {
+                Some(_) => vec![],
+                None => read_inj_payload(&inj.get_file())?,
+            };
+            let trampoline = match inj.get_trampoline() {
+                true => Some(Trampoline::new(&self.parser.header, inj.get_thumb())?),
+                false => None,
+            };
+            let bytes = match (&linker, &trampoline) {
+                (Some(linker), Some(trampoline)) => {
+                    vec![0; trampoline.call_stub_size() + linker.padded_size() as usize]
+                }
+                (Some(linker), None) => vec![0; linker.padded_size() as usize],
+                (None, Some(trampoline)) => vec![0; trampoline.wrapped_size(inj_bytes.len())],
+                (None, None) => inj_bytes.clone(),
+            };
+            let placeholder_size = bytes.len();
+            //only the extend strategy is bounded by the requested size
+            if inj.get_strategy() == config::InjStrategy::EXTEND && placeholder_size > inj.get_size() {
+                let what = if trampoline.is_some() { " including the trampoline" } else { "" };
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("Injection needs {:#x} bytes{}, more than the \
+                                                       injection size of {:#x}: use -s {:#x} or larger",
+                                                       placeholder_size, what, inj.get_size(),
+                                                       parser::align_up(placeholder_size as u64, 0x1000))))
             }
 
-            let seg_tree: intervaltree::IntervalTree<u64, u64> = tree_segs.iter().cloned().collect();
-            let size = inj.get_size();
-            let entry = inj.get_entry();
-            let mut section: String = " ".to_string();
+            let site = match inj.get_strategy() {
+                config::InjStrategy::EXTEND => self.inject_extend(&inj, bytes)?,
+                config::InjStrategy::CAVE => self.inject_cave(&inj, bytes)?,
+                config::InjStrategy::NOTE => self.inject_note(&inj, bytes)?,
+                config::InjStrategy::PADDING |
+                config::InjStrategy::REVERSE => self.inject_text(&inj, bytes)?,
+            };
 
-            if let Some(_section) = inj.get_extend() {
-                let off = self.parser.get_section_offset_by_name(_section.as_str());
+            let orig_entry = match self.parser.header.entry() {
+                Entry::ThirtyTwo(entry) => entry as u64,
+                Entry::SixtyFour(entry) => entry,
+            };
+            if let Some(linker) = linker {
+                let stub_size = trampoline.as_ref().map_or(0, |t| t.call_stub_size() as u64);
+                let image_vaddr = parser::align_up(site.vaddr + stub_size, linker.align);
+                let mut linked = match &trampoline {
+                    Some(trampoline) => {
+                        trampoline.call_stub(site.vaddr, image_vaddr + linker.entry, orig_entry)?
+                    }
+                    None => vec![],
+                };
+                linked.resize((image_vaddr - site.vaddr) as usize, 0);
+                linked.extend(linker.link(image_vaddr, &self.parser)?);
+                linked.resize(placeholder_size, 0);
+                self.parser.overwrite_segment_bytes(site.seg_idx, site.offset, &linked)?;
+                println!("DEDelf: linked {:#x} payload bytes at {:#x}, entered at {:#x}",
+                         linker.size, image_vaddr, image_vaddr + linker.entry);
+            } else if let Some(trampoline) = &trampoline {
+                let wrapped = trampoline.wrap(&inj_bytes, site.vaddr, orig_entry)?;
+                self.parser.overwrite_segment_bytes(site.seg_idx, site.offset, &wrapped)?;
+            }
+            if trampoline.is_some() {
+                println!("DEDelf: added trampoline to original entry point {:#x}", orig_entry);
+            }
 
-                if off.is_none() {
-                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                   "Invalid Extend Section Entered"));
-                }
+            //the trampoline starts at the injection site, wherever the strategy placed it
+            let entry = match &trampoline {
+                Some(trampoline) if trampoline.arch == Arch::THUMB => Some(site.vaddr | 1),
+                Some(_) => Some(site.vaddr),
+                None => inj.get_entry(),
+            };
+            if let Some(entry) = entry {
+                self.parser.header.update_exec_header("e_entry".to_string(), entry, None)?;
+                println!("DEDelf: entry point set to {:#x}", entry);
+            }
+            Ok(())
+        } else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid Config Options"));
+        }
+    }
 
-                offset = *off.unwrap() as u64;
-                //let mut sec_size = 0;
-                section = _section;
-                for i in 0..self.parser.sections.len() {
-                    //TODO resolve this
-                    if self.parser.sections[i].name() == section {
-                        sec_size = Some(self.parser.sections[i].size() as usize);
-                        break;
-                    }
-                }
-            } else if let Some(_offset) = inj.get_offset() {
-                offset = _offset;
-                let mut tree_secs = vec![];
-
-                /*generate interval tree using section bytes as bounds*/
-                for i in 0..self.parser.sections.len() {
-                    let left = self.parser.sections[i].offset();
-                    let right = left + self.parser.sections[i].size() as u64;
-                    tree_secs.push((left..right, i as u64));
+    /* Apply each requested modification in order to the same parsed ELF */
+    fn modify(&mut self) -> Result<(), std::io::Error> {
+        if let Some(modify) = &self.ops.modify {
+            let mods = modify.mods.clone();
+            if mods.is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid Config Options"));
+            }
+            for cfg in &mods {
+                match cfg {
+                    config::ModCfg::Exec(exec) => self.modify_exec(exec)?,
+                    config::ModCfg::Sec(sec) => self.modify_sec(sec)?,
+                    config::ModCfg::Seg(seg) => self.modify_seg(seg)?,
+                    config::ModCfg::NewSec(sec) => self.add_new_section(sec)?,
+                    config::ModCfg::Reloc(rel) => self.modify_reloc(rel)?,
+                    config::ModCfg::NewSeg(seg) => self.add_new_segment(seg)?,
+                    config::ModCfg::Sym(sym) => self.modify_symbol(sym)?,
+                    config::ModCfg::Dyn(dynamic) => self.modify_dynamic(dynamic)?,
                 }
+            }
+            return Ok(());
+        } else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid Config Options"));
+        }
+    }
 
-                let sec_tree: intervaltree::IntervalTree<u64, u64> =
-                    tree_secs.iter().cloned().collect();
 
-                /*return the section index that contains the specified byte offset*/
-                let sec_point: Vec<u64> = sec_tree
-                    .query_point(offset as u64)
-                    .map(|x| x.value)
-                    .collect();
+    fn write(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
+        self.parser.write_sections(file_ptr)?;
+        self.parser.write_segments(file_ptr)?;
+        self.parser.write_header_tables(file_ptr)?;
+        self.parser.write_exec_header(file_ptr)?;
+        Ok(())
+    }
+
+}
+
+impl Elf {
+    /*
+    * Inject by growing the segment containing the injection site by the injection size, and
+    * shifting every later segment and section
+    */
+    fn inject_extend(&mut self, inj: &config::InjModeOps,
+                     inj_bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        let mut offset: u64 = 0;
+        let mut sec_size = None;
+        let mut tree_segs = vec![];
+        let replace = inj.get_replace();
+        /*generate interval tree using segment bytes as bounds*/
+        for i in 0..self.parser.segments.len() {
+            let _left = self.parser.segments[i].offset();
+
+            let left: u64 = match _left {
+                PHTOffset::ThirtyTwo(left) => { left as u64 }
+                PHTOffset::SixtyFour(left) => { left }
+            };
+
+            let right = left + self.parser.segments[i].file_size() as u64;
+            tree_segs.push((left..right, i as u64));
+        }
+
+        let seg_tree: intervaltree::IntervalTree<u64, u64> = tree_segs.iter().cloned().collect();
+        let size = inj.get_size();
+        let mut section: String = " ".to_string();
+
+        if let Some(_section) = inj.get_extend() {
+            let off = self.parser.get_section_offset_by_name(_section.as_str());
+
+            if off.is_none() {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid Extend Section Entered"));
+            }
+
+            offset = *off.unwrap() as u64;
+            //let mut sec_size = 0;
+            section = _section;
+            for i in 0..self.parser.sections.len() {
+                //TODO resolve this
+                if self.parser.sections[i].name() == section {
+                    sec_size = Some(self.parser.sections[i].size() as usize);
+                    break;
+                }
+            }
+        } else if let Some(_offset) = inj.get_offset() {
+            offset = _offset;
+            let mut tree_secs = vec![];
 
-                section = self.parser.sections[sec_point[0] as usize].name();
+            /*generate interval tree using section bytes as bounds*/
+            for i in 0..self.parser.sections.len() {
+                let left = self.parser.sections[i].offset();
+                let right = left + self.parser.sections[i].size() as u64;
+                tree_secs.push((left..right, i as u64));
             }
 
-            let point: Vec<u64> = seg_tree
+            let sec_tree: intervaltree::IntervalTree<u64, u64> =
+                tree_secs.iter().cloned().collect();
+
+            /*return the section index that contains the specified byte offset*/
+            let sec_point: Vec<u64> = sec_tree
                 .query_point(offset as u64)
                 .map(|x| x.value)
                 .collect();
 
-            let inj_file = self.ops.get_inj_file();
-            if inj_file.is_none() {
-                return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                               "No file provided, exiting"));
-            }
+            section = self.parser.sections[sec_point[0] as usize].name();
+        }
+
+        let point: Vec<u64> = seg_tree
+            .query_point(offset as u64)
+            .map(|x| x.value)
+            .collect();
+
+        let seg_idx = point[0] as usize;
+        self.parser.modify_segment(seg_idx,
+                                   offset as u64,
+                                   sec_size, replace,
+                                   size,
+                                   inj_bytes)?;
 
-            let inj_file = inj_file.unwrap();
-            let mut fp = match File::open(inj_file.clone()) {
-                Err(why) => {
-                    println!("Could not open target injection file: {}: {}",
-                             inj_file, why.description());
-                    return Err(why);
+        self.parser.update_segment_offsets(point[0], size as u64);
+        self.parser.increase_sht_offset(size as u64)?;
+        self.parser.update_secheader_offsets(section, size as u64);
+
+        //the injected bytes replace the section, or follow it
+        let inj_offset = match sec_size {
+            Some(sec_size) if !replace => offset + sec_size as u64,
+            _ => offset,
+        };
+        let seg = &self.parser.segments[seg_idx];
+        Ok(InjSite {
+            seg_idx: seg_idx,
+            offset: inj_offset,
+            vaddr: seg.vaddr() + inj_offset - seg.file_offset(),
+        })
+    }
+
+    /*
+    * Inject by overwriting a code cave (see ElfParser::find_code_caves). If a byte offset is
+    * provided, the injection is placed at that offset, which must lie within a large enough
+    * cave; otherwise the first large enough cave is used. The file layout is unchanged.
+    */
+    fn inject_cave(&mut self, inj: &config::InjModeOps,
+                   inj_bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        let caves = self.parser.find_code_caves(inj_bytes.len());
+
+        let (cave, offset) = match inj.get_offset() {
+            Some(offset) => {
+                let cave = caves.iter().find(|cave| {
+                    cave.offset <= offset
+                        && offset + inj_bytes.len() as u64 <= cave.offset + cave.size
+                });
+                match cave {
+                    Some(cave) => (cave.clone(), offset),
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "No code cave large enough for the \
+                                                           injection at the provided byte offset")),
                 }
-                Ok(fp) => fp,
-            };
+            }
+            None => {
+                match caves.first() {
+                    Some(cave) => (cave.clone(), cave.offset),
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "No code cave large enough for the \
+                                                           injection found")),
+                }
+            }
+        };
 
+        self.parser.overwrite_segment_bytes(cave.seg_idx, offset, &inj_bytes)?;
+        let vaddr = cave.vaddr + (offset - cave.offset);
+        println!("DEDelf: injected {:#x} bytes into code cave at offset {:#x} (vaddr {:#x})",
+                 inj_bytes.len(), offset, vaddr);
 
-            let inj_bytes = parser::read_input(&mut fp)?;
-            let _inj_site = self.parser.modify_segment(point[0] as usize,
-                                                       offset as u64,
-                                                       sec_size, replace,
-                                                       size,
-                                                       inj_bytes.to_vec())?;
+        Ok(InjSite {
+            seg_idx: cave.seg_idx,
+            offset: offset,
+            vaddr: vaddr,
+        })
+    }
 
-            self.parser.update_segment_offsets(point[0], size as u64);
-            self.parser.increase_sht_offset(size as u64)?;
-            self.parser.update_secheader_offsets(section, size as u64);
-            if let Some(entry) = entry {
-                self.parser.header.update_exec_header("e_entry".to_string(),
-                                                      entry as u64,
-                                                      None)?;
+    /*
+    * Inject by converting a PT_NOTE segment into an executable PT_LOAD segment containing the
+    * injection bytes at the end of the file. A PT_NOTE program header index may be provided
+    * (in hex) as the injection position, otherwise the last PT_NOTE segment is used.
+    */
+    fn inject_note(&mut self, inj: &config::InjModeOps,
+                   inj_bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        if inj_bytes.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Injection file is empty"));
+        }
+
+        let note_idx = match inj.get_extend() {
+            Some(idx) => {
+                match usize::from_str_radix(idx.trim_start_matches("0x"), 16) {
+                    Ok(idx) => Some(idx),
+                    Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                             "Invalid Config Options: PT_NOTE \
+                                                             program header index must be a \
+                                                             valid hex value")),
+                }
             }
-            return Ok(());
-        } else {
+            None => None,
+        };
+
+        let flags = P_flag::PF_R as u32 | P_flag::PF_X as u32;
+        let idx = self.parser.convert_note_segment(note_idx, flags, inj_bytes)?;
+        let seg = &self.parser.segments[idx];
+        println!("DEDelf: converted PT_NOTE segment to PT_LOAD at program header index {:#x} \
+                 with offset {:#x} and vaddr {:#x}", idx, seg.file_offset(), seg.vaddr());
+
+        Ok(InjSite {
+            seg_idx: idx,
+            offset: seg.file_offset(),
+            vaddr: seg.vaddr(),
+        })
+    }
+
+    /*
+    * Inject into the text segment without shifting its contents in memory: either into the
+    * page padding after its end (padding), or by growing it downward by whole pages (reverse)
+    */
+    fn inject_text(&mut self, inj: &config::InjModeOps,
+                   inj_bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        if inj_bytes.is_empty() {
             return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                           "Invalid Config Options"));
+                                           "Injection file is empty"));
+        }
+
+        let site = match inj.get_strategy() {
+            config::InjStrategy::REVERSE => self.parser.inject_text_reverse(inj_bytes)?,
+            _ => self.parser.inject_text_padding(inj_bytes)?,
+        };
+        println!("DEDelf: injected bytes into the text segment at vaddr {:#x}", site.vaddr);
+        Ok(site)
+    }
+
+    /* List the code caves large enough for the injection file */
+    fn list_caves(&self, inj: &config::InjModeOps) -> Result<(), std::io::Error> {
+        let inj_size = match load_inj_object(&inj.get_file())? {
+            Some(linker) => linker.padded_size() as usize,
+            None => read_inj_payload(&inj.get_file())?.len(),
+        };
+        let caves = self.parser.find_code_caves(inj_size);
+        println!("DEDelf: found {} code caves of at least {:#x} bytes", caves.len(), inj_size);
+        for cave in &caves {
+            println!("  segment {:#x}: offset {:#x} vaddr {:#x} size {:#x}",
+                     cave.seg_idx, cave.offset, cave.vaddr, cave.size);
         }
+        Ok(())
     }
 
-    fn modify(&mut self) -> Result<(), std::io::Error> {
-        if let Some(modify) = &self.ops.modify {
-            if let Some(exec) = &modify.exec {
-                let replacement = exec.replacement.clone();
-                let field = config::get_exec_field(exec.op_mode);
-                let val: u64;// = 0;
-                match exec.op_mode {
-                    config::ExecModOps::IDENT => {
-                        let (val, offset) =
-                            match header::match_class_as_str(replacement.clone()) {
+    fn modify_exec(&mut self, exec: &config::ExecCfg) -> Result<(), std::io::Error> {
+        let replacement = exec.replacement.clone();
+        let field = config::get_exec_field(exec.op_mode);
+        let val: u64;// = 0;
+        match exec.op_mode {
+            config::ExecModOps::IDENT => {
+                let (val, offset) =
+                    match header::match_class_as_str(replacement.clone()) {
+                        Err(_) => {
+                            match header::match_data_as_str(replacement.clone()) {
                                 Err(_) => {
-                                    match header::match_data_as_str(replacement.clone()) {
-                                        Err(_) => {
-                                            match header::match_osabi_as_str(replacement) {
-                                                Err(err) => return Err(err),
-                                                Ok(val) => (val, header::EXEC::_EI_OSABI)
-                                            }
-                                        }
-                                        Ok(val) => (val, header::EXEC::_EI_DATA)
+                                    match header::match_osabi_as_str(replacement) {
+                                        Err(err) => return Err(err),
+                                        Ok(val) => (val, header::EXEC::_EI_OSABI)
                                     }
                                 }
-                                Ok(val) => (val, header::EXEC::_EI_CLASS)
-                            };
-                        self.parser.header.update_exec_header("e_ident".to_string(),
-                                                              val as u64,
-                                                              Some(offset))?;
-                        return Ok(());
-                    }
-                    config::ExecModOps::TYPE => {
-                        val = header::match_type_as_str(replacement)? as u64;
-                    }
-                    config::ExecModOps::MACH => {
-                        val = header::match_mach_as_str(replacement)? as u64;
-                    }
-                    config::ExecModOps::VERSION => {
-                        val = header::match_version_as_str(replacement)? as u64;
-                    }
-                    config::ExecModOps::ENTRY |
-                    config::ExecModOps::PHOFF |
-                    config::ExecModOps::SHOFF => {
-                        let trimmed = replacement.trim_start_matches("0x");
-
-                        let check = u64::from_str_radix(trimmed, 16);
-                        if check.is_err() {
-                            return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                           "Invalid Config Options: \
-                                                           entry or offset value is invalid \
-                                                           did you provide a valid \
-                                                           hex value?"));
-                        }
-                        val = check.unwrap();
-                    }
-                    config::ExecModOps::FLAGS |
-                    config::ExecModOps::EHSIZE |
-                    config::ExecModOps::PHENTSIZE |
-                    config::ExecModOps::PHNUM |
-                    config::ExecModOps::SHENTSIZE |
-                    config::ExecModOps::SHNUM |
-                    config::ExecModOps::SHSTRNDX
-
-                    => {
-                        let trimmed = replacement.trim_start_matches("0x");
-                        let check = u64::from_str_radix(trimmed, 16);
-                        if check.is_err() {
-                            return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                           "Invalid Config Options: \
-                                                           provided value is invalid \
-                                                           did you provide a valid \
-                                                           hex value?"));
+                                Ok(val) => (val, header::EXEC::_EI_DATA)
+                            }
                         }
-                        val = check.unwrap();
-                    }
-                };
-                self.parser.header.update_exec_header(field, val as u64, None)?;
+                        Ok(val) => (val, header::EXEC::_EI_CLASS)
+                    };
+                self.parser.header.update_exec_header("e_ident".to_string(),
+                                                      val as u64,
+                                                      Some(offset))?;
                 return Ok(());
-            } else if let Some(sec) = &modify.sec {
-                let field = config::get_sec_field(sec.op_mode);
-                let replacement = sec.replacement.clone();
-                let val: u64 = match &sec.op_mode {
-                    config::SecModOps::TYPE => {
-                        match_sh_type_as_str(replacement)? as u64
-                    }
-                    config::SecModOps::FLAGS => {
-                        match_sh_flag_as_str(replacement)?
-                    }
-                    config::SecModOps::NAME |
-                    config::SecModOps::ADDR |
-                    config::SecModOps::OFFSET |
-                    config::SecModOps::SIZE |
-                    config::SecModOps::LINK |
-                    config::SecModOps::INFO |
-                    config::SecModOps::ADDRALIGN |
-                    config::SecModOps::ENTSIZE
-                    => {
-                        let trimmed = replacement.trim_start_matches("0x");
-                        let check = u64::from_str_radix(trimmed, 16);
-                        if check.is_err() {
-                            return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                           "Invalid Config Options: \
-                                                           provided sec header replacement value \
-                                                           is invalid \
-                                                           did you provide a valid \
-                                                           hex value?"));
-                        }
-                        check.unwrap()
-                    }
-                };
-                self.parser.update_sec_header(sec.sec_name.clone(),
-                                              sec.sec_idx,
-                                              field,
-                                              val)?;
-            } else if let Some(seg) = &modify.seg {
-                let field = config::get_seg_field(seg.op_mode);
-                let replacement = seg.replacement.clone();
-                let val: u64 = match &seg.op_mode {
-                    config::SegModOps::TYPE => {
-                        match_p_type_as_str(replacement)? as u64
-                    }
-                    config::SegModOps::FLAGS => {
-                        match_p_flag_as_str(replacement)? as u64
-                    }
-                    config::SegModOps::OFFSET |
-                    config::SegModOps::VADDR |
-                    config::SegModOps::PADDR |
-                    config::SegModOps::FILESZ |
-                    config::SegModOps::MEMSZ |
-                    config::SegModOps::ALIGN => {
-                        let trimmed = replacement.trim_start_matches("0x");
-                        let check = u64::from_str_radix(trimmed, 16);
-                        if check.is_err() {
-                            return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                           "Invalid Config Options: \
-                                                           provided sec header replacement value \
-                                                           is invalid \
-                                                           did you provide a valid \
-                                                           hex value?"));
-                        }
-                        check.unwrap()
-                    }
-                };
-                self.parser.update_seg_header(seg.seg_idx, field, val)?;
-            } else {
+            }
+            config::ExecModOps::TYPE => {
+                val = header::match_type_as_str(replacement)? as u64;
+            }
+            config::ExecModOps::MACH => {
+                val = header::match_mach_as_str(replacement)? as u64;
+            }
+            config::ExecModOps::VERSION => {
+                val = header::match_version_as_str(replacement)? as u64;
+            }
+            config::ExecModOps::ENTRY => {
+                //the entry point may also be given relative to a symbol
+                let location = config::parse_location(&replacement);
+                if location.is_none() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: \
+                                                   entry or offset value is invalid \
+                                                   did you provide a valid \
+                                                   hex value?"));
+                }
+                val = self.location_vaddr(&location.unwrap())?;
+            }
+            config::ExecModOps::PHOFF |
+            config::ExecModOps::SHOFF => {
+                let trimmed = replacement.trim_start_matches("0x");
+
+                let check = u64::from_str_radix(trimmed, 16);
+                if check.is_err() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: \
+                                                   entry or offset value is invalid \
+                                                   did you provide a valid \
+                                                   hex value?"));
+                }
+                val = check.unwrap();
+            }
+            config::ExecModOps::FLAGS |
+            config::ExecModOps::EHSIZE |
+            config::ExecModOps::PHENTSIZE |
+            config::ExecModOps::PHNUM |
+            config::ExecModOps::SHENTSIZE |
+            config::ExecModOps::SHNUM |
+            config::ExecModOps::SHSTRNDX
+
+            => {
+                let trimmed = replacement.trim_start_matches("0x");
+                let check = u64::from_str_radix(trimmed, 16);
+                if check.is_err() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: \
+                                                   provided value is invalid \
+                                                   did you provide a valid \
+                                                   hex value?"));
+                }
+                val = check.unwrap();
+            }
+        };
+        self.parser.header.update_exec_header(field, val as u64, None)?;
+        return Ok(());
+    }
+
+    fn modify_sec(&mut self, sec: &config::SecCfg) -> Result<(), std::io::Error> {
+        let (sec_name, sec_idx) = match &sec.location {
+            Some(location) => {
+                let vaddr = self.location_vaddr(location)?;
+                match self.parser.section_at(vaddr) {
+                    Some(idx) => (None, Some(idx)),
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           format!("No section contains {:#x}", vaddr))),
+                }
+            }
+            None => (sec.sec_name.clone(), sec.sec_idx),
+        };
+
+        if let config::SecModOps::RENAME = sec.op_mode {
+            let idx = match (&sec_name, sec_idx) {
+                (Some(name), _) => self.parser.sections.iter().position(|s| &s.name() == name),
+                (None, idx) => idx,
+            };
+            let idx = match idx {
+                Some(idx) => idx,
+                None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                       "Invalid section name in mod options provided")),
+            };
+            let old_name = self.parser.sections.get(idx).map(|s| s.name()).unwrap_or_default();
+            let new_name = sec.replacement.trim();
+            self.parser.rename_section(idx, new_name)?;
+            println!("DEDelf: renamed section {} to {}", old_name, new_name);
+            return Ok(());
+        }
+
+        let field = config::get_sec_field(sec.op_mode);
+        let replacement = sec.replacement.clone();
+        let val: u64 = match &sec.op_mode {
+            config::SecModOps::TYPE => {
+                match_sh_type_as_str(replacement)? as u64
+            }
+            config::SecModOps::FLAGS => {
+                match_sh_flag_as_str(replacement)?
+            }
+            config::SecModOps::NAME |
+            config::SecModOps::ADDR |
+            config::SecModOps::OFFSET |
+            config::SecModOps::SIZE |
+            config::SecModOps::LINK |
+            config::SecModOps::INFO |
+            config::SecModOps::ADDRALIGN |
+            config::SecModOps::ENTSIZE
+            => {
+                let trimmed = replacement.trim_start_matches("0x");
+                let check = u64::from_str_radix(trimmed, 16);
+                if check.is_err() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: \
+                                                   provided sec header replacement value \
+                                                   is invalid \
+                                                   did you provide a valid \
+                                                   hex value?"));
+                }
+                check.unwrap()
+            }
+            //handled above
+            config::SecModOps::RENAME => 0,
+        };
+        self.parser.update_sec_header(sec_name,
+                                      sec_idx,
+                                      field,
+                                      val)?;
+        Ok(())
+    }
+
+    fn add_new_section(&mut self, sec: &config::NewSecCfg) -> Result<(), std::io::Error> {
+        let mut bytes = match &sec.file {
+            Some(file) => read_contents_file(file)?,
+            None => Vec::new(),
+        };
+
+        if let Some(size) = sec.sh_size {
+            if (size as usize) < bytes.len() {
                 return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                               "Invalid Config Options"));
+                                               "Invalid Config Options: new section contents \
+                                               are larger than the provided sh_size"));
+            }
+            bytes.resize(size as usize, 0);
+        }
+
+        let idx = self.parser.add_section(sec.name.clone(), bytes, sec.sh_type, sec.sh_flags,
+                                          sec.sh_link, sec.sh_info, sec.sh_addralign,
+                                          sec.sh_entsize, sec.seg_idx)?;
+        println!("DEDelf: added section {} at section header index {:#x}", sec.name, idx);
+        Ok(())
+    }
+
+    fn add_new_segment(&mut self, seg: &config::NewSegCfg) -> Result<(), std::io::Error> {
+        let bytes = match &seg.file {
+            Some(file) => read_contents_file(file)?,
+            None => Vec::new(),
+        };
+
+        let idx = self.parser.add_segment(seg.p_type, seg.p_flags, seg.p_vaddr, seg.p_align,
+                                          seg.p_memsz, bytes)?;
+        println!("DEDelf: added segment at program header index {:#x} with vaddr {:#x}",
+                 idx, self.parser.segments[idx].vaddr());
+        Ok(())
+    }
+
+    fn modify_seg(&mut self, seg: &config::SegCfg) -> Result<(), std::io::Error> {
+        let field = config::get_seg_field(seg.op_mode);
+        let replacement = seg.replacement.clone();
+        let val: u64 = match &seg.op_mode {
+            config::SegModOps::TYPE => {
+                match_p_type_as_str(replacement)? as u64
+            }
+            config::SegModOps::FLAGS => {
+                match_p_flag_as_str(replacement)? as u64
             }
+            config::SegModOps::OFFSET |
+            config::SegModOps::VADDR |
+            config::SegModOps::PADDR |
+            config::SegModOps::FILESZ |
+            config::SegModOps::MEMSZ |
+            config::SegModOps::ALIGN => {
+                let trimmed = replacement.trim_start_matches("0x");
+                let check = u64::from_str_radix(trimmed, 16);
+                if check.is_err() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: \
+                                                   provided sec header replacement value \
+                                                   is invalid \
+                                                   did you provide a valid \
+                                                   hex value?"));
+                }
+                check.unwrap()
+            }
+        };
+        let seg_idx = match &seg.location {
+            Some(location) => {
+                let vaddr = self.location_vaddr(location)?;
+                match self.parser.segment_at(vaddr) {
+                    Some(idx) => idx,
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           format!("No PT_LOAD segment contains {:#x}",
+                                                                   vaddr))),
+                }
+            }
+            None => seg.seg_idx,
+        };
+        self.parser.update_seg_header(seg_idx, field, val)?;
+        Ok(())
+    }
+
+    /*
+    * Modify, add or delete relocation entries. Entries selected by symbol name are all
+    * modified (or deleted); an add replacement is `r_offset,r_type,r_sym[,r_addend]`
+    */
+    fn modify_reloc(&mut self, rel: &config::RelocCfg) -> Result<(), std::io::Error> {
+        let t = self.parser.rel_table_idx(rel.sec_name.clone(), rel.sec_idx)?;
+        let sec_name = self.parser.rel_tables[t].sec_name.clone();
+        let machine = self.parser.rel_tables[t].machine;
+
+        if rel.op_mode == config::RelocModOps::ADD {
+            let parts: Vec<&str> = rel.replacement.split(',').map(|part| part.trim()).collect();
+            if parts.len() < 3 || parts.len() > 4 {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid Config Options: new relocation entries \
+                                               must be given as r_offset,r_type,r_sym[,r_addend]"));
+            }
+            let offset = parse_hex(parts[0])?;
+            let rtype = relocations::match_reloc_type_as_str(machine, parts[1].to_string())?;
+            let sym_idx = self.reloc_sym_idx(t, parts[2])?;
+            let addend = match parts.get(3) {
+                Some(addend) => parse_signed_hex(addend)?,
+                None => 0,
+            };
+            let e = self.parser.add_relocation(t, offset, sym_idx, rtype, addend)?;
+            println!("DEDelf: added relocation entry {:#x} to {}", e, sec_name);
+            return Ok(());
+        }
+
+        let mut entries = match &rel.entry {
+            Some(config::RelocEntry::Index(e)) => vec![*e],
+            Some(config::RelocEntry::Symbol(name)) => {
+                let entries = self.parser.relocations_for_symbol(t, name);
+                if entries.is_empty() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("Invalid Config Options: no entries \
+                                                           of {} refer to symbol {}",
+                                                           sec_name, name)));
+                }
+                entries
+            }
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: no relocation \
+                                                   entry provided")),
+        };
+
+        if rel.op_mode == config::RelocModOps::DELETE {
+            //delete from the back so that the remaining indexes stay valid
+            entries.sort();
+            for &e in entries.iter().rev() {
+                self.parser.delete_relocation(t, e)?;
+            }
+            println!("DEDelf: deleted {} relocation entries from {}", entries.len(), sec_name);
             return Ok(());
-        } else {
-            return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                           "Invalid Config Options"));
         }
+
+        let replacement = rel.replacement.trim();
+        let val = match rel.op_mode {
+            config::RelocModOps::TYPE => {
+                relocations::match_reloc_type_as_str(machine, replacement.to_string())? as u64
+            }
+            config::RelocModOps::SYM => self.reloc_sym_idx(t, replacement)? as u64,
+            config::RelocModOps::ADDEND => parse_signed_hex(replacement)? as u64,
+            _ => parse_hex(replacement)?,
+        };
+        let field = config::get_reloc_field(rel.op_mode);
+        for &e in &entries {
+            self.parser.modify_relocation(t, e, field.clone(), val)?;
+        }
+        Ok(())
     }
 
+    /*
+    * Modify a field of the selected .symtab/.dynsym entries and write each modified table
+    * back, or add a new symbol. st_value may be given relative to a symbol, binding, type and
+    * visibility by name (e.g. STB_GLOBAL) and st_shndx as a special index name or a section
+    * name. An add replacement is `name,st_value,st_size,st_type,st_bind[,st_shndx]`.
+    */
+    fn modify_symbol(&mut self, sym: &config::SymCfg) -> Result<(), std::io::Error> {
+        let table = match (&sym.sec_name, sym.sec_idx) {
+            (None, None) => None,
+            (name, idx) => Some(self.parser.symtab_sec_idx(name.clone(), idx)?),
+        };
 
-    fn write(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
-        self.parser.write_sections(file_ptr)?;
-        self.parser.write_segments(file_ptr)?;
-        self.parser.write_header_tables(file_ptr)?;
-        self.parser.write_exec_header(file_ptr)?;
+        if sym.op_mode == config::SymModOps::ADD {
+            let symtab_sec = match table {
+                Some(symtab_sec) => symtab_sec,
+                None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                       "Invalid Config Options: new symbols \
+                                                       require a symbol table")),
+            };
+            let parts: Vec<&str> = sym.replacement.split(',').map(|part| part.trim()).collect();
+            if parts.len() < 5 || parts.len() > 6 || parts[0].is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Invalid Config Options: new symbols must be \
+                                               given as name,st_value,st_size,st_type,st_bind\
+                                               [,st_shndx]"));
+            }
+            let value = match config::parse_location(parts[1]) {
+                Some(location) => self.location_vaddr(&location)?,
+                None => parse_hex(parts[1])?,
+            };
+            let size = parse_hex(parts[2])?;
+            let sym_type = symbols::match_st_type_as_str(parts[3].to_string())?;
+            let bind = symbols::match_st_bind_as_str(parts[4].to_string())?;
+            //by default the symbol is defined in the section containing its value
+            let shndx = match parts.get(5) {
+                Some(shndx) => self.sym_shndx(shndx)?,
+                None if value == 0 => symbols::SHN::SHN_UNDEF as u64,
+                None => match self.parser.section_at(value) {
+                    Some(idx) => idx as u64,
+                    None => symbols::SHN::SHN_ABS as u64,
+                },
+            };
+            let e = self.parser.add_symbol(symtab_sec, parts[0], value, size,
+                                           bind << 4 | sym_type, 0, shndx as u16)?;
+            println!("DEDelf: added symbol {} at index {:#x} of {}", parts[0], e,
+                     self.parser.sections[symtab_sec].name());
+            return Ok(());
+        }
+
+        let targets: Vec<(usize, usize)> = match &sym.entry {
+            Some(config::SymEntry::Index(e)) => {
+                //a bare index refers to .symtab, or to .dynsym in stripped files
+                let symtab_sec = match table {
+                    Some(symtab_sec) => Some(symtab_sec),
+                    None => self.parser.sections.iter().position(|sec| {
+                        sec.shtype_as_u32() == SH_Type::SHT_SYMTAB as u32
+                    }).or_else(|| self.parser.sections.iter().position(|sec| {
+                        sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32
+                    })),
+                };
+                match symtab_sec {
+                    Some(symtab_sec) => vec![(symtab_sec, *e)],
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "Invalid Config Options: no symbol \
+                                                           table found")),
+                }
+            }
+            Some(config::SymEntry::Name(name)) => {
+                let targets: Vec<(usize, usize)> = self.parser.elf_symbols.iter()
+                    .filter(|elf_sym| &elf_sym.name == name &&
+                        table.map_or(true, |t| elf_sym.symtab_idx as usize == t))
+                    .map(|elf_sym| (elf_sym.symtab_idx as usize, elf_sym.index as usize))
+                    .collect();
+                if targets.is_empty() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("Invalid Config Options: symbol {} \
+                                                           not found", name)));
+                }
+                targets
+            }
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid Config Options: no symbol provided")),
+        };
+
+        let replacement = sym.replacement.trim();
+        if sym.op_mode == config::SymModOps::RENAME {
+            //rename every selected entry of a table at once, as its string table is rebuilt
+            let mut tables: Vec<usize> = targets.iter().map(|&(symtab_sec, _)| symtab_sec).collect();
+            tables.sort();
+            tables.dedup();
+            for symtab_sec in tables {
+                let renames: Vec<(usize, String)> = targets.iter()
+                    .filter(|&&(sec, _)| sec == symtab_sec)
+                    .map(|&(_, e)| (e, replacement.to_string()))
+                    .collect();
+                self.parser.rename_symbols(symtab_sec, &renames)?;
+            }
+            println!("DEDelf: renamed {} symbol entries to {}", targets.len(), replacement);
+            return Ok(());
+        }
+
+        let val = match sym.op_mode {
+            config::SymModOps::VALUE => match config::parse_location(replacement) {
+                Some(location) => self.location_vaddr(&location)?,
+                None => parse_hex(replacement)?,
+            },
+            config::SymModOps::BIND => symbols::match_st_bind_as_str(replacement.to_string())? as u64,
+            config::SymModOps::TYPE => symbols::match_st_type_as_str(replacement.to_string())? as u64,
+            config::SymModOps::VISIBILITY => {
+                symbols::match_st_visibility_as_str(replacement.to_string())? as u64
+            }
+            //st_other holds the visibility in its low bits
+            config::SymModOps::OTHER => {
+                match symbols::match_st_visibility_as_str(replacement.to_string()) {
+                    Ok(vis) => vis as u64,
+                    Err(_) => parse_hex(replacement)?,
+                }
+            }
+            config::SymModOps::SHNDX => self.sym_shndx(replacement)?,
+            //st_name is set directly, with renames handled above
+            _ => parse_hex(replacement)?,
+        };
+
+        let field = config::get_sym_field(sym.op_mode);
+        let mut tables: Vec<usize> = Vec::new();
+        for &(symtab_sec, e) in &targets {
+            self.parser.modify_symbol(symtab_sec, e, field.as_str(), val)?;
+            if !tables.contains(&symtab_sec) {
+                tables.push(symtab_sec);
+            }
+        }
+        for &symtab_sec in &tables {
+            self.parser.write_symbol_table(symtab_sec)?;
+        }
+        println!("DEDelf: modified {} of {} symbol entries", field, targets.len());
+        Ok(())
+    }
+
+    /* Section index for st_shndx given as SHN_UNDEF, SHN_ABS, SHN_COMMON, a section name or hex */
+    fn sym_shndx(&self, val: &str) -> Result<u64, std::io::Error> {
+        match val {
+            "SHN_UNDEF" => Ok(symbols::SHN::SHN_UNDEF as u64),
+            "SHN_ABS" => Ok(symbols::SHN::SHN_ABS as u64),
+            "SHN_COMMON" => Ok(symbols::SHN::SHN_COMMON as u64),
+            _ => match self.parser.sections.iter().position(|sec| sec.name() == val) {
+                Some(idx) => Ok(idx as u64),
+                None => parse_hex(val),
+            }
+        }
+    }
+
+    /*
+    * Add, remove or replace DT_NEEDED entries of the .dynamic section, or set, remove or
+    * convert its RPATH, RUNPATH and SONAME strings, or set or clear DT_FLAGS/DT_FLAGS_1 flags.
+    * The program interpreter and stack executability (NOEXECSTACK, which is not a dynamic
+    * flag but is hardened alongside them) are set here too.
+    */
+    fn modify_dynamic(&mut self, dynamic: &config::DynCfg) -> Result<(), std::io::Error> {
+        let replacement = dynamic.replacement.trim();
+        match dynamic.op_mode {
+            config::DynModOps::ADD_NEEDED => {
+                if self.parser.add_needed(replacement)? {
+                    println!("DEDelf: added needed library {}", replacement);
+                } else {
+                    println!("DEDelf: {} is already needed, skipping", replacement);
+                }
+            }
+            config::DynModOps::REMOVE_NEEDED => {
+                self.parser.remove_needed(replacement)?;
+                println!("DEDelf: removed needed library {}", replacement);
+            }
+            config::DynModOps::REPLACE_NEEDED => {
+                let (old_lib, new_lib) = match replacement.find(':') {
+                    Some(pos) => (&replacement[..pos], &replacement[pos + 1..]),
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "Invalid Config Options: replaced \
+                                                           libraries must be given as \
+                                                           <old>:<new>")),
+                };
+                self.parser.replace_needed(old_lib, new_lib)?;
+                println!("DEDelf: replaced needed library {} with {}", old_lib, new_lib);
+            }
+            config::DynModOps::SET_RPATH |
+            config::DynModOps::SET_RUNPATH |
+            config::DynModOps::SET_SONAME => {
+                let tag = match dynamic.op_mode {
+                    config::DynModOps::SET_RPATH => DynTag::DT_RPATH,
+                    config::DynModOps::SET_RUNPATH => DynTag::DT_RUNPATH,
+                    _ => DynTag::DT_SONAME,
+                };
+                self.parser.set_dynamic_string(tag, replacement)?;
+                println!("DEDelf: set {} to {}", tag.name(), replacement);
+            }
+            config::DynModOps::REMOVE_RPATH |
+            config::DynModOps::REMOVE_RUNPATH |
+            config::DynModOps::REMOVE_SONAME => {
+                let tag = match dynamic.op_mode {
+                    config::DynModOps::REMOVE_RPATH => DynTag::DT_RPATH,
+                    config::DynModOps::REMOVE_RUNPATH => DynTag::DT_RUNPATH,
+                    _ => DynTag::DT_SONAME,
+                };
+                self.parser.remove_dynamic_tag(tag)?;
+                println!("DEDelf: removed {}", tag.name());
+            }
+            config::DynModOps::CONVERT_RPATH => {
+                self.parser.convert_dynamic_tag(DynTag::DT_RPATH, DynTag::DT_RUNPATH)?;
+                println!("DEDelf: converted DT_RPATH to DT_RUNPATH");
+            }
+            config::DynModOps::CONVERT_RUNPATH => {
+                self.parser.convert_dynamic_tag(DynTag::DT_RUNPATH, DynTag::DT_RPATH)?;
+                println!("DEDelf: converted DT_RUNPATH to DT_RPATH");
+            }
+            config::DynModOps::SET_INTERPRETER => {
+                self.parser.set_interpreter(replacement)?;
+                println!("DEDelf: set program interpreter to {}", replacement);
+            }
+            config::DynModOps::SET_FLAGS |
+            config::DynModOps::CLEAR_FLAGS => {
+                let set = dynamic.op_mode == config::DynModOps::SET_FLAGS;
+                let mut names: Vec<&str> = replacement.split('|').map(|name| name.trim()).collect();
+                if names.contains(&"NOEXECSTACK") {
+                    self.parser.set_stack_executable(!set)?;
+                    names.retain(|&name| name != "NOEXECSTACK");
+                }
+                if !names.is_empty() {
+                    let (flags, flags_1) = dynamic::match_dyn_flags_as_str(names.join("|"))?;
+                    let (set_flags, clear_flags) = if set { (flags, 0) } else { (0, flags) };
+                    let (set_flags_1, clear_flags_1) = if set { (flags_1, 0) } else { (0, flags_1) };
+                    self.parser.update_dynamic_flags(DynTag::DT_FLAGS, set_flags, clear_flags)?;
+                    self.parser.update_dynamic_flags(DynTag::DT_FLAGS_1, set_flags_1,
+                                                     clear_flags_1)?;
+                }
+                println!("DEDelf: {} {}", if set { "set" } else { "cleared" }, replacement);
+            }
+        }
+        Ok(())
+    }
+
+    /* Virtual address of a location given as a hex value or relative to a symbol */
+    fn location_vaddr(&self, location: &config::Location) -> Result<u64, std::io::Error> {
+        match location {
+            config::Location::Value(val) => Ok(*val),
+            config::Location::Symbol(name, off) => self.parser.symbol_address(name, *off),
+            config::Location::HexOrSymbol(val, name) => match self.parser.symbol_address(name, 0) {
+                Ok(_) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                 format!("{} is both a hex value and a symbol: use \
+                                                         0x{} for the value or {}+0x0 for the \
+                                                         symbol", name, name, name))),
+                Err(_) => Ok(*val),
+            },
+        }
+    }
+
+    /*
+    * Resolve the injection options given relative to a symbol: the entry point (-e) to the
+    * symbol's virtual address, the byte offset (-b) to its file offset and the section to
+    * extend (-p) to the section containing it
+    */
+    fn resolve_inj_locations(&self, inj: &mut config::InjModeOps) -> Result<(), std::io::Error> {
+        match inj.get_entry_location() {
+            Some(config::Location::Value(_)) | None => (),
+            Some(location) => inj.set_entry(self.location_vaddr(&location)?),
+        }
+        match inj.get_offset_location() {
+            Some(config::Location::Value(_)) | None => (),
+            //a bare hex value that is not a symbol is already a file offset
+            Some(config::Location::HexOrSymbol(offset, name))
+                if self.parser.symbol_address(&name, 0).is_err() => inj.set_offset(offset),
+            Some(location) => {
+                let vaddr = self.location_vaddr(&location)?;
+                match self.parser.vaddr_to_offset(vaddr) {
+                    Some(offset) => inj.set_offset(offset),
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           format!("{:#x} is not backed by file \
+                                                                   contents", vaddr))),
+                }
+            }
+        }
+        let extend = inj.get_extend().and_then(|extend| config::parse_symbol_location(&extend));
+        if let Some(location) = extend {
+            let vaddr = self.location_vaddr(&location)?;
+            match self.parser.section_at(vaddr) {
+                Some(idx) => inj.set_extend(self.parser.sections[idx].name()),
+                None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                       format!("No section contains {:#x}", vaddr))),
+            }
+        }
         Ok(())
     }
 
+    /* Symbol index for a relocation entry given as a hex index or a symbol name */
+    fn reloc_sym_idx(&self, t: usize, sym: &str) -> Result<u32, std::io::Error> {
+        if let Ok(idx) = u32::from_str_radix(sym.trim_start_matches("0x"), 16) {
+            return Ok(idx);
+        }
+        match self.parser.rel_symbol_idx(t, sym) {
+            Some(idx) => Ok(idx),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("Invalid Config Options: symbol {} not found \
+                                                    in the symbol table of the relocation section",
+                                                    sym))),
+        }
+    }
+}
+
+fn parse_hex(val: &str) -> Result<u64, std::io::Error> {
+    match u64::from_str_radix(val.trim_start_matches("0x"), 16) {
+        Ok(val) => Ok(val),
+        Err(_) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                          "Invalid Config Options: \
+                                          provided value is invalid \
+                                          did you provide a valid \
+                                          hex value?")),
+    }
+}
+
+/* Parse a hex value that may be negative, e.g. an addend of `-0x8` */
+fn parse_signed_hex(val: &str) -> Result<i64, std::io::Error> {
+    match val.strip_prefix('-') {
+        Some(val) => Ok((parse_hex(val)? as i64).wrapping_neg()),
+        None => Ok(parse_hex(val)? as i64),
+    }
+}
+
+/* Read the contents of a file provided for injection or for a new section or segment */
+fn read_contents_file(file: &String) -> Result<Vec<u8>, std::io::Error> {
+    let mut fp = match File::open(file.clone()) {
+        Err(why) => {
+            println!("Could not open contents file: {}: {}", file, why);
+            return Err(why);
+        }
+        Ok(fp) => fp,
+    };
+    parser::read_input(&mut fp)
+}
+
+/* Split a `path/to/file:name` payload spec, if the file exists and the name is non-empty */
+fn split_inj_spec(spec: &str) -> Option<(&str, &str)> {
+    let idx = spec.rfind(':')?;
+    let (file, name) = (&spec[..idx], &spec[idx + 1..]);
+    if name.is_empty() || !Path::new(file).is_file() {
+        return None
+    }
+    Some((file, name))
+}
+
+/*
+* Read the injection payload. The payload may be a file containing only the bytes to inject,
+* or a section or symbol of another ELF file given as `path/to/file:name` (e.g. payload.o:.text
+* or payload.elf:sym_name), in which case just that section's or symbol's bytes are used.
+*/
+fn read_inj_payload(spec: &String) -> Result<Vec<u8>, std::io::Error> {
+    if let Some((file, name)) = split_inj_spec(spec) {
+        let payload = ElfParser::new(file.to_string())?;
+        let bytes = payload.get_named_bytes(name)?;
+        println!("DEDelf: read {:#x} payload bytes from {} in {}", bytes.len(), name, file);
+        return Ok(bytes)
+    }
+    read_contents_file(spec)
+}
+
+/*
+* Load a relocatable object payload (`path/to/file.o` or `path/to/file.o:name`) to be linked,
+* entered at the section or symbol name if given. Returns None for any other payload.
+*/
+fn load_inj_object(spec: &str) -> Result<Option<Linker>, std::io::Error> {
+    let (file, name) = match split_inj_spec(spec) {
+        Some((file, name)) => (file, Some(name)),
+        None => (spec, None),
+    };
+    if !linker::is_relocatable(file) {
+        return Ok(None)
+    }
+    let linker = Linker::new(ElfParser::new(file.to_string())?, name)?;
+    println!("DEDelf: linking relocatable object {} ({} sections, {:#x} bytes)",
+             file, linker.placements.len(), linker.size);
+    Ok(Some(linker))
 }
 
 /*
 * fn run: perform the requested operations on the input file (must contain a valid ELF).
 * 1. Create the generic Elf object that implements the DedElf trait
 * 2. Perform the requested injection and/or modifications
-* 3. Write the modified bytes to the provided outfile
+* 3. Write the modified bytes to the provided outfile (unless only listing information)
 */
 pub fn run(file: String, ops: config::DedElfOps, outfile: String) -> Result<(), std::io::Error> {
     let mut ded_elf: Elf = DedElf::new(file, ops)?;
     ded_elf.inject_or_modify()?;
+    if ded_elf.ops.list_only() {
+        return Ok(());
+    }
     let mut dir_path = std::env::current_dir().unwrap();
     dir_path.push(outfile.as_str());
 
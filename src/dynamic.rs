@@ -1,47 +1,580 @@
+use std::io::{Read, Write};
+use byteorder::*;
+
+use crate::header::*;
+
+/* Bounds of the OS and processor specific d_tag ranges */
+const OLD_DT_LOOS: u64 = 0x60000000;
+const OLD_DT_HIOS: u64 = 0x6fffffff;
+const DT_LOPROC: u64 = 0x70000000;
+const DT_HIPROC: u64 = 0x7fffffff;
+
+#[derive(Clone, Debug)]
+pub enum Dynamic {
+    ThirtyTwo(Dyn32),
+    SixtyFour(Dyn64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Dyn32 {
+    pub d_tag: i32,
+    pub d_val: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Dyn64 {
+    pub d_tag: i64,
+    pub d_val: u64,
+}
+
+impl Dyn32 {
+    pub fn parse_dyn<R, B: ByteOrder>(rdr: &mut R) -> Result<Dyn32, std::io::Error>
+        where R: Read {
+        let tag = rdr.read_i32::<B>()?;
+        let val = rdr.read_u32::<B>()?;
+        Ok(Dyn32 {
+            d_tag: tag,
+            d_val: val,
+        })
+    }
+
+    pub fn write_dyn<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_i32::<B>(self.d_tag)?;
+        wrtr.write_u32::<B>(self.d_val)?;
+        Ok(())
+    }
+}
+
+impl Dyn64 {
+    pub fn parse_dyn<R, B: ByteOrder>(rdr: &mut R) -> Result<Dyn64, std::io::Error>
+        where R: Read {
+        let tag = rdr.read_i64::<B>()?;
+        let val = rdr.read_u64::<B>()?;
+        Ok(Dyn64 {
+            d_tag: tag,
+            d_val: val,
+        })
+    }
+
+    pub fn write_dyn<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_i64::<B>(self.d_tag)?;
+        wrtr.write_u64::<B>(self.d_val)?;
+        Ok(())
+    }
+}
+
+/*
+* Accessors shared by both entry sizes. d_tag is signed in the file, but all defined tags are
+* positive, so tags are handled as u64 (32 bit tags are zero extended).
+*/
+impl Dynamic {
+    pub fn new(class: EXEC::EI_CLASS, tag: u64, val: u64) -> Result<Dynamic, std::io::Error> {
+        match class {
+            EXEC::EI_CLASS::ELFCLASS32 => Ok(Dynamic::ThirtyTwo(Dyn32 {
+                d_tag: tag as u32 as i32,
+                d_val: val as u32,
+            })),
+            EXEC::EI_CLASS::ELFCLASS64 => Ok(Dynamic::SixtyFour(Dyn64 {
+                d_tag: tag as i64,
+                d_val: val,
+            })),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Elf not supported")),
+        }
+    }
+
+    pub fn tag(&self) -> u64 {
+        match self {
+            Dynamic::ThirtyTwo(dyn32) => dyn32.d_tag as u32 as u64,
+            Dynamic::SixtyFour(dyn64) => dyn64.d_tag as u64,
+        }
+    }
+
+    pub fn val(&self) -> u64 {
+        match self {
+            Dynamic::ThirtyTwo(dyn32) => dyn32.d_val as u64,
+            Dynamic::SixtyFour(dyn64) => dyn64.d_val,
+        }
+    }
+
+    pub fn set_tag(&mut self, tag: u64) {
+        match self {
+            Dynamic::ThirtyTwo(dyn32) => dyn32.d_tag = tag as u32 as i32,
+            Dynamic::SixtyFour(dyn64) => dyn64.d_tag = tag as i64,
+        }
+    }
+
+    pub fn set_val(&mut self, val: u64) -> Result<(), std::io::Error> {
+        match self {
+            Dynamic::ThirtyTwo(dyn32) => {
+                if val > u32::MAX as u64 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("d_val {:#x} does not fit in a 32 bit \
+                                                           entry", val)))
+                }
+                dyn32.d_val = val as u32;
+            }
+            Dynamic::SixtyFour(dyn64) => dyn64.d_val = val,
+        }
+        Ok(())
+    }
+
+    /* Size in bytes of the entry in the file */
+    pub fn entsize(&self) -> usize {
+        match self {
+            Dynamic::ThirtyTwo(_) => 8,
+            Dynamic::SixtyFour(_) => 16,
+        }
+    }
+
+    pub fn write<W>(&self, wrtr: &mut W, data: EXEC::EI_DATA) -> Result<(), std::io::Error>
+        where W: Write {
+        match (self, data) {
+            (Dynamic::ThirtyTwo(dyn32), EXEC::EI_DATA::ELFDATA2LSB) => {
+                dyn32.write_dyn::<W, LittleEndian>(wrtr)
+            }
+            (Dynamic::ThirtyTwo(dyn32), EXEC::EI_DATA::ELFDATA2MSB) => {
+                dyn32.write_dyn::<W, BigEndian>(wrtr)
+            }
+            (Dynamic::SixtyFour(dyn64), EXEC::EI_DATA::ELFDATA2LSB) => {
+                dyn64.write_dyn::<W, LittleEndian>(wrtr)
+            }
+            (Dynamic::SixtyFour(dyn64), EXEC::EI_DATA::ELFDATA2MSB) => {
+                dyn64.write_dyn::<W, BigEndian>(wrtr)
+            }
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Elf not supported")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DynamicEntry {
+    pub dynamic: Dynamic,
+    pub tag: DynTag,
+    //the .dynstr string of string valued tags (e.g. DT_NEEDED), resolved after parsing
+    pub string: Option<String>,
+}
+
+/*
+* Dynamic table object to hold the parsed entries of the SHT_DYNAMIC section. link_idx is the
+* index of the string table section (sh_link, normally .dynstr) that string valued entries
+* are offsets into. All entries are kept, including any DT_NULL padding after the first
+* DT_NULL, so that the table can be written back in place.
+*/
+#[derive(Clone, Debug)]
+pub struct DynamicTable {
+    pub section_idx: u32,
+    pub sec_name: String,
+    pub link_idx: u32,
+    pub class: EXEC::EI_CLASS,
+    pub data: EXEC::EI_DATA,
+    pub entries: Vec<DynamicEntry>,
+}
+
+impl DynamicTable {
+    pub fn parse_dynamic_table<R>(rdr: &mut R, sec_size: u64, idx: u32, name: String,
+                                  link: u32,
+                                  data: EXEC::EI_DATA,
+                                  class: EXEC::EI_CLASS) -> Result<DynamicTable, std::io::Error>
+        where R: Read {
+        let entsize = match class {
+            EXEC::EI_CLASS::ELFCLASS32 => 8,
+            EXEC::EI_CLASS::ELFCLASS64 => 16,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported")),
+        };
+
+        let mut entries: Vec<DynamicEntry> = Vec::new();
+        for _ in 0..sec_size / entsize {
+            let dynamic = match (class, data) {
+                (EXEC::EI_CLASS::ELFCLASS32, EXEC::EI_DATA::ELFDATA2LSB) => {
+                    Dynamic::ThirtyTwo(Dyn32::parse_dyn::<R, LittleEndian>(rdr)?)
+                }
+                (EXEC::EI_CLASS::ELFCLASS32, EXEC::EI_DATA::ELFDATA2MSB) => {
+                    Dynamic::ThirtyTwo(Dyn32::parse_dyn::<R, BigEndian>(rdr)?)
+                }
+                (EXEC::EI_CLASS::ELFCLASS64, EXEC::EI_DATA::ELFDATA2LSB) => {
+                    Dynamic::SixtyFour(Dyn64::parse_dyn::<R, LittleEndian>(rdr)?)
+                }
+                (EXEC::EI_CLASS::ELFCLASS64, EXEC::EI_DATA::ELFDATA2MSB) => {
+                    Dynamic::SixtyFour(Dyn64::parse_dyn::<R, BigEndian>(rdr)?)
+                }
+                _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                    "Elf not supported")),
+            };
+            entries.push(DynamicEntry {
+                tag: DynTag::new(dynamic.tag()),
+                dynamic: dynamic,
+                string: None,
+            });
+        }
+
+        Ok(DynamicTable {
+            section_idx: idx,
+            sec_name: name,
+            link_idx: link,
+            class: class,
+            data: data,
+            entries: entries,
+        })
+    }
+
+    /* Resolve the strings of string valued entries from the contents of the linked string table */
+    pub fn resolve_strings(&mut self, strtab: &[u8]) {
+        for entry in &mut self.entries {
+            if !entry.tag.is_string() {
+                entry.string = None;
+                continue
+            }
+            let start = entry.dynamic.val() as usize;
+            if start >= strtab.len() {
+                entry.string = None;
+                continue
+            }
+            let end = strtab[start..].iter().position(|&b| b == 0)
+                .map_or(strtab.len(), |pos| start + pos);
+            entry.string = Some(String::from_utf8_lossy(&strtab[start..end]).to_string());
+        }
+    }
+
+    /* Number of entries up to and including the first DT_NULL */
+    pub fn len(&self) -> usize {
+        match self.entries.iter().position(|entry| entry.tag == DynTag::DT_NULL) {
+            Some(pos) => pos + 1,
+            None => self.entries.len(),
+        }
+    }
+
+    /* Index of the first entry with the provided tag, before the terminating DT_NULL */
+    pub fn find(&self, tag: DynTag) -> Option<usize> {
+        self.entries[..self.len()].iter().position(|entry| entry.tag == tag)
+    }
+
+    /* Value of the first entry with the provided tag */
+    pub fn get(&self, tag: DynTag) -> Option<u64> {
+        self.find(tag).map(|idx| self.entries[idx].dynamic.val())
+    }
+
+    /* Strings of all entries with the provided tag, in table order */
+    pub fn get_strings(&self, tag: DynTag) -> Vec<String> {
+        self.entries[..self.len()].iter()
+            .filter(|entry| entry.tag == tag)
+            .filter_map(|entry| entry.string.clone())
+            .collect()
+    }
+
+    pub fn needed(&self) -> Vec<String> {
+        self.get_strings(DynTag::DT_NEEDED)
+    }
+
+    pub fn soname(&self) -> Option<String> {
+        self.get_strings(DynTag::DT_SONAME).into_iter().next()
+    }
+
+    pub fn rpath(&self) -> Option<String> {
+        self.get_strings(DynTag::DT_RPATH).into_iter().next()
+    }
+
+    pub fn runpath(&self) -> Option<String> {
+        self.get_strings(DynTag::DT_RUNPATH).into_iter().next()
+    }
+
+    /* Set the value of the first entry with the provided tag. Returns false if there is none */
+    pub fn set(&mut self, tag: DynTag, val: u64) -> Result<bool, std::io::Error> {
+        match self.find(tag) {
+            Some(idx) => {
+                self.entries[idx].dynamic.set_val(val)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /*
+    * Insert an entry before the entry at idx (at most the index of the terminating DT_NULL).
+    * A spare DT_NULL slot after the terminating DT_NULL is used if there is one, so that the
+    * table only grows when it has no padding left.
+    */
+    pub fn insert(&mut self, idx: usize, tag: DynTag, val: u64) -> Result<(), std::io::Error> {
+        if idx >= self.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid dynamic entry index provided"))
+        }
+        let dynamic = Dynamic::new(self.class, tag.as_u64(), val)?;
+        if self.entries.len() > self.len() {
+            self.entries.pop();
+        }
+        self.entries.insert(idx, DynamicEntry {
+            dynamic: dynamic,
+            tag: tag,
+            string: None,
+        });
+        Ok(())
+    }
+
+    /* Change the tag of the entry at idx, keeping its value */
+    pub fn retag(&mut self, idx: usize, tag: DynTag) {
+        self.entries[idx].dynamic.set_tag(tag.as_u64());
+        self.entries[idx].tag = tag;
+    }
+
+    /* Remove the entry at idx, padding the end of the table with DT_NULL so it does not shrink */
+    pub fn remove(&mut self, idx: usize) -> Result<(), std::io::Error> {
+        if idx + 1 >= self.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid dynamic entry index provided"))
+        }
+        self.entries.remove(idx);
+        self.entries.push(DynamicEntry {
+            dynamic: Dynamic::new(self.class, DynTag::DT_NULL.as_u64(), 0)?,
+            tag: DynTag::DT_NULL,
+            string: None,
+        });
+        Ok(())
+    }
+
+    /* Serialize the entries back into section contents */
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        for entry in &self.entries {
+            entry.dynamic.write(&mut bytes, self.data)?;
+        }
+        Ok(bytes)
+    }
+}
+
+/*
+* Generates the dynamic tag enum with conversions from the raw d_tag value and from the tag
+* name, e.g. "DT_NEEDED". Tags without a name are kept as their value, by range.
+*/
+macro_rules! dyn_tags {
+    ($name:ident { $($variant:ident = $val:expr,)* }) => {
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum $name {
+            $($variant,)*
+            Os(u64),
+            Proc(u64),
+            Unknown(u64),
+        }
+
+        impl $name {
+            pub fn new(tag: u64) -> $name {
+                match tag {
+                    $($val => $name::$variant,)*
+                    OLD_DT_LOOS..=OLD_DT_HIOS => $name::Os(tag),
+                    DT_LOPROC..=DT_HIPROC => $name::Proc(tag),
+                    _ => $name::Unknown(tag),
+                }
+            }
+
+            pub fn as_u64(&self) -> u64 {
+                match self {
+                    $($name::$variant => $val,)*
+                    $name::Os(tag) | $name::Proc(tag) | $name::Unknown(tag) => *tag,
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<$name> {
+                match name {
+                    $(stringify!($variant) => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn name(&self) -> String {
+                match self {
+                    $($name::$variant => stringify!($variant).to_string(),)*
+                    $name::Os(tag) => format!("LOOS+{:#x}", tag - OLD_DT_LOOS),
+                    $name::Proc(tag) => format!("LOPROC+{:#x}", tag - DT_LOPROC),
+                    $name::Unknown(tag) => format!("{:#x}", tag),
+                }
+            }
+        }
+    }
+}
+
+dyn_tags!(DynTag {
+    DT_NULL = 0,
+    DT_NEEDED = 1,
+    DT_PLTRELSZ = 2,
+    DT_PLTGOT = 3,
+    DT_HASH = 4,
+    DT_STRTAB = 5,
+    DT_SYMTAB = 6,
+    DT_RELA = 7,
+    DT_RELASZ = 8,
+    DT_RELAENT = 9,
+    DT_STRSZ = 10,
+    DT_SYMENT = 11,
+    DT_INIT = 12,
+    DT_FINI = 13,
+    DT_SONAME = 14,
+    DT_RPATH = 15,
+    DT_SYMBOLIC = 16,
+    DT_REL = 17,
+    DT_RELSZ = 18,
+    DT_RELENT = 19,
+    DT_PLTREL = 20,
+    DT_DEBUG = 21,
+    DT_TEXTREL = 22,
+    DT_JMPREL = 23,
+    DT_BIND_NOW = 24,
+    DT_INIT_ARRAY = 25,
+    DT_FINI_ARRAY = 26,
+    DT_INIT_ARRAYSZ = 27,
+    DT_FINI_ARRAYSZ = 28,
+    DT_RUNPATH = 29,
+    DT_FLAGS = 30,
+    //DT_ENCODING = 32 is an alias of DT_PREINIT_ARRAY
+    DT_PREINIT_ARRAY = 32,
+    DT_PREINIT_ARRAYSZ = 33,
+    DT_SYMTAB_SHNDX = 34,
+    DT_RELRSZ = 35,
+    DT_RELR = 36,
+    DT_RELRENT = 37,
+    DT_ANDROID_REL = 0x6000000f,
+    DT_ANDROID_RELSZ = 0x60000010,
+    DT_ANDROID_RELA = 0x60000011,
+    DT_ANDROID_RELASZ = 0x60000012,
+    DT_ANDROID_RELR = 0x6fffe000,
+    DT_ANDROID_RELRSZ = 0x6fffe001,
+    DT_ANDROID_RELRENT = 0x6fffe003,
+    DT_GNU_PRELINKED = 0x6ffffdf5,
+    DT_GNU_CONFLICTSZ = 0x6ffffdf6,
+    DT_GNU_LIBLISTSZ = 0x6ffffdf7,
+    DT_CHECKSUM = 0x6ffffdf8,
+    DT_PLTPADSZ = 0x6ffffdf9,
+    DT_MOVEENT = 0x6ffffdfa,
+    DT_MOVESZ = 0x6ffffdfb,
+    DT_FEATURE_1 = 0x6ffffdfc,
+    DT_POSFLAG_1 = 0x6ffffdfd,
+    DT_SYMINSZ = 0x6ffffdfe,
+    DT_SYMINENT = 0x6ffffdff,
+    DT_GNU_HASH = 0x6ffffef5,
+    DT_TLSDESC_PLT = 0x6ffffef6,
+    DT_TLSDESC_GOT = 0x6ffffef7,
+    DT_GNU_CONFLICT = 0x6ffffef8,
+    DT_GNU_LIBLIST = 0x6ffffef9,
+    DT_CONFIG = 0x6ffffefa,
+    DT_DEPAUDIT = 0x6ffffefb,
+    DT_AUDIT = 0x6ffffefc,
+    DT_PLTPAD = 0x6ffffefd,
+    DT_MOVETAB = 0x6ffffefe,
+    DT_SYMINFO = 0x6ffffeff,
+    DT_VERSYM = 0x6ffffff0,
+    DT_RELACOUNT = 0x6ffffff9,
+    DT_RELCOUNT = 0x6ffffffa,
+    DT_FLAGS_1 = 0x6ffffffb,
+    DT_VERDEF = 0x6ffffffc,
+    DT_VERDEFNUM = 0x6ffffffd,
+    DT_VERNEED = 0x6ffffffe,
+    DT_VERNEEDNUM = 0x6fffffff,
+    DT_AUXILIARY = 0x7ffffffd,
+    DT_FILTER = 0x7fffffff,
+});
+
+impl DynTag {
+    /* Tags whose value is an offset into the dynamic string table */
+    pub fn is_string(&self) -> bool {
+        match self {
+            DynTag::DT_NEEDED | DynTag::DT_SONAME | DynTag::DT_RPATH | DynTag::DT_RUNPATH |
+            DynTag::DT_AUXILIARY | DynTag::DT_FILTER | DynTag::DT_CONFIG |
+            DynTag::DT_DEPAUDIT | DynTag::DT_AUDIT => true,
+            _ => false,
+        }
+    }
+}
+
+/* Get a dynamic tag from its name (e.g. `DT_NEEDED`) or a hex value */
+pub fn match_dyn_tag_as_str(tag: String) -> Result<DynTag, std::io::Error> {
+    if let Some(tag) = DynTag::from_name(&tag) {
+        return Ok(tag)
+    }
+    match u64::from_str_radix(tag.trim_start_matches("0x"), 16) {
+        Ok(tag) => Ok(DynTag::new(tag)),
+        Err(_) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                          format!("Invalid dynamic tag {}", tag))),
+    }
+}
+
+/* DT_FLAGS values, from elf.h */
+pub fn match_df_flag_as_str(flag: String) -> Result<u64, std::io::Error> {
+    match flag.as_str() {
+        "DF_ORIGIN" => Ok(0x1),
+        "DF_SYMBOLIC" => Ok(0x2),
+        "DF_TEXTREL" => Ok(0x4),
+        "DF_BIND_NOW" => Ok(0x8),
+        "DF_STATIC_TLS" => Ok(0x10),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "Invalid DT_FLAGS flag")),
+    }
+}
+
+/* DT_FLAGS_1 values, from elf.h */
+pub fn match_df_1_flag_as_str(flag: String) -> Result<u64, std::io::Error> {
+    match flag.as_str() {
+        "DF_1_NOW" => Ok(0x1),
+        "DF_1_GLOBAL" => Ok(0x2),
+        "DF_1_GROUP" => Ok(0x4),
+        "DF_1_NODELETE" => Ok(0x8),
+        "DF_1_LOADFLTR" => Ok(0x10),
+        "DF_1_INITFIRST" => Ok(0x20),
+        "DF_1_NOOPEN" => Ok(0x40),
+        "DF_1_ORIGIN" => Ok(0x80),
+        "DF_1_DIRECT" => Ok(0x100),
+        "DF_1_TRANS" => Ok(0x200),
+        "DF_1_INTERPOSE" => Ok(0x400),
+        "DF_1_NODEFLIB" => Ok(0x800),
+        "DF_1_NODUMP" => Ok(0x1000),
+        "DF_1_CONFALT" => Ok(0x2000),
+        "DF_1_ENDFILTEE" => Ok(0x4000),
+        "DF_1_DISPRELDNE" => Ok(0x8000),
+        "DF_1_DISPRELPND" => Ok(0x10000),
+        "DF_1_NODIRECT" => Ok(0x20000),
+        "DF_1_IGNMULDEF" => Ok(0x40000),
+        "DF_1_NOKSYMS" => Ok(0x80000),
+        "DF_1_NOHDR" => Ok(0x100000),
+        "DF_1_EDITED" => Ok(0x200000),
+        "DF_1_NORELOC" => Ok(0x400000),
+        "DF_1_SYMINTPOSE" => Ok(0x800000),
+        "DF_1_GLOBAUDIT" => Ok(0x1000000),
+        "DF_1_SINGLETON" => Ok(0x2000000),
+        "DF_1_STUB" => Ok(0x4000000),
+        "DF_1_PIE" => Ok(0x8000000),
+        "DF_1_KMOD" => Ok(0x10000000),
+        "DF_1_WEAKFILTER" => Ok(0x20000000),
+        "DF_1_NOCOMMON" => Ok(0x40000000),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "Invalid DT_FLAGS_1 flag")),
+    }
+}
+
 /*
-/* This is the info that is needed to parse the dynamic section of the file */
-#define DT_NULL		0
-#define DT_NEEDED	1
-#define DT_PLTRELSZ	2
-#define DT_PLTGOT	3
-#define DT_HASH		4
-#define DT_STRTAB	5
-#define DT_SYMTAB	6
-#define DT_RELA		7
-#define DT_RELASZ	8
-#define DT_RELAENT	9
-#define DT_STRSZ	10
-#define DT_SYMENT	11
-#define DT_INIT		12
-#define DT_FINI		13
-#define DT_SONAME	14
-#define DT_RPATH 	15
-#define DT_SYMBOLIC	16
-#define DT_REL	        17
-#define DT_RELSZ	18
-#define DT_RELENT	19
-#define DT_PLTREL	20
-#define DT_DEBUG	21
-#define DT_TEXTREL	22
-#define DT_JMPREL	23
-#define DT_ENCODING	32
-#define OLD_DT_LOOS	0x60000000
-#define DT_LOOS		0x6000000d
-#define DT_HIOS		0x6ffff000
-#define DT_VALRNGLO	0x6ffffd00
-#define DT_VALRNGHI	0x6ffffdff
-#define DT_ADDRRNGLO	0x6ffffe00
-#define DT_ADDRRNGHI	0x6ffffeff
-#define DT_VERSYM	0x6ffffff0
-#define DT_RELACOUNT	0x6ffffff9
-#define DT_RELCOUNT	0x6ffffffa
-#define DT_FLAGS_1	0x6ffffffb
-#define DT_VERDEF	0x6ffffffc
-#define	DT_VERDEFNUM	0x6ffffffd
-#define DT_VERNEED	0x6ffffffe
-#define	DT_VERNEEDNUM	0x6fffffff
-#define OLD_DT_HIOS     0x6fffffff
-#define DT_LOPROC	0x70000000
-#define DT_HIPROC	0x7fffffff
-
-*/
\ No newline at end of file
+* Parse a combination of DT_FLAGS and DT_FLAGS_1 flags separated by `|` (e.g.
+* `DF_BIND_NOW|DF_1_NOW`) into the (DT_FLAGS, DT_FLAGS_1) bits to change. Names may be given
+* without the DF_/DF_1_ prefix, in which case a name defined for both tags (e.g. `ORIGIN`)
+* selects both, the same as the linker option setting them does.
+*/
+pub fn match_dyn_flags_as_str(flags: String) -> Result<(u64, u64), std::io::Error> {
+    let mut df_flags: u64 = 0;
+    let mut df_1_flags: u64 = 0;
+    for flag in flags.split("|") {
+        let flag = flag.trim();
+        let df = match_df_flag_as_str(flag.to_string())
+            .or_else(|_| match_df_flag_as_str(format!("DF_{}", flag)));
+        let df_1 = match_df_1_flag_as_str(flag.to_string())
+            .or_else(|_| match_df_1_flag_as_str(format!("DF_1_{}", flag)));
+        match (df, df_1) {
+            (Err(_), Err(_)) => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                               format!("Invalid dynamic flag \
+                                                                       {}", flag))),
+            (df, df_1) => {
+                df_flags |= df.unwrap_or(0);
+                df_1_flags |= df_1.unwrap_or(0);
+            }
+        }
+    }
+    Ok((df_flags, df_1_flags))
+}
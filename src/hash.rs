@@ -0,0 +1,454 @@
+use std::io::Write;
+use byteorder::*;
+
+use crate::header::*;
+
+/*
+* Symbol hash tables (SHT_HASH and SHT_GNU_HASH) used by the loader to look up dynamic symbols
+* by name. Both index into the .dynsym section they link to, so they must be rebuilt whenever
+* dynamic symbols are added, renamed, removed or reordered.
+*
+* SysV .hash layout (all words are 32 bit):
+*   nbucket, nchain, bucket[nbucket], chain[nchain]
+* where nchain is the number of symbols, and each bucket/chain value is a symbol index
+* (0 terminates a chain).
+*
+* GNU .gnu.hash layout:
+*   nbuckets, symoffset, bloom_size, bloom_shift (32 bit words)
+*   bloom[bloom_size] (ELF class sized words)
+*   buckets[nbuckets], chain[nsyms - symoffset] (32 bit words)
+* Only the symbols from symoffset on are hashed, and they must be sorted by bucket. Each
+* bucket holds the index of its first symbol, and each chain value holds the symbol's hash
+* with the low bit set on the last symbol of a bucket.
+*/
+
+pub fn elf_hash(name: &[u8]) -> u32 {
+    let mut h: u32 = 0;
+    for &c in name {
+        h = (h << 4).wrapping_add(c as u32);
+        let g = h & 0xf0000000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+pub fn gnu_hash(name: &[u8]) -> u32 {
+    let mut h: u32 = 5381;
+    for &c in name {
+        h = h.wrapping_mul(33).wrapping_add(c as u32);
+    }
+    h
+}
+
+#[derive(Clone, Debug)]
+pub struct SysvHash {
+    pub buckets: Vec<u32>,
+    pub chains: Vec<u32>,
+}
+
+impl SysvHash {
+    pub fn parse(bytes: &[u8], data: EXEC::EI_DATA) -> Result<SysvHash, std::io::Error> {
+        let words = read_u32s(bytes, data)?;
+        if words.len() < 2 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid hash section: too small"))
+        }
+        let nbucket = words[0] as usize;
+        let nchain = words[1] as usize;
+        if words.len() < 2 + nbucket + nchain {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid hash section: too small for its buckets \
+                                           and chains"))
+        }
+        Ok(SysvHash {
+            buckets: words[2..2 + nbucket].to_vec(),
+            chains: words[2 + nbucket..2 + nbucket + nchain].to_vec(),
+        })
+    }
+
+    /* Build a table with nbucket buckets over the names of every symbol of a .dynsym */
+    pub fn build(names: &[Vec<u8>], nbucket: usize) -> SysvHash {
+        let nbucket = nbucket.max(1);
+        let mut buckets = vec![0; nbucket];
+        let mut chains = vec![0; names.len()];
+        //walk backwards so that each chain lists its symbols in table order
+        for idx in (1..names.len()).rev() {
+            let bucket = elf_hash(&names[idx]) as usize % nbucket;
+            chains[idx] = buckets[bucket];
+            buckets[bucket] = idx as u32;
+        }
+        SysvHash {
+            buckets: buckets,
+            chains: chains,
+        }
+    }
+
+    /* Index of the symbol with the provided name, given the names of the .dynsym symbols */
+    pub fn lookup(&self, name: &[u8], names: &[Vec<u8>]) -> Option<u32> {
+        if self.buckets.is_empty() {
+            return None
+        }
+        let mut idx = self.buckets[elf_hash(name) as usize % self.buckets.len()];
+        while idx != 0 {
+            if names.get(idx as usize).map_or(false, |sym| &sym[..] == name) {
+                return Some(idx)
+            }
+            idx = *self.chains.get(idx as usize)?;
+        }
+        None
+    }
+
+    pub fn to_bytes(&self, data: EXEC::EI_DATA) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_u32(&mut bytes, self.buckets.len() as u32, data)?;
+        write_u32(&mut bytes, self.chains.len() as u32, data)?;
+        for &val in self.buckets.iter().chain(self.chains.iter()) {
+            write_u32(&mut bytes, val, data)?;
+        }
+        Ok(bytes)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GnuHash {
+    pub symoffset: u32,
+    pub bloom_shift: u32,
+    pub bloom: Vec<u64>,
+    pub buckets: Vec<u32>,
+    pub chains: Vec<u32>,
+}
+
+impl GnuHash {
+    pub fn parse(bytes: &[u8], data: EXEC::EI_DATA,
+                 class: EXEC::EI_CLASS) -> Result<GnuHash, std::io::Error> {
+        let word = bloom_word_size(class)?;
+        if bytes.len() < 16 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid GNU hash section: too small"))
+        }
+        let header = read_u32s(&bytes[..16], data)?;
+        let (nbuckets, symoffset, bloom_size, bloom_shift) =
+            (header[0] as usize, header[1], header[2] as usize, header[3]);
+
+        let bloom_end = 16 + bloom_size * word;
+        let buckets_end = bloom_end + nbuckets * 4;
+        if bytes.len() < buckets_end {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid GNU hash section: too small for its bloom \
+                                           filter and buckets"))
+        }
+        let mut bloom: Vec<u64> = Vec::new();
+        for chunk in bytes[16..bloom_end].chunks_exact(word) {
+            bloom.push(match (word, data) {
+                (4, EXEC::EI_DATA::ELFDATA2MSB) => BigEndian::read_u32(chunk) as u64,
+                (4, _) => LittleEndian::read_u32(chunk) as u64,
+                (_, EXEC::EI_DATA::ELFDATA2MSB) => BigEndian::read_u64(chunk),
+                (_, _) => LittleEndian::read_u64(chunk),
+            });
+        }
+        //the chain array runs to the end of the section (one entry per hashed symbol)
+        let chains_len = (bytes.len() - buckets_end) / 4 * 4;
+        Ok(GnuHash {
+            symoffset: symoffset,
+            bloom_shift: bloom_shift,
+            bloom: bloom,
+            buckets: read_u32s(&bytes[bloom_end..buckets_end], data)?,
+            chains: read_u32s(&bytes[buckets_end..buckets_end + chains_len], data)?,
+        })
+    }
+
+    /*
+    * Bucket of each symbol from symoffset on. Rebuilding the table requires the hashed symbols
+    * to already be sorted by bucket (see ElfParser::rebuild_hash_tables).
+    */
+    pub fn bucket_of(name: &[u8], nbuckets: usize) -> usize {
+        gnu_hash(name) as usize % nbuckets.max(1)
+    }
+
+    /*
+    * Build a table over the names of every symbol of a .dynsym, hashing the symbols from
+    * symoffset on, which must be sorted by bucket (see bucket_of)
+    */
+    pub fn build(names: &[Vec<u8>], symoffset: usize, nbuckets: usize, bloom_size: usize,
+                 bloom_shift: u32, class: EXEC::EI_CLASS) -> Result<GnuHash, std::io::Error> {
+        let bits = (bloom_word_size(class)? * 8) as u32;
+        let nbuckets = nbuckets.max(1);
+        let bloom_size = bloom_size.max(1).next_power_of_two();
+        let symoffset = symoffset.max(1).min(names.len());
+
+        let mut bloom = vec![0u64; bloom_size];
+        let mut buckets = vec![0u32; nbuckets];
+        let mut chains = vec![0u32; names.len() - symoffset];
+        let mut last_bucket: Option<usize> = None;
+        for idx in symoffset..names.len() {
+            let h = gnu_hash(&names[idx]);
+            let bucket = h as usize % nbuckets;
+            if let Some(last) = last_bucket {
+                if bucket < last {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "GNU hash symbols are not sorted by bucket"))
+                }
+            }
+            if buckets[bucket] == 0 {
+                buckets[bucket] = idx as u32;
+            }
+            last_bucket = Some(bucket);
+
+            let word = (h / bits) as usize % bloom_size;
+            bloom[word] |= 1 << (h % bits) | 1 << ((h >> bloom_shift) % bits);
+
+            //the low bit marks the last symbol of a bucket
+            let last_in_bucket = idx + 1 == names.len() ||
+                gnu_hash(&names[idx + 1]) as usize % nbuckets != bucket;
+            chains[idx - symoffset] = (h & !1) | last_in_bucket as u32;
+        }
+
+        Ok(GnuHash {
+            symoffset: symoffset as u32,
+            bloom_shift: bloom_shift,
+            bloom: bloom,
+            buckets: buckets,
+            chains: chains,
+        })
+    }
+
+    /* Index of the symbol with the provided name, given the names of the .dynsym symbols */
+    pub fn lookup(&self, name: &[u8], names: &[Vec<u8>], class: EXEC::EI_CLASS) -> Option<u32> {
+        let bits = (bloom_word_size(class).ok()? * 8) as u32;
+        if self.buckets.is_empty() || self.bloom.is_empty() {
+            return None
+        }
+        let h = gnu_hash(name);
+        let word = self.bloom[(h / bits) as usize % self.bloom.len()];
+        let mask = 1 << (h % bits) | 1 << ((h >> self.bloom_shift) % bits);
+        if word & mask != mask {
+            return None
+        }
+
+        let mut idx = self.buckets[h as usize % self.buckets.len()];
+        if idx == 0 || idx < self.symoffset {
+            return None
+        }
+        loop {
+            let chain = *self.chains.get((idx - self.symoffset) as usize)?;
+            if chain | 1 == h | 1 && names.get(idx as usize).map_or(false, |sym| &sym[..] == name) {
+                return Some(idx)
+            }
+            if chain & 1 == 1 {
+                return None
+            }
+            idx += 1;
+        }
+    }
+
+    pub fn to_bytes(&self, data: EXEC::EI_DATA,
+                    class: EXEC::EI_CLASS) -> Result<Vec<u8>, std::io::Error> {
+        let word = bloom_word_size(class)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        write_u32(&mut bytes, self.buckets.len() as u32, data)?;
+        write_u32(&mut bytes, self.symoffset, data)?;
+        write_u32(&mut bytes, self.bloom.len() as u32, data)?;
+        write_u32(&mut bytes, self.bloom_shift, data)?;
+        for &val in &self.bloom {
+            match (word, data) {
+                (4, EXEC::EI_DATA::ELFDATA2MSB) => bytes.write_u32::<BigEndian>(val as u32)?,
+                (4, _) => bytes.write_u32::<LittleEndian>(val as u32)?,
+                (_, EXEC::EI_DATA::ELFDATA2MSB) => bytes.write_u64::<BigEndian>(val)?,
+                (_, _) => bytes.write_u64::<LittleEndian>(val)?,
+            }
+        }
+        for &val in self.buckets.iter().chain(self.chains.iter()) {
+            write_u32(&mut bytes, val, data)?;
+        }
+        Ok(bytes)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Hash {
+    Sysv(SysvHash),
+    Gnu(GnuHash),
+}
+
+/*
+* Hash table object to hold a parsed SHT_HASH or SHT_GNU_HASH section. link_idx is the index
+* of the .dynsym section (sh_link) it indexes into.
+*/
+#[derive(Clone, Debug)]
+pub struct HashTable {
+    pub section_idx: u32,
+    pub sec_name: String,
+    pub link_idx: u32,
+    pub class: EXEC::EI_CLASS,
+    pub data: EXEC::EI_DATA,
+    pub hash: Hash,
+}
+
+impl HashTable {
+    pub fn parse_hash_table(bytes: &[u8], gnu: bool, idx: u32, name: String, link: u32,
+                            data: EXEC::EI_DATA,
+                            class: EXEC::EI_CLASS) -> Result<HashTable, std::io::Error> {
+        let hash = if gnu {
+            Hash::Gnu(GnuHash::parse(bytes, data, class)?)
+        } else {
+            Hash::Sysv(SysvHash::parse(bytes, data)?)
+        };
+        Ok(HashTable {
+            section_idx: idx,
+            sec_name: name,
+            link_idx: link,
+            class: class,
+            data: data,
+            hash: hash,
+        })
+    }
+
+    pub fn lookup(&self, name: &[u8], names: &[Vec<u8>]) -> Option<u32> {
+        match &self.hash {
+            Hash::Sysv(hash) => hash.lookup(name, names),
+            Hash::Gnu(hash) => hash.lookup(name, names, self.class),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        match &self.hash {
+            Hash::Sysv(hash) => hash.to_bytes(self.data),
+            Hash::Gnu(hash) => hash.to_bytes(self.data, self.class),
+        }
+    }
+}
+
+/* The GNU hash bloom filter is made of ELF class sized words */
+fn bloom_word_size(class: EXEC::EI_CLASS) -> Result<usize, std::io::Error> {
+    match class {
+        EXEC::EI_CLASS::ELFCLASS32 => Ok(4),
+        EXEC::EI_CLASS::ELFCLASS64 => Ok(8),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+fn read_u32s(bytes: &[u8], data: EXEC::EI_DATA) -> Result<Vec<u32>, std::io::Error> {
+    match data {
+        EXEC::EI_DATA::ELFDATA2LSB => {
+            Ok(bytes.chunks_exact(4).map(LittleEndian::read_u32).collect())
+        }
+        EXEC::EI_DATA::ELFDATA2MSB => {
+            Ok(bytes.chunks_exact(4).map(BigEndian::read_u32).collect())
+        }
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+fn write_u32<W>(wrtr: &mut W, val: u32, data: EXEC::EI_DATA) -> Result<(), std::io::Error>
+    where W: Write {
+    match data {
+        EXEC::EI_DATA::ELFDATA2LSB => wrtr.write_u32::<LittleEndian>(val),
+        EXEC::EI_DATA::ELFDATA2MSB => wrtr.write_u32::<BigEndian>(val),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* .dynsym names: the null symbol, two unhashed imports, then exports */
+    fn names() -> Vec<Vec<u8>> {
+        let mut names: Vec<Vec<u8>> = vec![b"".to_vec(), b"printf".to_vec(), b"exit".to_vec()];
+        names.extend((0..40).map(|i| format!("f{}", i).into_bytes()));
+        names.push(b"_ZNSt8ios_base4InitC1Ev".to_vec());
+        names
+    }
+
+    /* names with the symbols from symoffset on sorted by GNU hash bucket */
+    fn sorted_names(symoffset: usize, nbuckets: usize) -> Vec<Vec<u8>> {
+        let mut names = names();
+        names[symoffset..].sort_by_key(|name| GnuHash::bucket_of(name, nbuckets));
+        names
+    }
+
+    #[test]
+    fn hash_functions() {
+        assert_eq!(elf_hash(b""), 0);
+        assert_eq!(elf_hash(b"printf"), 0x077905a6);
+        assert_eq!(elf_hash(b"exit"), 0x0006cf04);
+        assert_eq!(elf_hash(b"_ZNSt8ios_base4InitC1Ev"), 0x0c0d71d6);
+        assert_eq!(gnu_hash(b""), 0x1505);
+        assert_eq!(gnu_hash(b"printf"), 0x156b2bb8);
+        assert_eq!(gnu_hash(b"exit"), 0x7c967e3f);
+        assert_eq!(gnu_hash(b"_ZNSt8ios_base4InitC1Ev"), 0x4cd4b8c7);
+    }
+
+    #[test]
+    fn sysv_round_trip() {
+        let names = names();
+        for &data in &[EXEC::EI_DATA::ELFDATA2LSB, EXEC::EI_DATA::ELFDATA2MSB] {
+            let built = SysvHash::build(&names, 7);
+            let bytes = built.to_bytes(data).unwrap();
+            assert_eq!(bytes.len(), 4 * (2 + 7 + names.len()));
+            let parsed = SysvHash::parse(&bytes, data).unwrap();
+            assert_eq!(parsed.buckets, built.buckets);
+            assert_eq!(parsed.chains, built.chains);
+            for (idx, name) in names.iter().enumerate().skip(1) {
+                assert_eq!(parsed.lookup(name, &names), Some(idx as u32));
+            }
+            assert_eq!(parsed.lookup(b"nosuch", &names), None);
+        }
+    }
+
+    #[test]
+    fn gnu_round_trip() {
+        let symoffset = 3;
+        for &(class, word) in &[(EXEC::EI_CLASS::ELFCLASS32, 4), (EXEC::EI_CLASS::ELFCLASS64, 8)] {
+            let names = sorted_names(symoffset, 5);
+            let built = GnuHash::build(&names, symoffset, 5, 2, 6, class).unwrap();
+            let table = HashTable {
+                section_idx: 1,
+                sec_name: ".gnu.hash".to_string(),
+                link_idx: 2,
+                class: class,
+                data: EXEC::EI_DATA::ELFDATA2LSB,
+                hash: Hash::Gnu(built),
+            };
+            let bytes = table.to_bytes().unwrap();
+            assert_eq!(bytes.len(), 16 + 2 * word + 4 * (5 + names.len() - symoffset));
+
+            let parsed = HashTable::parse_hash_table(&bytes, true, 1, ".gnu.hash".to_string(), 2,
+                                                     EXEC::EI_DATA::ELFDATA2LSB, class).unwrap();
+            assert_eq!(parsed.to_bytes().unwrap(), bytes);
+            for (idx, name) in names.iter().enumerate().skip(symoffset) {
+                assert_eq!(parsed.lookup(name, &names), Some(idx as u32));
+            }
+            //symbols below symoffset are not hashed
+            assert_eq!(parsed.lookup(b"printf", &names), None);
+            assert_eq!(parsed.lookup(b"exit", &names), None);
+            assert_eq!(parsed.lookup(b"nosuch", &names), None);
+        }
+    }
+
+    #[test]
+    fn gnu_chain_ends() {
+        let names = sorted_names(1, 3);
+        let built = GnuHash::build(&names, 1, 3, 1, 5, EXEC::EI_CLASS::ELFCLASS64).unwrap();
+        //one chain end per non-empty bucket, on the last symbol of the table in particular
+        let ends = built.chains.iter().filter(|&&chain| chain & 1 == 1).count();
+        assert_eq!(ends, built.buckets.iter().filter(|&&bucket| bucket != 0).count());
+        assert_eq!(built.chains.last().unwrap() & 1, 1);
+        for (idx, name) in names.iter().enumerate().skip(1) {
+            assert_eq!(built.lookup(name, &names, EXEC::EI_CLASS::ELFCLASS64), Some(idx as u32));
+        }
+    }
+
+    #[test]
+    fn gnu_unsorted_symbols() {
+        let mut names = sorted_names(1, 4);
+        names[1..].reverse();
+        assert!(GnuHash::build(&names, 1, 4, 1, 5, EXEC::EI_CLASS::ELFCLASS64).is_err());
+    }
+}
@@ -471,6 +471,28 @@ impl ExecutiveHeader {
         }
     }
 
+    pub fn elf_type(&self)-> u16 {
+        match &self.EH {
+            ExecHeader::ThirtyTwo(exec32) => {
+                exec32.e_type
+            },
+            ExecHeader::SixtyFour(exec64)=>{
+                exec64.e_type
+            },
+        }
+    }
+
+    pub fn machine(&self)-> u16 {
+        match &self.EH {
+            ExecHeader::ThirtyTwo(exec32) => {
+                exec32.e_machine
+            },
+            ExecHeader::SixtyFour(exec64)=>{
+                exec64.e_machine
+            },
+        }
+    }
+
     pub fn ph_entry_num(&self)-> u16 {
         match &self.EH {
             ExecHeader::ThirtyTwo(exec32) => {
@@ -742,7 +764,8 @@ pub fn match_mach_as_str(mach: String) -> Result<u16, std::io::Error> {
         "EM_ME16" => Ok(59),     /*Toyota ME16 processor */
         "EM_ST100" => Ok(60),    /*STMicroelectronics ST100 processor */
         "EM_TINYJ" => Ok(61),    /*Advanced Logic Corp. TinyJ embedded processor family */
-        //Reserved 	62-65 	/*Reserved for future use */
+        "EM_X86_64" => Ok(62),   /*AMD x86-64 architecture */
+        //Reserved 	63-65 	/*Reserved for future use */
         "EM_FX66" => Ok(66),     /*Siemens FX66 microcontroller */
         "EM_ST9PLUS" => Ok(67),  /*	STMicroelectronics ST9+ 8/16 bit microcontroller */
         "EM_ST7" => Ok(68),      /*STMicroelectronics ST7 8-bit microcontroller */
@@ -941,7 +964,8 @@ pub fn match_mach(mach: u16) -> Result<EXEC::EI_MACH, std::io::Error> {
         59=> Ok(EXEC::EI_MACH::EM_ME16 ),    //Toyota ME16 processor
         60=> Ok(EXEC::EI_MACH::EM_ST100 ),    //STMicroelectronics ST100 processor
         61=> Ok(EXEC::EI_MACH::EM_TINYJ ),  //Advanced Logic Corp. TinyJ embedded processor family
-        //Reserved 	62-65 	//Reserved for future use
+        62=> Ok(EXEC::EI_MACH::EM_X86_64 ), //AMD x86-64 architecture
+        //Reserved 	63-65 	//Reserved for future use
         66=> Ok(EXEC::EI_MACH::EM_FX66 ),     //Siemens FX66 microcontroller
         67=> Ok(EXEC::EI_MACH::EM_ST9PLUS ),  //	STMicroelectronics ST9+ 8/16 bit microcontroller
         68=> Ok(EXEC::EI_MACH::EM_ST7 ),     //STMicroelectronics ST7 8-bit microcontroller
@@ -1191,7 +1215,8 @@ pub(super) mod EXEC {
         EM_ME16 = 59,     //Toyota ME16 processor
         EM_ST100 = 60,    //STMicroelectronics ST100 processor
         EM_TINYJ = 61,    //Advanced Logic Corp. TinyJ embedded processor family
-        //Reserved 	62-65 	//Reserved for future use
+        EM_X86_64 = 62,   //AMD x86-64 architecture
+        //Reserved 	63-65 	//Reserved for future use
         EM_FX66 = 66,     //Siemens FX66 microcontroller
         EM_ST9PLUS = 67,  //	STMicroelectronics ST9+ 8/16 bit microcontroller
         EM_ST7 = 68,      //STMicroelectronics ST7 8-bit microcontroller
@@ -1387,7 +1412,8 @@ pub(super) mod EXEC {
                 EI_MACH::EM_ME16 => 59,     //Toyota ME16 processor
                 EI_MACH::EM_ST100 => 60,    //STMicroelectronics ST100 processor
                 EI_MACH::EM_TINYJ => 61,    //Advanced Logic Corp. TinyJ embedded processor family
-                //Reserved 	62-65 	//Reserved for future use
+                EI_MACH::EM_X86_64 => 62,   //AMD x86-64 architecture
+                //Reserved 	63-65 	//Reserved for future use
                 EI_MACH::EM_FX66 => 66,     //Siemens FX66 microcontroller
                 EI_MACH::EM_ST9PLUS => 67,  //	STMicroelectronics ST9+ 8/16 bit microcontroller
                 EI_MACH::EM_ST7 => 68,      //STMicroelectronics ST7 8-bit microcontroller
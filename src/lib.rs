@@ -5,6 +5,7 @@
 extern crate byteorder;
 extern crate intervaltree;
 extern crate argparse;
+extern crate serde_json;
 
 pub mod config;
 pub mod header;
@@ -14,6 +15,10 @@ pub mod segment;
 pub mod dedelf;
 pub mod symbols;
 pub mod relocations;
+pub mod dynamic;
+pub mod hash;
+pub mod trampoline;
+pub mod linker;
 
 
 use std::env;
@@ -34,6 +39,9 @@ pub fn display_useage() {
 \t\t\t`exec_header`\n\
 \t\t\t`sec_header`\n\
 \t\t\t`prog_header`\n\
+\t\t\t`relocation`\n\
+\t\t\t`symbol`\n\
+\t\t\t`dynamic`\n\
 \t\tTo modify the exec header, provide the value to change (using\n\
 \t\tELF specification exec header struct fields) followed by \n\
 \t\ta valid replacement type. For section and program header \n\
@@ -42,6 +50,35 @@ pub fn display_useage() {
 \t\tsection name (for section headers with string table entries\n\
 \t\tonly) or header index (for either section or program) within\n\
 \t\tthe respective header table, and specify replacement value.\n\
+\t\tFor relocation modifications, provide the relocation section\n\
+\t\tname or index, optionally followed by `:<entry index or symbol>`\n\
+\t\t(e.g. -p .rela.dyn:0x2), a relocation entry field (r_offset,\n\
+\t\tr_info, r_sym, r_type, r_addend) or `add`/`delete`, and the\n\
+\t\treplacement value.\n\
+\t\tFor symbol modifications, provide `[<table>:]<symbol>` with -p,\n\
+\t\twhere symbol is a 0x prefixed index or a name (e.g. -p .dynsym:f4),\n\
+\t\tan Elf_Sym field (st_value, st_size, st_bind, st_type,\n\
+\t\tst_visibility, st_shndx...) and the replacement value. Use -p\n\
+\t\t<table> -f add -r name,value,size,type,bind[,shndx] to add a\n\
+\t\tsymbol, or -f rename -r <new name> to rename symbols.\n\
+\t\t--rename-symbol <old>:<new> and --rename-section <old>:<new>\n\
+\t\tare shorthand for renaming a symbol or a section (the\n\
+\t\tsec_header field `rename`).\n\
+\t\tFor dynamic modifications, provide add_needed, remove_needed\n\
+\t\tor replace_needed and the library name (`<old>:<new>` to\n\
+\t\treplace), or use --add-needed, --remove-needed and\n\
+\t\t--replace-needed as shorthand. Use set_rpath, set_runpath or\n\
+\t\tset_soname (or --set-rpath, --set-runpath, --set-soname) with\n\
+\t\tthe new string, and remove_rpath, remove_runpath,\n\
+\t\tremove_soname, convert_rpath or convert_runpath with -r none.\n\
+\t\tUse set_interpreter (or --set-interpreter) with the new\n\
+\t\tprogram interpreter path, and set_flags or clear_flags with\n\
+\t\tDT_FLAGS/DT_FLAGS_1 names separated by `|` (e.g. BIND_NOW|NOW).\n\
+\tconfig file:\n\
+\t\t--file-config <config file>\n\
+\t\tUse a .json config file to specify the mode, infile, outfile\n\
+\t\tand all options (see tests/dedelf.json). Use mode `dual` to\n\
+\t\tperform both injection and modification in the same pass.\n\
 \nOUTPUT:\n\
 \t-o <output file>\n\t\tUse -o to specify a filename to write \n\
 \t\tcontents to. If not specified, the default of `_inj` \n\
@@ -56,12 +93,33 @@ pub fn display_useage() {
 \t\tSet the section that gets extended to the value as long as it \n\
 \t\tis valid e.g. has an entry in the section header string table.\n\
 \t\tif not set, the .text section is the default extended section\n\
+\t\t<symbol>+0x<off> selects the section containing that address\n\
 \t-e <offset in base 16>\n\
 \t\tChange the entry point of the executive header to the value\n\
 \t\trelative to the byte offset of the injected bytes, \n\
 \t\te.g., if a value of 0x10 is supplied, and the injection\n\
 \t\t site starts at byte offset 0x1000, then the entry point \n\
 \t\tin the executive header will be modified to be 0x1010\n\
+\t\tThe entry point may also be given as <symbol>+0x<off>\n\
+\t--trampoline\n\
+\t\tWrap the injected bytes in a trampoline that saves registers\n\
+\t\tand returns to the original entry point, and set the entry\n\
+\t\tpoint to the trampoline (cannot be combined with -e)\n\
+\t--thumb\n\
+\t\tBuild the trampoline from Thumb instructions (ARM only)\n\
+\t--strategy <extend|cave|note|padding|reverse>\n\
+\t\tSet the injection strategy. `extend` (default) grows the\n\
+\t\tsegment containing the injection site, `cave` overwrites a\n\
+\t\tcode cave (a run of padding bytes in an executable segment)\n\
+\t\twithout changing the file layout. Use -b to pick the cave.\n\
+\t\t`note` converts a PT_NOTE segment into an executable PT_LOAD\n\
+\t\tsegment at the end of the file. Use -p to pick the PT_NOTE\n\
+\t\tprogram header index. `padding` injects into the padding after\n\
+\t\tthe text segment, and `reverse` grows the text segment\n\
+\t\tdownward by whole pages.\n\
+\t--list-caves\n\
+\t\tList the code caves large enough for the injection file,\n\
+\t\twith their offsets and vaddrs, without writing a file\n\
 \nExamples:\n\
 \n{} modify path/to/target/file -m exec_header -f eh_entry -r 0x50250 \n\
 \tThis command will modify the entry point in the executive header\n\
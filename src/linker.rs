@@ -0,0 +1,632 @@
+use std::fs::File;
+use std::io::Read;
+
+use crate::header::*;
+use crate::parser::{ElfParser, align_up};
+use crate::section::{SH_Type, SH_Flags};
+use crate::symbols::*;
+use crate::relocations::{RelSymbol, X86_64_Reloc, I386_Reloc, AArch64_Reloc};
+
+/* A relocation entry of the payload, with the implicit addend of REL entries already read */
+#[derive(Clone, Debug)]
+struct Reloc {
+    sec_idx: usize,
+    offset: u64,
+    symbol: Option<RelSymbol>,
+    rtype: u32,
+    addend: i64,
+}
+
+/*
+* Links a relocatable object (ET_REL) into a single image, so it can be injected as a payload:
+* its SHF_ALLOC sections are laid out one after another, and its REL/RELA entries are applied
+* against the vaddr the image is placed at. Undefined symbols are resolved against the .symtab
+* and .dynsym of the target. Supported for x86-64, i386 and AArch64 little endian objects.
+*/
+pub struct Linker {
+    pub object: ElfParser,
+    //(section index, image offset) of each placed section
+    pub placements: Vec<(usize, u64)>,
+    pub size: u64,
+    pub align: u64,
+    //image offset the injected code is entered at
+    pub entry: u64,
+    relocs: Vec<Reloc>,
+}
+
+/* Check whether a file is an ELF relocatable object, without parsing it */
+pub fn is_relocatable(file: &str) -> bool {
+    let mut ident = [0; 18];
+    let read = File::open(file).and_then(|mut fp| fp.read_exact(&mut ident));
+    if read.is_err() || &ident[..4] != b"\x7fELF" {
+        return false
+    }
+    let e_type = match ident[EXEC::_EI_DATA] {
+        2 => u16::from_be_bytes([ident[16], ident[17]]),
+        _ => u16::from_le_bytes([ident[16], ident[17]]),
+    };
+    e_type == EXEC::EI_TYPE::ET_REL as u16
+}
+
+impl Linker {
+    /*
+    * Lay out the object. The image is entered at the section or symbol named entry if given,
+    * otherwise at the start of the first executable section.
+    */
+    pub fn new(object: ElfParser, entry: Option<&str>) -> Result<Linker, std::io::Error> {
+        match object.header.data {
+            EXEC::EI_DATA::ELFDATA2LSB => {}
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Linking is only supported for little endian \
+                                                objects")),
+        }
+        match match_mach(object.header.machine()) {
+            Ok(EXEC::EI_MACH::EM_386) | Ok(EXEC::EI_MACH::EM_X86_64)
+            | Ok(EXEC::EI_MACH::EM_AARCH64) => {}
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Linking is not supported for this machine type")),
+        }
+
+        let mut placements = vec![];
+        let mut size = 0;
+        let mut align = 1;
+        for (i, sec) in object.sections.iter().enumerate() {
+            //notes and unwind tables are not needed to run the payload
+            if sec.flags() & SH_Flags::SHF_ALLOC as u64 == 0 || sec.size() == 0
+                || sec.shtype_as_u32() == SH_Type::SHT_NOTE as u32 || sec.name() == ".eh_frame" {
+                continue
+            }
+            let sec_align = sec.addralign().max(1);
+            size = align_up(size, sec_align);
+            placements.push((i, size));
+            size += sec.size();
+            align = align.max(sec_align);
+        }
+        if placements.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Object has no sections to link"))
+        }
+
+        let relocs = read_relocs(&object, &placements);
+        let mut linker = Linker {
+            object: object,
+            placements: placements,
+            size: size,
+            align: align,
+            entry: 0,
+            relocs: relocs,
+        };
+        linker.entry = linker.entry_offset(entry)?;
+        Ok(linker)
+    }
+
+    /* Size to reserve for the image when its vaddr alignment is not yet known */
+    pub fn padded_size(&self) -> u64 {
+        self.size + self.align - 1
+    }
+
+    /* Build the image for vaddr (which must be aligned to self.align), relocated for target */
+    pub fn link(&self, vaddr: u64, target: &ElfParser) -> Result<Vec<u8>, std::io::Error> {
+        let mut image = vec![0; self.size as usize];
+        for &(sec_idx, offset) in &self.placements {
+            let sec = &self.object.sections[sec_idx];
+            if sec.shtype_as_u32() != SH_Type::SHT_NOBITS as u32 {
+                let bytes = sec.raw_bytes();
+                image[offset as usize..offset as usize + bytes.len()].copy_from_slice(&bytes);
+            }
+        }
+
+        let pie = target.header.elf_type() == EXEC::EI_TYPE::ET_DYN as u16;
+        for reloc in &self.relocs {
+            let s = match &reloc.symbol {
+                Some(sym) => self.symbol_vaddr(sym, vaddr, target)?,
+                None => 0,
+            };
+            let offset = self.image_offset(reloc.sec_idx).unwrap() + reloc.offset;
+            let p = vaddr + offset;
+            match match_mach(self.object.header.machine()) {
+                Ok(EXEC::EI_MACH::EM_X86_64) => apply_x86_64(&mut image, offset, reloc, s, p, pie)?,
+                Ok(EXEC::EI_MACH::EM_386) => apply_i386(&mut image, offset, reloc, s, p, pie)?,
+                _ => apply_aarch64(&mut image, offset, reloc, s, p, pie)?,
+            }
+        }
+        Ok(image)
+    }
+
+    fn image_offset(&self, sec_idx: usize) -> Option<u64> {
+        self.placements.iter().find(|(idx, _)| *idx == sec_idx).map(|(_, offset)| *offset)
+    }
+
+    fn entry_offset(&self, entry: Option<&str>) -> Result<u64, std::io::Error> {
+        let name = match entry {
+            Some(name) => name,
+            None => {
+                let exec = self.placements.iter().find(|(idx, _)| {
+                    self.object.sections[*idx].flags() & SH_Flags::SHF_EXECINSTR as u64 != 0
+                });
+                return Ok(exec.map_or(0, |(_, offset)| *offset))
+            }
+        };
+        if let Some(sec_idx) = self.object.sections.iter().position(|sec| sec.name() == name) {
+            return self.image_offset(sec_idx).ok_or(std::io::Error::new(
+                std::io::ErrorKind::Other, format!("Section {} is not linked", name)))
+        }
+        let sym = self.object.lookup_symbol(name);
+        match sym.and_then(|(value, _, shndx)| self.image_offset(shndx as usize).map(|off| off + value)) {
+            Some(offset) => Ok(offset),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("No linked section or symbol named {}", name))),
+        }
+    }
+
+    /* Resolve a payload symbol to its vaddr, looking up undefined symbols in the target */
+    fn symbol_vaddr(&self, sym: &RelSymbol, vaddr: u64,
+                    target: &ElfParser) -> Result<u64, std::io::Error> {
+        match sym.shndx {
+            shndx if shndx == SHN::SHN_ABS as u16 => Ok(sym.value),
+            shndx if shndx == SHN::SHN_COMMON as u16 => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                  format!("Common symbol {} is not supported, \
+                                                  compile the payload with -fno-common", sym.name))),
+            shndx if shndx == SHN::SHN_UNDEF as u16 => {
+                if sym.name.is_empty() {
+                    return Ok(0)
+                }
+                match target.lookup_symbol(&sym.name) {
+                    Some((value, _, shndx)) if shndx != SHN::SHN_UNDEF as u16 => Ok(value),
+                    //the PLT of a position independent i386 target expects %ebx to hold its
+                    // GOT address, which the payload has no way of setting up
+                    _ if target.header.machine() == EXEC::EI_MACH::EM_386 as u16
+                        && target.header.elf_type() == EXEC::EI_TYPE::ET_DYN as u16 => {
+                        Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                format!("Imported function {} cannot be called \
+                                                from an i386 payload through the PLT of a \
+                                                position independent target", sym.name)))
+                    }
+                    //functions imported by the target are called through its PLT
+                    _ => plt_entry(target, &sym.name).ok_or(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("Undefined symbol {} is not defined in the target", sym.name))),
+                }
+            }
+            shndx => {
+                let offset = match self.image_offset(shndx as usize) {
+                    Some(offset) => offset,
+                    None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           format!("Symbol {} is in a section \
+                                                           that is not linked", sym.name))),
+                };
+                let value = if sym.info & 0xf == ST_type::STT_SECTION as u8 { 0 } else { sym.value };
+                Ok(vaddr + offset + value)
+            }
+        }
+    }
+}
+
+/*
+* Find the PLT stub of a function imported by the target, from the order of its JUMP_SLOT
+* relocation in .rela.plt (or .rel.plt). Assumes the standard PLT layout of ld, i.e. a PLT
+* header followed by 16 byte entries, or the entries in .plt.sec when present (x86 IBT).
+*/
+fn plt_entry(target: &ElfParser, name: &str) -> Option<u64> {
+    let relplt = target.rel_tables.iter()
+        .find(|table| table.sec_name == ".rela.plt" || table.sec_name == ".rel.plt")?;
+    let slot = relplt.entries.iter()
+        .position(|ent| ent.symbol.as_ref().map_or(false, |sym| sym.name == name))? as u64;
+
+    let find = |name: &str| target.sections.iter().find(|sec| sec.name() == name);
+    match (find(".plt.sec"), find(".plt")) {
+        (Some(plt_sec), _) => Some(plt_sec.addr() + 16 * slot),
+        (None, Some(plt)) if target.header.machine() == EXEC::EI_MACH::EM_AARCH64 as u16 => {
+            Some(plt.addr() + 32 + 16 * slot)
+        }
+        (None, Some(plt)) => Some(plt.addr() + 16 * (slot + 1)),
+        _ => None,
+    }
+}
+
+/* Read the REL and RELA entries applying to placed sections */
+fn read_relocs(object: &ElfParser, placements: &[(usize, u64)]) -> Vec<Reloc> {
+    let mut relocs = vec![];
+    for table in &object.rel_tables {
+        let target_idx = table.info_idx as usize;
+        if !placements.iter().any(|(idx, _)| *idx == target_idx) {
+            continue
+        }
+        for ent in &table.entries {
+            let offset = ent.reloc.offset();
+            let addend = match ent.reloc.addend() {
+                Some(addend) => addend,
+                None => {
+                    //REL entries keep the addend in the relocated field, always 32 bits wide here
+                    let sec_bytes = object.sections[target_idx].raw_bytes();
+                    match sec_bytes.get(offset as usize..offset as usize + 4) {
+                        Some(field) => i32::from_le_bytes([field[0], field[1], field[2], field[3]]) as i64,
+                        None => 0,
+                    }
+                }
+            };
+            relocs.push(Reloc {
+                sec_idx: target_idx,
+                offset: offset,
+                symbol: ent.symbol.clone(),
+                rtype: ent.reloc.rtype(),
+                addend: addend,
+            });
+        }
+    }
+    relocs
+}
+
+fn apply_x86_64(image: &mut [u8], offset: u64, reloc: &Reloc, s: u64, p: u64,
+                pie: bool) -> Result<(), std::io::Error> {
+    let value = s.wrapping_add(reloc.addend as u64);
+    match X86_64_Reloc::from_u32(reloc.rtype) {
+        Some(X86_64_Reloc::R_X86_64_NONE) => Ok(()),
+        Some(X86_64_Reloc::R_X86_64_64) => {
+            absolute_allowed(reloc, pie)?;
+            write_field(image, offset, &value.to_le_bytes())
+        }
+        Some(X86_64_Reloc::R_X86_64_PC32) | Some(X86_64_Reloc::R_X86_64_PLT32) => {
+            write_pc32(image, offset, reloc, value, p)
+        }
+        Some(rtype @ X86_64_Reloc::R_X86_64_32) | Some(rtype @ X86_64_Reloc::R_X86_64_32S) => {
+            absolute_allowed(reloc, pie)?;
+            let fits = if rtype == X86_64_Reloc::R_X86_64_32 {
+                value <= u32::MAX as u64
+            } else {
+                (i32::MIN as i64..=i32::MAX as i64).contains(&(value as i64))
+            };
+            if !fits {
+                return Err(overflow(reloc))
+            }
+            write_field(image, offset, &(value as u32).to_le_bytes())
+        }
+        Some(X86_64_Reloc::R_X86_64_PC64) => {
+            write_field(image, offset, &value.wrapping_sub(p).to_le_bytes())
+        }
+        Some(X86_64_Reloc::R_X86_64_GOTPCRELX) | Some(X86_64_Reloc::R_X86_64_REX_GOTPCRELX) => {
+            //there is no GOT, so relax the GOT load to a direct reference like ld does
+            let op = offset as usize;
+            if op < 2 || op + 4 > image.len() {
+                return Err(unsupported(reloc))
+            }
+            match (image[op - 2], image[op - 1]) {
+                (0x8b, _) => image[op - 2] = 0x8d, //mov foo@GOTPCREL(%rip), %reg -> lea
+                (0xff, 0x15) => { //call *foo@GOTPCREL(%rip) -> addr32 call foo
+                    image[op - 2] = 0x67;
+                    image[op - 1] = 0xe8;
+                }
+                (0xff, 0x25) => { //jmp *foo@GOTPCREL(%rip) -> jmp foo; nop
+                    image[op - 2] = 0xe9;
+                    write_pc32(image, offset - 1, reloc, value, p - 1)?;
+                    image[op + 3] = 0x90;
+                    return Ok(())
+                }
+                _ => return Err(unsupported(reloc)),
+            }
+            write_pc32(image, offset, reloc, value, p)
+        }
+        _ => Err(unsupported(reloc)),
+    }
+}
+
+fn apply_i386(image: &mut [u8], offset: u64, reloc: &Reloc, s: u64, p: u64,
+              pie: bool) -> Result<(), std::io::Error> {
+    let value = (s as u32).wrapping_add(reloc.addend as u32);
+    match I386_Reloc::from_u32(reloc.rtype) {
+        Some(I386_Reloc::R_386_NONE) => Ok(()),
+        Some(I386_Reloc::R_386_32) => {
+            absolute_allowed(reloc, pie)?;
+            write_field(image, offset, &value.to_le_bytes())
+        }
+        Some(I386_Reloc::R_386_PC32) | Some(I386_Reloc::R_386_PLT32) => {
+            write_field(image, offset, &value.wrapping_sub(p as u32).to_le_bytes())
+        }
+        _ => Err(unsupported(reloc)),
+    }
+}
+
+fn apply_aarch64(image: &mut [u8], offset: u64, reloc: &Reloc, s: u64, p: u64,
+                 pie: bool) -> Result<(), std::io::Error> {
+    let value = s.wrapping_add(reloc.addend as u64);
+    let insn = image.get(offset as usize..offset as usize + 4)
+        .map_or(0, |field| u32::from_le_bytes([field[0], field[1], field[2], field[3]]));
+    let insn = match AArch64_Reloc::from_u32(reloc.rtype) {
+        Some(AArch64_Reloc::R_AARCH64_NONE) | Some(AArch64_Reloc::R_AARCH64_NULL) => return Ok(()),
+        Some(AArch64_Reloc::R_AARCH64_ABS64) => {
+            absolute_allowed(reloc, pie)?;
+            return write_field(image, offset, &value.to_le_bytes())
+        }
+        Some(AArch64_Reloc::R_AARCH64_ABS32) => {
+            absolute_allowed(reloc, pie)?;
+            if value > u32::MAX as u64 {
+                return Err(overflow(reloc))
+            }
+            return write_field(image, offset, &(value as u32).to_le_bytes())
+        }
+        Some(AArch64_Reloc::R_AARCH64_PREL64) => {
+            return write_field(image, offset, &value.wrapping_sub(p).to_le_bytes())
+        }
+        Some(AArch64_Reloc::R_AARCH64_PREL32) => return write_pc32(image, offset, reloc, value, p),
+        Some(rtype @ AArch64_Reloc::R_AARCH64_ADR_PREL_PG_HI21)
+        | Some(rtype @ AArch64_Reloc::R_AARCH64_ADR_PREL_PG_HI21_NC) => {
+            let pages = ((value & !0xfff) as i64).wrapping_sub((p & !0xfff) as i64) >> 12;
+            if rtype == AArch64_Reloc::R_AARCH64_ADR_PREL_PG_HI21
+                && !(-(1 << 20)..1 << 20).contains(&pages) {
+                return Err(overflow(reloc))
+            }
+            let pages = pages as u32;
+            insn & !0x60ffffe0 | (pages & 3) << 29 | (pages >> 2 & 0x7ffff) << 5
+        }
+        Some(AArch64_Reloc::R_AARCH64_ADD_ABS_LO12_NC) => {
+            insn & !(0xfff << 10) | (value as u32 & 0xfff) << 10
+        }
+        Some(rtype @ AArch64_Reloc::R_AARCH64_LDST8_ABS_LO12_NC)
+        | Some(rtype @ AArch64_Reloc::R_AARCH64_LDST16_ABS_LO12_NC)
+        | Some(rtype @ AArch64_Reloc::R_AARCH64_LDST32_ABS_LO12_NC)
+        | Some(rtype @ AArch64_Reloc::R_AARCH64_LDST64_ABS_LO12_NC)
+        | Some(rtype @ AArch64_Reloc::R_AARCH64_LDST128_ABS_LO12_NC) => {
+            //the offset is scaled by the access size
+            let shift = match rtype {
+                AArch64_Reloc::R_AARCH64_LDST8_ABS_LO12_NC => 0,
+                AArch64_Reloc::R_AARCH64_LDST16_ABS_LO12_NC => 1,
+                AArch64_Reloc::R_AARCH64_LDST32_ABS_LO12_NC => 2,
+                AArch64_Reloc::R_AARCH64_LDST64_ABS_LO12_NC => 3,
+                _ => 4,
+            };
+            insn & !(0xfff << 10) | ((value as u32 & 0xfff) >> shift) << 10
+        }
+        Some(AArch64_Reloc::R_AARCH64_JUMP26) | Some(AArch64_Reloc::R_AARCH64_CALL26) => {
+            let disp = value.wrapping_sub(p) as i64;
+            if !(-(1 << 27)..1 << 27).contains(&disp) {
+                return Err(overflow(reloc))
+            }
+            insn & !0x3ffffff | (disp >> 2) as u32 & 0x3ffffff
+        }
+        _ => return Err(unsupported(reloc)),
+    };
+    write_field(image, offset, &insn.to_le_bytes())
+}
+
+fn write_pc32(image: &mut [u8], offset: u64, reloc: &Reloc, value: u64,
+              p: u64) -> Result<(), std::io::Error> {
+    let disp = value.wrapping_sub(p) as i64;
+    if !(i32::MIN as i64..=i32::MAX as i64).contains(&disp) {
+        return Err(overflow(reloc))
+    }
+    write_field(image, offset, &(disp as i32).to_le_bytes())
+}
+
+fn write_field(image: &mut [u8], offset: u64, bytes: &[u8]) -> Result<(), std::io::Error> {
+    let offset = offset as usize;
+    match image.get_mut(offset..offset + bytes.len()) {
+        Some(field) => {
+            field.copy_from_slice(bytes);
+            Ok(())
+        }
+        None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                        "Relocation is outside of its section")),
+    }
+}
+
+/* Absolute addresses are only known ahead of time if the target is not position independent */
+fn absolute_allowed(reloc: &Reloc, pie: bool) -> Result<(), std::io::Error> {
+    if pie {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                       format!("Absolute relocation type {} cannot be applied for a \
+                                       position independent target, compile the payload with \
+                                       -fpie", reloc.rtype)))
+    }
+    Ok(())
+}
+
+fn unsupported(reloc: &Reloc) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other,
+                        format!("Unsupported relocation type {}", reloc.rtype))
+}
+
+fn overflow(reloc: &Reloc) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other,
+                        format!("Relocation type {} overflows at the injection site", reloc.rtype))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reloc(rtype: u32, offset: u64, addend: i64) -> Reloc {
+        Reloc {
+            sec_idx: 1,
+            offset: offset,
+            symbol: None,
+            rtype: rtype,
+            addend: addend,
+        }
+    }
+
+    fn word(image: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([image[offset], image[offset + 1], image[offset + 2], image[offset + 3]])
+    }
+
+    #[test]
+    fn x86_64_pc32_and_absolute() {
+        let mut image = vec![0xe8, 0, 0, 0, 0];
+        //call foo: S + A - P with A = -4
+        apply_x86_64(&mut image, 1, &reloc(4, 1, -4), 0x2000, 0x1001, true).unwrap();
+        assert_eq!(image, vec![0xe8, 0xfb, 0x0f, 0, 0]);
+        apply_x86_64(&mut image, 1, &reloc(2, 1, -4), 0x800, 0x1001, true).unwrap();
+        assert_eq!(image, vec![0xe8, 0xfb, 0xf7, 0xff, 0xff]);
+        assert!(apply_x86_64(&mut image, 1, &reloc(2, 1, -4), 0x1_0000_2000, 0x1001, true)
+            .is_err());
+
+        let mut image = vec![0; 8];
+        apply_x86_64(&mut image, 0, &reloc(1, 0, 8), 0x401000, 0, false).unwrap();
+        assert_eq!(image, 0x401008u64.to_le_bytes().to_vec());
+        assert!(apply_x86_64(&mut image, 0, &reloc(1, 0, 0), 0x401000, 0, true).is_err());
+
+        //R_X86_64_32 zero extends, R_X86_64_32S sign extends
+        assert!(apply_x86_64(&mut image, 0, &reloc(10, 0, 0), 0xffffffff, 0, false).is_ok());
+        assert!(apply_x86_64(&mut image, 0, &reloc(10, 0, 0), 0x1_0000_0000, 0, false).is_err());
+        apply_x86_64(&mut image, 0, &reloc(11, 0, 0), 0xffffffff80000000, 0, false).unwrap();
+        assert_eq!(word(&image, 0), 0x80000000);
+        assert!(apply_x86_64(&mut image, 0, &reloc(11, 0, 0), 0x80000000, 0, false).is_err());
+
+        //relocations outside of the image
+        assert!(apply_x86_64(&mut image, 6, &reloc(2, 6, -4), 0x1000, 0, true).is_err());
+    }
+
+    #[test]
+    fn x86_64_gotpcrelx_relaxation() {
+        //mov foo@GOTPCREL(%rip), %rax -> lea foo(%rip), %rax
+        let mut image = vec![0x48, 0x8b, 0x05, 0, 0, 0, 0];
+        apply_x86_64(&mut image, 3, &reloc(42, 3, -4), 0x3000, 0x1003, true).unwrap();
+        assert_eq!(image, vec![0x48, 0x8d, 0x05, 0xf9, 0x1f, 0, 0]);
+
+        //call *foo@GOTPCREL(%rip) -> addr32 call foo
+        let mut image = vec![0xff, 0x15, 0, 0, 0, 0];
+        apply_x86_64(&mut image, 2, &reloc(41, 2, -4), 0x3000, 0x1002, true).unwrap();
+        assert_eq!(image, vec![0x67, 0xe8, 0xfa, 0x1f, 0, 0]);
+
+        //jmp *foo@GOTPCREL(%rip) -> jmp foo; nop, the jmp ending at 0x1005
+        let mut image = vec![0xff, 0x25, 0, 0, 0, 0];
+        apply_x86_64(&mut image, 2, &reloc(41, 2, -4), 0x3000, 0x1002, true).unwrap();
+        assert_eq!(image, vec![0xe9, 0xfb, 0x1f, 0, 0, 0x90]);
+
+        //other instructions cannot be relaxed
+        let mut image = vec![0x48, 0x03, 0x05, 0, 0, 0, 0];
+        assert!(apply_x86_64(&mut image, 3, &reloc(42, 3, -4), 0x3000, 0x1003, true).is_err());
+        assert!(apply_x86_64(&mut image, 1, &reloc(42, 1, -4), 0x3000, 0x1001, true).is_err());
+    }
+
+    #[test]
+    fn i386_relocations() {
+        let mut image = vec![0; 4];
+        apply_i386(&mut image, 0, &reloc(2, 0, -4), 0x8049000, 0x8048010, false).unwrap();
+        assert_eq!(word(&image, 0), 0xfec);
+        apply_i386(&mut image, 0, &reloc(1, 0, 4), 0x8049000, 0, false).unwrap();
+        assert_eq!(word(&image, 0), 0x8049004);
+        assert!(apply_i386(&mut image, 0, &reloc(1, 0, 4), 0x8049000, 0, true).is_err());
+    }
+
+    #[test]
+    fn aarch64_adrp_lo12() {
+        //adrp x0, foo; add x0, x0, :lo12:foo; ldr x1, [x0, :lo12:foo]
+        let mut image: Vec<u8> = [0x90000000u32, 0x91000000, 0xf9400001].iter()
+            .flat_map(|insn| insn.to_le_bytes().to_vec()).collect();
+        apply_aarch64(&mut image, 0, &reloc(275, 0, 0x345), 0x412000, 0x400010, true).unwrap();
+        assert_eq!(word(&image, 0), 0xd0000080); //adrp x0, . + 0x12000
+        apply_aarch64(&mut image, 4, &reloc(277, 4, 0x345), 0x412000, 0x400014, true).unwrap();
+        assert_eq!(word(&image, 4), 0x910d1400); //add x0, x0, #0x345
+        apply_aarch64(&mut image, 8, &reloc(286, 8, 0x348), 0x412000, 0x400018, true).unwrap();
+        assert_eq!(word(&image, 8), 0xf941a401); //ldr x1, [x0, #0x348]
+
+        //a page backwards, replacing the previous immediate
+        let mut image = 0xd0000083u32.to_le_bytes().to_vec();
+        apply_aarch64(&mut image, 0, &reloc(275, 0, 0), 0x3ff000, 0x400000, true).unwrap();
+        assert_eq!(word(&image, 0), 0xf0ffffe3); //adrp x3, . - 0x1000
+
+        //+-4GiB range, unchecked for the _NC variant
+        assert!(apply_aarch64(&mut image, 0, &reloc(275, 0, 0), 0x1_0040_0000, 0x400000, true)
+            .is_err());
+        assert!(apply_aarch64(&mut image, 0, &reloc(276, 0, 0), 0x1_0040_0000, 0x400000, true)
+            .is_ok());
+    }
+
+    #[test]
+    fn aarch64_call26_and_data() {
+        let mut image = 0x94000000u32.to_le_bytes().to_vec();
+        apply_aarch64(&mut image, 0, &reloc(283, 0, 0), 0x3ff000, 0x400000, true).unwrap();
+        assert_eq!(image, vec![0x00, 0xfc, 0xff, 0x97]); //bl . - 0x1000
+        apply_aarch64(&mut image, 0, &reloc(282, 0, 0), 0x401000, 0x400000, true).unwrap();
+        assert_eq!(word(&image, 0), 0x94000400); //b . + 0x1000, keeping the opcode
+        assert!(apply_aarch64(&mut image, 0, &reloc(283, 0, 0), 0x8400000, 0x400000, true)
+            .is_err());
+        assert!(apply_aarch64(&mut image, 0, &reloc(283, 0, 0), 0x83ffffc, 0x400000, true)
+            .is_ok());
+
+        let mut image = vec![0; 8];
+        apply_aarch64(&mut image, 0, &reloc(257, 0, 0), 0x412000, 0, false).unwrap();
+        assert_eq!(image, 0x412000u64.to_le_bytes().to_vec());
+        assert!(apply_aarch64(&mut image, 0, &reloc(257, 0, 0), 0x412000, 0, true).is_err());
+        apply_aarch64(&mut image, 0, &reloc(261, 0, 0), 0x3f0000, 0x400000, true).unwrap();
+        assert_eq!(word(&image, 0), 0xffff0000);
+        assert!(apply_aarch64(&mut image, 0, &reloc(1000, 0, 0), 0, 0, true).is_err());
+    }
+
+    fn parse(path: &str) -> ElfParser {
+        ElfParser::new(format!("{}/tests/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap()
+    }
+
+    fn placed_names(linker: &Linker) -> Vec<(String, u64)> {
+        linker.placements.iter()
+            .map(|&(idx, offset)| (linker.object.sections[idx].name(), offset))
+            .collect()
+    }
+
+    /* Destination of the rel32 call or jump whose displacement is at offset */
+    fn rel32_target(image: &[u8], offset: usize, vaddr: u64) -> u64 {
+        let disp = word(image, offset) as i32 as i64;
+        (vaddr + offset as u64 + 4).wrapping_add(disp as u64)
+    }
+
+    #[test]
+    fn link_main_object_through_plt() {
+        let target = parse("fixtures/pie");
+        let linker = Linker::new(parse("code/main.o"), Some("main")).unwrap();
+        //.data and .bss are empty, the debug sections are not allocated
+        assert_eq!(placed_names(&linker),
+                   vec![(".text".to_string(), 0), (".rodata".to_string(), 0x26)]);
+        assert_eq!(linker.size, 0x37);
+        assert_eq!(linker.entry, 0);
+
+        let vaddr = 0x5000;
+        let image = linker.link(vaddr, &target).unwrap();
+        //lea .rodata(%rip), %rdi
+        assert_eq!(image[0x13..0x16], [0x48, 0x8d, 0x3d]);
+        assert_eq!(rel32_target(&image, 0x16, vaddr), vaddr + 0x26);
+        assert_eq!(&image[0x26..0x37], b"Simple test main\0");
+
+        //call puts, through the PLT stub jumping through the GOT slot of puts
+        assert_eq!(image[0x1a], 0xe8);
+        let stub = rel32_target(&image, 0x1b, vaddr);
+        let plt = target.sections.iter()
+            .find(|sec| sec.addr() <= stub && stub < sec.addr() + sec.size())
+            .unwrap();
+        let stub_bytes = &plt.raw_bytes()[(stub - plt.addr()) as usize..];
+        let jmp = stub_bytes.windows(2).position(|op| op == [0xff, 0x25]).unwrap();
+        let got_slot = rel32_target(stub_bytes, jmp + 2, stub);
+        let relplt = target.rel_tables.iter().find(|table| table.sec_name == ".rela.plt").unwrap();
+        let puts = relplt.entries.iter()
+            .find(|ent| ent.symbol.as_ref().map_or(false, |sym| sym.name == "puts"))
+            .unwrap();
+        assert_eq!(got_slot, puts.reloc.offset());
+    }
+
+    #[test]
+    fn link_i386_rel_addends() {
+        let target = parse("fixtures/target386");
+        let linker = Linker::new(parse("fixtures/payload386.o"), None).unwrap();
+        assert_eq!(placed_names(&linker),
+                   vec![(".text".to_string(), 0), (".rodata.str1.1".to_string(), 0x1d)]);
+        let (target_func, _, _) = target.lookup_symbol("target_func").unwrap();
+
+        let vaddr = 0x0804c000;
+        let image = linker.link(vaddr, &target).unwrap();
+        //push $"first"; call target_func; movl $"payload", (%esp); call target_func
+        assert_eq!(word(&image, 4) as u64, vaddr + 0x1d);
+        assert_eq!(rel32_target(&image, 9, vaddr) as u32 as u64, target_func);
+        //the implicit addend of the REL entry selects the second string
+        assert_eq!(word(&image, 0x10) as u64, vaddr + 0x1d + 6);
+        assert_eq!(&image[0x23..0x2b], b"payload\0");
+        assert_eq!(rel32_target(&image, 0x15, vaddr) as u32 as u64, target_func);
+    }
+
+    #[test]
+    fn reject_i386_plt_calls_for_pic_targets() {
+        //only a call to the imported function, so it cannot fail on an absolute relocation
+        let linker = Linker::new(parse("fixtures/call386.o"), None).unwrap();
+        assert!(linker.link(0x0804c000, &parse("fixtures/target386")).is_ok());
+        let err = linker.link(0x5000, &parse("fixtures/lib386.so")).unwrap_err();
+        assert!(err.to_string().contains("Imported function target_func"));
+    }
+}
@@ -4,9 +4,12 @@ use std::io::{Read, Seek, SeekFrom};
 use std::collections::HashMap;
 use crate::header::*;
 use crate::symbols::*;
+use crate::relocations::{Relocation, RelocationTable, RelSymbol, RelocEncoding};
+use crate::dynamic::{DynamicTable, DynTag};
+use crate::hash::{HashTable, Hash, SysvHash, GnuHash};
 
-use crate::section::{Strtab, Section, SecHeader, SecHeader32, SecHeader64, SH_Type};
-use crate::segment::{Segment, ProgHeader, ProgHeader32, ProgHeader64};
+use crate::section::{Strtab, Section, SecHeader, SecHeader32, SecHeader64, SH_Type, SH_Flags};
+use crate::segment::{Segment, ProgHeader, ProgHeader32, ProgHeader64, PT_type, P_flag};
 
 use byteorder::*;
 
@@ -17,19 +20,29 @@ pub struct ElfParser{
    pub sections: Vec<Section>,
 
 
-    //TODO refine these data structures for fast parsing & manipulation of symbols/strings/rel/rela
+    //TODO refine these data structures for fast parsing & manipulation of strings/rel/rela
     // Right now these are mainly placeholders
     pub sechdrstr: HashMap<String, usize>,
-    pub symbols: HashMap<String, usize>,
     pub dynstr: HashMap<String, usize>,
     pub relsym: HashMap<String, usize>,
     pub relasym: HashMap<String, usize>,
+
+    //symbol indices into elf_symbols, rebuilt by link_symbols: by name (defined and .symtab
+    // symbols first), by name for .dynsym only, sorted by address (defined symbols with a
+    // size) and by defining section index
+    pub symbols: HashMap<String, usize>,
     pub dynsym: HashMap<String, usize>,
+    pub sym_addrs: Vec<usize>,
+    pub sec_symbols: HashMap<usize, Vec<usize>>,
 
     //TODO use in support of more options for modifying/manipulating strings and symbols
     pub string_tables: Vec<Strtab>,
     pub sym_tables: Vec<Symtable>,
     pub dynsym_tables: Vec<DynSymtable>,
+    pub rel_tables: Vec<RelocationTable>,
+    pub dynamic: Option<DynamicTable>,
+    pub hash_tables: Vec<HashTable>,
+    pub elf_symbols: Vec<ElfSymbol>,
 
     //pub shtstr_tab: Box<Vec<u8>>, Dont need-- captured in string_tables vector
     //pub dyn_str: Box<Vec<u8>>, Dont need-- captured in string_tables vector
@@ -70,6 +83,47 @@ pub fn get_strtable_entry(byte_string: String, val: u32) -> String {
     search_str.to_string()
 }
 
+/* Padding byte values that a code cave may consist of: zeros, x86 nop and x86 int3 */
+pub const CAVE_BYTES: [u8; 3] = [0x00, 0x90, 0xcc];
+
+/* A run of padding bytes within an executable segment, see ElfParser::find_code_caves */
+#[derive(Clone, Debug)]
+pub struct CodeCave {
+    pub seg_idx: usize,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub size: u64,
+}
+
+/* Location of injected bytes: the segment holding them, their file offset and their vaddr */
+#[derive(Clone, Debug)]
+pub struct InjSite {
+    pub seg_idx: usize,
+    pub offset: u64,
+    pub vaddr: u64,
+}
+
+/*
+* A reference to a string of a string table section: the sh_name of a section header, the
+* st_name of an entry of a .symtab/.dynsym section, the d_val of a string valued .dynamic
+* entry, or a name field (vn_file, vna_name or vda_name) at a byte offset of a version section
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StrRef {
+    SecName(usize),
+    SymName(usize, usize),
+    Dynamic(usize),
+    VerName(usize, usize),
+}
+
+/* Round val up to the next multiple of align (align values of 0 and 1 mean no alignment) */
+pub fn align_up(val: u64, align: u64) -> u64 {
+    if align <= 1 {
+        return val
+    }
+    (val + align - 1) / align * align
+}
+
 impl ElfParser {
     pub fn new(infile: String) -> Result<ElfParser, std::io::Error> {
         let mut file_ptr = match File::open(infile) {
@@ -89,12 +143,18 @@ impl ElfParser {
             string_tables: Vec::new(),
             sym_tables:  Vec::new(),
             dynsym_tables:  Vec::new(),
+            rel_tables:  Vec::new(),
+            dynamic: None,
+            hash_tables: Vec::new(),
+            elf_symbols: Vec::new(),
             sechdrstr: HashMap::new(),
             symbols: HashMap::new(),
             dynstr: HashMap::new(),
             relsym: HashMap::new(),
             relasym: HashMap::new(),
             dynsym: HashMap::new(),
+            sym_addrs: Vec::new(),
+            sec_symbols: HashMap::new(),
             sec_offsets: HashMap::new(),
             seg_offsets: HashMap::new()
         };
@@ -318,7 +378,7 @@ impl ElfParser {
                                            "No section header string names available, exiting with error"))
         }
 
-        for itr in &mut self.sections {
+        for (sec_idx, itr) in self.sections.iter_mut().enumerate() {
             let name_idx = itr.name_idx();
             let name: String = get_strtable_entry(byte_string.clone(), name_idx);
             //println!("Name! is {:?}", name);
@@ -342,7 +402,6 @@ impl ElfParser {
                                                                  self.header.class)?;
                     //println!("The symtab! {:?}", symtab);
                     self.sym_tables.push(symtab);
-                    self.symbols.insert(name, index);
                 }
                 SH_Type::SHT_DYNSYM => {
                    // println!("Inserted section type DynSym with name {:?} and index {:?}", name.clone(), index);
@@ -359,7 +418,6 @@ impl ElfParser {
 
 
                     self.dynsym_tables.push(dynsymtab);
-                    self.dynsym.insert(name, index);
                 }
                 SH_Type::SHT_STRTAB => {
                //     println!("Creating string table with name {:?} and index {:?}", name.clone(), index);
@@ -367,126 +425,315 @@ impl ElfParser {
                     let strtab = Strtab::parse_str_table(name.clone(), bytes);
                     self.string_tables.push(strtab);
                 }
-                SH_Type::SHT_RELA => {
-                    self.relasym.insert(name, index);
-                    //todo PARSE RELA SYMBOL DATA!
+                SH_Type::SHT_RELA | SH_Type::SHT_REL => {
+                    let rela = itr.shtype_as_u32() == SH_Type::SHT_RELA as u32;
+                    file_ptr.seek(SeekFrom::Start(itr.offset().into()))?;
+                    let reltab = RelocationTable::parse_rel_table::<R>(file_ptr,
+                                                                     itr.size(),
+                                                                     rela,
+                                                                     sec_idx as u32,
+                                                                     name.clone(),
+                                                                     index as u32,
+                                                                     itr.info_idx(),
+                                                                     self.header.machine(),
+                                                                     self.header.data,
+                                                                     self.header.class)?;
+                    self.rel_tables.push(reltab);
+                    if rela {
+                        self.relasym.insert(name, index);
+                    } else {
+                        self.relsym.insert(name, index);
+                    }
                 }
-                SH_Type::SHT_REL => {
+                SH_Type::SHT_RELR | SH_Type::SHT_ANDROID_RELR => {
+                    let reltab = RelocationTable::parse_relr_table(&itr.raw_bytes(),
+                                                                   sec_idx as u32,
+                                                                   name.clone(),
+                                                                   index as u32,
+                                                                   itr.info_idx(),
+                                                                   self.header.machine(),
+                                                                   self.header.data,
+                                                                   self.header.class)?;
+                    self.rel_tables.push(reltab);
                     self.relsym.insert(name, index);
-                    //todo PARSE REL SYMBOL DATA!
                 }
-                SH_Type::SHT_DYNAMIC => {
-                    self.dynsym.insert(name, index);
+                SH_Type::SHT_ANDROID_REL | SH_Type::SHT_ANDROID_RELA => {
+                    let rela = itr.shtype_as_u32() == SH_Type::SHT_ANDROID_RELA as u32;
+                    let reltab = RelocationTable::parse_aps2_table(&itr.raw_bytes(),
+                                                                   rela,
+                                                                   sec_idx as u32,
+                                                                   name.clone(),
+                                                                   index as u32,
+                                                                   itr.info_idx(),
+                                                                   self.header.machine(),
+                                                                   self.header.data,
+                                                                   self.header.class)?;
+                    self.rel_tables.push(reltab);
+                    if rela {
+                        self.relasym.insert(name, index);
+                    } else {
+                        self.relsym.insert(name, index);
+                    }
                 }
-                SH_Type::SHT_HASH => {
-                    //TODO need to determine what kind of functionality to support for such sections
-                  //  self.symbols.insert(name, index);
+                SH_Type::SHT_DYNAMIC => {
+                    file_ptr.seek(SeekFrom::Start(itr.offset().into()))?;
+                    let dyntab = DynamicTable::parse_dynamic_table::<R>(file_ptr,
+                                                                       itr.size(),
+                                                                       sec_idx as u32,
+                                                                       name.clone(),
+                                                                       index as u32,
+                                                                       self.header.data,
+                                                                       self.header.class)?;
+                    self.dynamic = Some(dyntab);
                 }
-                SH_Type::SHT_GNU_HASH => {
-                    //TODO need support for this section
-                  //  self.symbols.insert(name, index);
+                SH_Type::SHT_HASH | SH_Type::SHT_GNU_HASH => {
+                    let gnu = itr.shtype_as_u32() == SH_Type::SHT_GNU_HASH as u32;
+                    let hashtab = HashTable::parse_hash_table(&itr.raw_bytes(),
+                                                              gnu,
+                                                              sec_idx as u32,
+                                                              name.clone(),
+                                                              index as u32,
+                                                              self.header.data,
+                                                              self.header.class)?;
+                    self.hash_tables.push(hashtab);
                 }
                 _ => {}
             }
         }
 
-        //TODO add support for more manipulation/modification of symbols
-        for symtab in &self.sym_tables {
-
-            let symbytes = self.sections[symtab.section_idx as usize].raw_bytes().clone();
-            let _ent_size = self.sections[symtab.section_idx as usize].entsize();
-            let syms = String::from_utf8(symbytes.clone());
-            if syms.is_err() {
-                //println!("Error parsing symbols");
-                break;
-            }
-            let syms = syms.unwrap();
-            let len = syms.len();
-
-            let mut output_vec: Vec<String> = Vec::new();
-            for val in symbytes.clone() {
-                let pos = syms[val as usize..len]
-                    .chars().position(|c| c == '\u{0}')
-                    .unwrap();
+        self.link_symbols();
+        self.link_relocation_symbols();
+        self.link_dynamic_strings();
+        Ok(())
+    }
 
-                let search_str =
-                    &syms[val as usize..(val as usize + pos) as usize];
-                //  index += 1;
-                output_vec.push(search_str.to_string());
+    /*
+    * Resolve the entries of every .symtab and .dynsym section into elf_symbols (see ElfSymbol),
+    * in section order. Call again after changing symbols or their string tables.
+    */
+    pub fn link_symbols(&mut self) {
+        let mut symbols: Vec<ElfSymbol> = Vec::new();
+        for (symtab_idx, sec) in self.sections.iter().enumerate() {
+            let dynamic = sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32;
+            if !dynamic && sec.shtype_as_u32() != SH_Type::SHT_SYMTAB as u32 {
+                continue
             }
+            //section_idx of a parsed symbol table is its sh_link i.e. the index of its string table
+            let strtab_idx = sec.link_idx();
+            let entries: Vec<(u32, u64, u64, u8, u8, u16)> = if dynamic {
+                match self.dynsym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx) {
+                    Some(symtab) => symtab.entries.iter().map(|sym| {
+                        (sym.name_idx(), sym.value(), sym.size(), sym.info(), sym.other(), sym.shndx())
+                    }).collect(),
+                    None => continue,
+                }
+            } else {
+                match self.sym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx) {
+                    Some(symtab) => symtab.entries.iter().map(|sym| {
+                        (sym.name_idx(), sym.value(), sym.size(), sym.info(), sym.other(), sym.shndx())
+                    }).collect(),
+                    None => continue,
+                }
+            };
+            let strtab = match self.sections.get(strtab_idx as usize) {
+                Some(strtab) => strtab.raw_bytes(),
+                None => continue,
+            };
 
-            for i in 0..symtab.entries.len() {
-                let (_sht_idx, tab_idx, _sym_size) = match &symtab.entries[i] {
-                    Symbol::ThirtyTwo(sym) => (sym.st_shndx as u64, sym.st_name as u64, sym.st_size as u64),
-                    Symbol::SixtyFour(sym) => (sym.st_shndx as u64, sym.st_name as u64, sym.st_size as u64)
+            for (index, (st_name, value, size, info, other, shndx)) in entries.into_iter().enumerate() {
+                let start = (st_name as usize).min(strtab.len());
+                let end = strtab[start..].iter().position(|&b| b == 0)
+                    .map_or(strtab.len(), |pos| start + pos);
+                //special section indexes (SHN_UNDEF, SHN_ABS, SHN_COMMON...) are not sections
+                let section = if shndx == SHN::SHN_UNDEF as u16 || shndx >= 0xff00 {
+                    None
+                } else {
+                    self.sections.get(shndx as usize).map(|sec| sec.name())
                 };
+                symbols.push(ElfSymbol {
+                    symtab_idx: symtab_idx as u32,
+                    index: index as u32,
+                    dynamic: dynamic,
+                    name: String::from_utf8_lossy(&strtab[start..end]).to_string(),
+                    value: value,
+                    size: size,
+                    info: info,
+                    other: other,
+                    shndx: shndx,
+                    bind: ST_bind::from_u8(info >> 4),
+                    sym_type: ST_type::from_u8(info & 0xf),
+                    visibility: ST_visibility::from_u8(other & 0x3),
+                    section: section,
+                });
+            }
+        }
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        let mut dynsym: HashMap<String, usize> = HashMap::new();
+        let symtab_first = (0..symbols.len()).filter(|&i| !symbols[i].dynamic)
+            .chain((0..symbols.len()).filter(|&i| symbols[i].dynamic));
+        for i in symtab_first {
+            let sym = &symbols[i];
+            if sym.name.is_empty() {
+                continue
+            }
+            let replace = match by_name.get(&sym.name) {
+                Some(&prev) => symbols[prev].is_undefined() && !sym.is_undefined(),
+                None => true,
+            };
+            if replace {
+                by_name.insert(sym.name.clone(), i);
+            }
+            if sym.dynamic {
+                dynsym.entry(sym.name.clone()).or_insert(i);
+            }
+        }
+        let mut sec_symbols: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, sym) in symbols.iter().enumerate() {
+            if sym.section.is_some() {
+                sec_symbols.entry(sym.shndx as usize).or_default().push(i);
+            }
+        }
+        //section, file and TLS symbols do not have an address
+        let mut sym_addrs: Vec<usize> = (0..symbols.len()).filter(|&i| {
+            symbols[i].section.is_some() && symbols[i].size > 0 && !matches!(symbols[i].sym_type,
+                Some(ST_type::STT_SECTION) | Some(ST_type::STT_FILE) | Some(ST_type::STT_TLS))
+        }).collect();
+        sym_addrs.sort_by_key(|&i| symbols[i].value);
+
+        self.elf_symbols = symbols;
+        self.symbols = by_name;
+        self.dynsym = dynsym;
+        self.sec_symbols = sec_symbols;
+        self.sym_addrs = sym_addrs;
+    }
 
-                if tab_idx == 0 {
-                   // println!("Symbol has no name or no known size");
+    /* Find a symbol by name, preferring defined symbols and .symtab over .dynsym */
+    pub fn find_symbol(&self, name: &str) -> Option<&ElfSymbol> {
+        self.symbols.get(name).map(|&i| &self.elf_symbols[i])
+    }
 
-                    continue
-                } else if tab_idx as usize >= symbytes.len() {
-                    continue
-                } else {
+    /*
+    * Find the symbol (typically the function) whose st_value..st_value + st_size range contains
+    * the provided virtual address, preferring the one starting closest to it
+    */
+    pub fn symbol_at(&self, vaddr: u64) -> Option<&ElfSymbol> {
+        let end = self.sym_addrs.partition_point(|&i| self.elf_symbols[i].value <= vaddr);
+        self.sym_addrs[..end].iter().rev()
+            .map(|&i| &self.elf_symbols[i])
+            .find(|sym| vaddr < sym.value.saturating_add(sym.size))
+    }
 
-                    let symbol_name = String::from_utf8(symbytes[tab_idx as usize..].to_vec());
+    /* Symbols defined in the section at sec_idx, in symbol table order */
+    pub fn symbols_in_section(&self, sec_idx: usize) -> Vec<&ElfSymbol> {
+        match self.sec_symbols.get(&sec_idx) {
+            Some(syms) => syms.iter().map(|&i| &self.elf_symbols[i]).collect(),
+            None => Vec::new(),
+        }
+    }
 
-                    if symbol_name.is_err() {
-                        //println!("Error parsing symbol");
-                        break;
-                    }
+    /* Virtual address of a defined symbol plus an offset, for `<symbol>+0x<off>` options */
+    pub fn symbol_address(&self, name: &str, off: i64) -> Result<u64, std::io::Error> {
+        match self.find_symbol(name) {
+            Some(sym) if !sym.is_undefined() => Ok(sym.value.wrapping_add(off as u64)),
+            Some(_) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("Symbol {} is not defined in the file", name))),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("Symbol {} not found", name))),
+        }
+    }
 
-                    //TODO eventually will support
-                    let symbol_name = symbol_name.unwrap();
-                    let _name = symbol_name.split("\u{0}").collect::<Vec<_>>()[0];
+    /* Index of the alloc section with file contents covering the provided virtual address */
+    pub fn section_at(&self, vaddr: u64) -> Option<usize> {
+        self.sections.iter().position(|sec| {
+            sec.flags() & SH_Flags::SHF_ALLOC as u64 != 0 &&
+                sec.shtype_as_u32() != SH_Type::SHT_NOBITS as u32 &&
+                sec.addr() <= vaddr && vaddr < sec.addr() + sec.size()
+        })
+    }
 
-                    //println!("Symbol name is {:?} table index is {:?}", name, tab_idx);
+    /* Index of the PT_LOAD segment whose memory image covers the provided virtual address */
+    pub fn segment_at(&self, vaddr: u64) -> Option<usize> {
+        self.segments.iter().position(|seg| {
+            seg.p_type() == PT_type::PT_LOAD as u32 &&
+                seg.vaddr() <= vaddr && vaddr < seg.vaddr() + seg.mem_size()
+        })
+    }
 
-                }
-            }
+    /* File offset of the provided virtual address, if it is backed by file contents */
+    pub fn vaddr_to_offset(&self, vaddr: u64) -> Option<u64> {
+        if let Some(sec_idx) = self.section_at(vaddr) {
+            let sec = &self.sections[sec_idx];
+            return Some(sec.offset() + vaddr - sec.addr())
+        }
+        let seg = &self.segments[self.segment_at(vaddr)?];
+        if vaddr - seg.vaddr() >= seg.file_size() {
+            return None
         }
+        Some(seg.file_offset() + vaddr - seg.vaddr())
+    }
 
-        //TODO add support for manipulating/modifying dynamic symbols
-        for symtab in &self.dynsym_tables {
+    /* Resolve the strings of string valued .dynamic entries from the string table it links to */
+    fn link_dynamic_strings(&mut self) {
+        let strtab = match &self.dynamic {
+            Some(dyntab) => match self.sections.get(dyntab.link_idx as usize) {
+                Some(sec) => sec.raw_bytes(),
+                None => return,
+            },
+            None => return,
+        };
+        if let Some(dyntab) = &mut self.dynamic {
+            dyntab.resolve_strings(&strtab);
+        }
+    }
 
-            let symbytes = self.sections[symtab.section_idx as usize].raw_bytes().clone();
-            let _ent_size = self.sections[symtab.section_idx as usize].entsize();
-            let syms = String::from_utf8(symbytes.clone());//.unwrap();
-            if syms.is_err() {
-               // println!("Error parsing symbols");
-                break;
+    /* Resolve the symbol of each relocation entry from the symbol table its section links to */
+    fn link_relocation_symbols(&mut self) {
+        for t in 0..self.rel_tables.len() {
+            let link_idx = self.rel_tables[t].link_idx as usize;
+            for e in 0..self.rel_tables[t].entries.len() {
+                let sym_idx = self.rel_tables[t].entries[e].reloc.sym_idx();
+                let symbol = if sym_idx == 0 { None } else { self.symbol_by_index(link_idx, sym_idx) };
+                self.rel_tables[t].entries[e].symbol = symbol;
             }
-            let _syms = syms.unwrap();
-
-
-            for i in 0..symtab.entries.len() {
-                let (_sht_idx, tab_idx, _sym_size) = match &symtab.entries[i] {
-                    DynSymbol::ThirtyTwo(sym) => (sym.st_shndx as u64, sym.st_name as u64, sym.st_size as u64),
-                    DynSymbol::SixtyFour(sym) => (sym.st_shndx as u64, sym.st_name as u64, sym.st_size as u64)
-                };
-                 if tab_idx == 0 {
-      //              println!("Symbol has no name or no known size");
-
-                    continue
-                } else if tab_idx as usize >= symbytes.len() {
-                    continue
-                } else {
-                    let symbol_name = String::from_utf8(symbytes[tab_idx as usize..].to_vec());
-
-                    if symbol_name.is_err() {
-                    //    println!("Error parsing symbol");
-                        break;
-                    }
-
-                     //TODO store these in hashmap for O(1) symbol lookup!
-                    let symbol_name = symbol_name.unwrap();
-                    let _name = symbol_name.split("\u{0}").collect::<Vec<_>>()[0];
+        }
+    }
 
-                    //println!("Symbol name is {:?} table index is {:?}", name, tab_idx);
-                }
+    /* Get a symbol by its index in the .symtab or .dynsym section at symtab_sec */
+    pub fn symbol_by_index(&self, symtab_sec: usize, sym_idx: u32) -> Option<RelSymbol> {
+        let symtab_sec = self.sections.get(symtab_sec)?;
+        //section_idx of a parsed symbol table is its sh_link i.e. the index of its string table
+        let strtab_idx = symtab_sec.link_idx();
+        let (st_name, value, size, info, shndx) = if symtab_sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32 {
+            let symtab = self.dynsym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx)?;
+            match symtab.entries.get(sym_idx as usize)? {
+                DynSymbol::ThirtyTwo(sym) => (sym.st_name, sym.st_value as u64, sym.st_size as u64, sym.st_info, sym.st_shndx),
+                DynSymbol::SixtyFour(sym) => (sym.st_name, sym.st_value, sym.st_size, sym.st_info, sym.st_shndx),
+            }
+        } else {
+            let symtab = self.sym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx)?;
+            match symtab.entries.get(sym_idx as usize)? {
+                Symbol::ThirtyTwo(sym) => (sym.st_name, sym.st_value as u64, sym.st_size as u64, sym.st_info, sym.st_shndx),
+                Symbol::SixtyFour(sym) => (sym.st_name, sym.st_value, sym.st_size, sym.st_info, sym.st_shndx),
             }
+        };
+
+        let strtab = self.sections.get(strtab_idx as usize)?.raw_bytes();
+        let start = (st_name as usize).min(strtab.len());
+        let end = strtab[start..].iter().position(|&b| b == 0)
+            .map_or(strtab.len(), |pos| start + pos);
+        let mut name = String::from_utf8_lossy(&strtab[start..end]).to_string();
+        //section symbols are unnamed, use the name of their section instead
+        if name.is_empty() && info & 0xf == ST_type::STT_SECTION as u8 {
+            name = self.sections.get(shndx as usize).map_or(name, |sec| sec.name());
         }
-        Ok(())
+        Some(RelSymbol {
+            index: sym_idx,
+            name: name,
+            value: value,
+            size: size,
+            info: info,
+            shndx: shndx,
+        })
     }
 
         pub fn size(&self) -> usize {
@@ -712,106 +959,2815 @@ impl ElfParser {
         Ok(())
     }
 
-    pub fn write_segments(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
-        for i in (0..self.segments.len()).rev() {
-            self.segments[i].write_segment(file_ptr)?;
+    /*
+    * Byte offset one past the end of all file content (exec header, PHT, section and segment
+    * bytes). The SHT is not included since any operation that adds file content moves the
+    * SHT to the end of the file afterwards (see move_sht_to_end)
+    */
+    pub fn file_end(&self) -> u64 {
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        let mut end = pht_offset + self.header.ph_entry_num() as u64 * self.header.ph_entry_size() as u64;
+
+        for sec in &self.sections {
+            if sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32 {
+                continue
+            }
+            end = end.max(sec.offset() + sec.size());
         }
-        Ok(())
+        //segment raw bytes may extend past p_filesz (they are read in as p_memsz bytes)
+        for seg in &self.segments {
+            end = end.max(seg.file_offset() + seg.raw_bytes.len() as u64);
+        }
+        end
     }
 
+    /*
+    * Check if the byte range [start, end) overlaps any file content other than the SHT and
+    * the optionally provided section and segment indexes
+    */
+    pub fn overlaps_file_content(&self, start: u64, end: u64,
+                                 skip_sec: Option<usize>, skip_seg: Option<usize>) -> bool {
+        let overlaps = |left: u64, right: u64| left < end && start < right;
 
-    pub fn write_exec_header(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
-        self.header.write_exec_header(file_ptr)?;
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        let pht_end = pht_offset + self.header.ph_entry_num() as u64 * self.header.ph_entry_size() as u64;
+        if overlaps(0, self.exec_header_size()) || overlaps(pht_offset, pht_end) {
+            return true
+        }
+
+        for i in 0..self.sections.len() {
+            let sec = &self.sections[i];
+            if Some(i) == skip_sec || sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32 {
+                continue
+            }
+            if overlaps(sec.offset(), sec.offset() + sec.size()) {
+                return true
+            }
+        }
+        for i in 0..self.segments.len() {
+            let seg = &self.segments[i];
+            if Some(i) == skip_seg {
+                continue
+            }
+            if overlaps(seg.file_offset(), seg.file_offset() + seg.raw_bytes.len() as u64) {
+                return true
+            }
+        }
+        false
+    }
+
+    fn exec_header_size(&self) -> u64 {
+        match self.header.class {
+            EXEC::EI_CLASS::ELFCLASS32 => 0x34,
+            _ => 0x40,
+        }
+    }
+
+    /* Move the SHT to the (word aligned) end of the file, after all other file content */
+    pub fn move_sht_to_end(&mut self) -> Result<(), std::io::Error> {
+        let align = match self.header.class {
+            EXEC::EI_CLASS::ELFCLASS32 => 4,
+            _ => 8,
+        };
+        let new_offset = align_up(self.file_end(), align);
+        self.update_sht_offset(new_offset)
+    }
+
+    /*
+    * Replace the contents of a non-alloc section (e.g. .shstrtab), updating sh_size. If the
+    * new contents no longer fit at the section's current offset, the section is moved to the
+    * end of the file. Callers must move the SHT afterwards.
+    */
+    pub fn set_nonalloc_section_bytes(&mut self, sec_idx: usize,
+                                      bytes: Vec<u8>) -> Result<(), std::io::Error> {
+        if sec_idx >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid sec header index provided"))
+        }
+        if self.sections[sec_idx].flags() & SH_Flags::SHF_ALLOC as u64 != 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Cannot move an alloc section to the end of the file"))
+        }
+
+        let offset = self.sections[sec_idx].offset();
+        let new_size = bytes.len() as u64;
+        if self.overlaps_file_content(offset, offset + new_size, Some(sec_idx), None) {
+            let new_offset = align_up(self.file_end(), self.sections[sec_idx].addralign());
+            self.sections[sec_idx].set_offset(new_offset);
+            self.sec_offsets.insert(self.sections[sec_idx].name(), new_offset as usize);
+        }
+        self.sections[sec_idx].set_size(new_size);
+        self.sections[sec_idx].raw_bytes = bytes;
         Ok(())
     }
 
-    pub fn write_header_tables(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
-        let sht_offset = match self.header.sht_offset() {
-            SHTOffset::ThirtyTwo(off) => {
-                off as u64
+    /*
+    * Append a new section and section header table entry. The name is added to the section
+    * header string table, and e_shnum and e_shoff are updated. Non-alloc sections are placed
+    * at the end of the file. SHF_ALLOC sections are placed directly after the end of a
+    * covering PT_LOAD segment, within the padding between it and the next segment, and
+    * the segment is grown to include them: the covering segment is either the provided index
+    * or the first PT_LOAD with matching permissions and enough room. Returns the index of
+    * the new section header.
+    */
+    pub fn add_section(&mut self, name: String, bytes: Vec<u8>, sh_type: u32, sh_flags: u64,
+                       sh_link: u32, sh_info: u32, sh_addralign: u64, sh_entsize: u64,
+                       seg_idx: Option<usize>) -> Result<usize, std::io::Error> {
+        if self.sec_offsets.contains_key(&name) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Section name already exists"))
+        }
+
+        let nobits = sh_type == SH_Type::SHT_NOBITS as u32;
+        let alloc = sh_flags & SH_Flags::SHF_ALLOC as u64 != 0;
+        if nobits && alloc {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Adding SHF_ALLOC sections of type SHT_NOBITS \
+                                           is not supported"))
+        }
+        let size = bytes.len() as u64;
+
+        /* Add the section name to the section header string table */
+        let shstrndx = self.header.shstrndx() as usize;
+        if shstrndx >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "No section header string table, cannot add section"))
+        }
+        let shstrtab_name = self.sections[shstrndx].name();
+        let strtab = self.string_tables.iter_mut().find(|strtab| strtab.name == shstrtab_name);
+        let (name_idx, strtab_bytes) = match strtab {
+            Some(strtab) => (strtab.add_str(name.as_str()), strtab.strtab.clone()),
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No section header string table, cannot add section")),
+        };
+        self.set_nonalloc_section_bytes(shstrndx, strtab_bytes)?;
+
+        let (offset, addr) = if alloc {
+            let seg_idx = self.find_section_segment(size, sh_addralign, sh_flags, seg_idx)?;
+            let seg_offset = self.segments[seg_idx].file_offset();
+            let old_size = self.segments[seg_idx].file_size();
+            let offset = align_up(seg_offset + old_size, sh_addralign);
+            let addr = self.segments[seg_idx].vaddr() + (offset - seg_offset);
+
+            let mut seg_bytes = self.segments[seg_idx].raw_bytes.clone();
+            seg_bytes.resize((offset - seg_offset) as usize, 0);
+            seg_bytes.extend(&bytes);
+            let new_size = seg_bytes.len() as u64;
+            self.segments[seg_idx].set_bytes(seg_bytes);
+            self.segments[seg_idx].update_seg_header("p_filesz".to_string(), new_size)?;
+            self.segments[seg_idx].update_seg_header("p_memsz".to_string(), new_size)?;
+            (offset, addr)
+        } else {
+            (align_up(self.file_end(), sh_addralign), 0)
+        };
+
+        let header = match self.header.class {
+            EXEC::EI_CLASS::ELFCLASS32 => {
+                SecHeader::ThirtyTwo(SecHeader32::new(name_idx, sh_type, sh_flags as u32,
+                                                      addr as u32, offset as u32, size as u32,
+                                                      sh_link, sh_info, sh_addralign as u32,
+                                                      sh_entsize as u32))
             }
-            SHTOffset::SixtyFour(off) => {
-                off
+            EXEC::EI_CLASS::ELFCLASS64 => {
+                SecHeader::SixtyFour(SecHeader64::new(name_idx, sh_type, sh_flags, addr,
+                                                      offset, size, sh_link, sh_info,
+                                                      sh_addralign, sh_entsize))
             }
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported"))
         };
 
-        let pht_offset = match self.header.pht_offset() {
-            PHTOffset::ThirtyTwo(off) => {
-                off as u64
+        self.sections.push(Section {
+            SH: header,
+            raw_bytes: if nobits { Vec::new() } else { bytes },
+            name: name.clone(),
+        });
+        self.sec_offsets.insert(name, offset as usize);
+
+        let sec_idx = self.sections.len() - 1;
+        self.header.update_exec_header("e_shnum".to_string(), self.sections.len() as u64, None)?;
+        self.move_sht_to_end()?;
+        Ok(sec_idx)
+    }
+
+    /*
+    * Find a PT_LOAD segment that can be grown by size bytes (aligned to align) without
+    * overlapping any other file content or the memory image of any other PT_LOAD segment.
+    * Segments with p_memsz larger than p_filesz (e.g. containing .bss) cannot be grown.
+    * If no segment index is provided, only segments with permissions matching the section
+    * flags are considered.
+    */
+    fn find_section_segment(&self, size: u64, align: u64, sh_flags: u64,
+                            seg_idx: Option<usize>) -> Result<usize, std::io::Error> {
+        let candidates: Vec<usize> = match seg_idx {
+            Some(idx) => {
+                if idx >= self.segments.len() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid segment header index provided"))
+                }
+                vec![idx]
             }
-            PHTOffset::SixtyFour(off) => {
-                off
+            None => {
+                let exec = sh_flags & SH_Flags::SHF_EXECINSTR as u64 != 0;
+                let write = sh_flags & SH_Flags::SHF_WRITE as u64 != 0;
+                (0..self.segments.len()).filter(|&i| {
+                    let flags = self.segments[i].flags();
+                    (flags & P_flag::PF_X as u32 != 0) == exec
+                        && (flags & P_flag::PF_W as u32 != 0) == write
+                }).collect()
             }
         };
 
-        file_ptr.seek(SeekFrom::Start(sht_offset))?;
+        for i in candidates {
+            let seg = &self.segments[i];
+            if seg.p_type() != PT_type::PT_LOAD as u32 || seg.file_size() != seg.mem_size() {
+                continue
+            }
+            let seg_end = seg.file_offset() + seg.file_size();
+            let end = align_up(seg_end, align) + size;
+            if self.overlaps_file_content(seg_end, end, None, Some(i)) {
+                continue
+            }
 
-        for sec in &self.sections {
-            match &sec.SH {
-                SecHeader::ThirtyTwo(secheader) => {
-                    match self.header.data {
-                        EXEC::EI_DATA::ELFDATA2LSB => {
-                            secheader.write_sec_header::<LittleEndian>(file_ptr)?;
-                        }
-                        EXEC::EI_DATA::ELFDATA2MSB => {
-                            secheader.write_sec_header::<BigEndian>(file_ptr)?;
-                        }
-                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                            "Cant write Elf"))
-                    }
-                }
-                SecHeader::SixtyFour(secheader) => {
-                    match self.header.data {
-                        EXEC::EI_DATA::ELFDATA2LSB => {
-                            secheader.write_sec_header::<LittleEndian>(file_ptr)?;
-                        }
-                        EXEC::EI_DATA::ELFDATA2MSB => {
-                            secheader.write_sec_header::<BigEndian>(file_ptr)?;
-                        }
-                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                            "Cant write Elf"))
-                    }
-                }
+            let addr_end = seg.vaddr() + (end - seg.file_offset());
+            let page = seg.align().max(1);
+            let collides = self.segments.iter().enumerate().any(|(j, other)| {
+                j != i && other.p_type() == PT_type::PT_LOAD as u32
+                    && other.vaddr() >= seg.vaddr()
+                    && addr_end > other.vaddr() / page * page
+            });
+            if !collides {
+                return Ok(i)
             }
         }
 
-        file_ptr.seek(SeekFrom::Start(pht_offset))?;
+        Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                "No PT_LOAD segment with matching permissions has enough \
+                                room for the new section"))
+    }
 
-        for seg in &self.segments {
-            match &seg.PH {
-                ProgHeader::ThirtyTwo(progheader) => {
-                    match self.header.data {
-                        EXEC::EI_DATA::ELFDATA2LSB => {
-                            progheader.write_prog_header::<byteorder::LittleEndian>(file_ptr)?;
-                        }
-                        EXEC::EI_DATA::ELFDATA2MSB => {
-                            progheader.write_prog_header::<byteorder::BigEndian>(file_ptr)?;
-                        }
-                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                            "Cant write Elf"))
-                    }
-                }
-                ProgHeader::SixtyFour(progheader) => {
-                    match self.header.data {
-                        EXEC::EI_DATA::ELFDATA2LSB => {
-                            progheader.write_prog_header::<byteorder::LittleEndian>(file_ptr)?;
-                        }
-                        EXEC::EI_DATA::ELFDATA2MSB => {
-                            progheader.write_prog_header::<byteorder::BigEndian>(file_ptr)?;
-                        }
-                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
-                                                            "Cant write Elf"))
-                    }
-                }
+    /*
+    * Grow the PHT by count entries, updating e_phnum and the PT_PHDR entry (if any). The
+    * PHT is grown in place if the bytes directly after it are unused (and still covered by
+    * the PT_LOAD segment covering the PHT), otherwise the PHT is moved (see relocate_pht).
+    * The caller is responsible for inserting count new segments.
+    */
+    pub fn grow_pht(&mut self, count: u16) -> Result<(), std::io::Error> {
+        if self.pht_has_room(count) {
+            let ph_num = self.header.ph_entry_num();
+            self.header.update_exec_header("e_phnum".to_string(), (ph_num + count) as u64, None)?;
+            self.update_phdr_segment()?;
+            Ok(())
+        } else {
+            self.relocate_pht(count)
+        }
+    }
+
+    /* Check if the PHT can grow in place by count entries */
+    fn pht_has_room(&self, count: u16) -> bool {
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        let ph_num = self.header.ph_entry_num();
+        let ph_size = self.header.ph_entry_size() as u64;
+        let old_end = pht_offset + ph_num as u64 * ph_size;
+        let new_end = old_end + count as u64 * ph_size;
+
+        let overlaps = |left: u64, right: u64| left < new_end && old_end < right;
+        let sec_overlap = self.sections.iter().any(|sec| {
+            sec.shtype_as_u32() != SH_Type::SHT_NOBITS as u32
+                && overlaps(sec.offset(), sec.offset() + sec.size())
+        });
+        //segments covering the PHT itself (e.g. PT_PHDR and the first PT_LOAD) are fine
+        let seg_overlap = self.segments.iter().any(|seg| {
+            seg.file_offset() >= old_end
+                && overlaps(seg.file_offset(), seg.file_offset() + seg.raw_bytes.len() as u64)
+        });
+        let uncovered = self.segments.iter().any(|seg| {
+            seg.p_type() == PT_type::PT_LOAD as u32
+                && seg.file_offset() <= pht_offset
+                && pht_offset < seg.file_offset() + seg.file_size()
+                && new_end > seg.file_offset() + seg.file_size()
+        });
+        !(sec_overlap || seg_overlap || uncovered)
+    }
+
+    /*
+    * Move the PHT to the end of the file with room for count additional entries, under a
+    * new read-only PT_LOAD segment so that it is still mapped (as required for PT_PHDR).
+    * e_phoff, e_phnum and the PT_PHDR entry are updated. The new location is chosen so that
+    * the PHT vaddr minus its file offset is the same as for the first PT_LOAD segment, since
+    * some kernels compute AT_PHDR from e_phoff and the first PT_LOAD rather than PT_PHDR.
+    * This means the file is padded up to the end of the highest PT_LOAD segment's memory
+    * image. The caller is responsible for inserting count new segments.
+    */
+    pub fn relocate_pht(&mut self, count: u16) -> Result<(), std::io::Error> {
+        let loads: Vec<&Segment> = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32)
+            .collect();
+        let page = loads.iter().map(|seg| seg.align()).max().unwrap_or(0).max(0x1000);
+        let (base, top) = match loads.iter().min_by_key(|seg| seg.vaddr()) {
+            Some(first) => {
+                let base = first.vaddr().wrapping_sub(first.file_offset());
+                let top = loads.iter().map(|seg| seg.vaddr() + seg.mem_size()).max().unwrap_or(0);
+                (base, top)
+            }
+            None => (0, 0),
+        };
+
+        let offset = align_up(self.file_end().max(top.wrapping_sub(base)), page);
+        let vaddr = base.wrapping_add(offset);
+
+        //the new PT_LOAD for the PHT itself, along with count entries for the caller
+        let ph_num = self.header.ph_entry_num() + 1 + count;
+        let pht_size = ph_num as u64 * self.header.ph_entry_size() as u64;
+        let flags = P_flag::PF_R as u32;
+
+        let header = match self.header.class {
+            EXEC::EI_CLASS::ELFCLASS32 => {
+                ProgHeader::ThirtyTwo(ProgHeader32::new(PT_type::PT_LOAD as u32, offset as u32,
+                                                        vaddr as u32, vaddr as u32,
+                                                        pht_size as u32, pht_size as u32,
+                                                        flags, page as u32))
+            }
+            EXEC::EI_CLASS::ELFCLASS64 => {
+                ProgHeader::SixtyFour(ProgHeader64::new(PT_type::PT_LOAD as u32, offset, vaddr,
+                                                        vaddr, pht_size, pht_size, flags, page))
+            }
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported"))
+        };
+
+        //placed after the last PT_LOAD entry, which keeps PT_LOAD entries sorted by vaddr
+        let seg_idx = match self.segments.iter()
+            .rposition(|seg| seg.p_type() == PT_type::PT_LOAD as u32) {
+            Some(idx) => idx + 1,
+            None => self.segments.len(),
+        };
+        //the PHT is written by write_header_tables, after the segment bytes
+        self.segments.insert(seg_idx, Segment {
+            PH: header,
+            raw_bytes: vec![0; pht_size as usize],
+        });
+
+        self.header.update_exec_header("e_phoff".to_string(), offset, None)?;
+        self.header.update_exec_header("e_phnum".to_string(), ph_num as u64, None)?;
+        for seg in &mut self.segments {
+            if seg.p_type() == PT_type::PT_PHDR as u32 {
+                seg.update_seg_header("p_offset".to_string(), offset)?;
+                seg.update_seg_header("p_vaddr".to_string(), vaddr)?;
+                seg.update_seg_header("p_paddr".to_string(), vaddr)?;
             }
         }
+        self.update_phdr_segment()?;
+        self.move_sht_to_end()?;
         Ok(())
     }
 
-    pub fn write_sections(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
-        for sec in &self.sections {
-            sec.write_section(file_ptr)?;
+    /* Update the PT_PHDR entry (if any) size to cover e_phnum entries */
+    fn update_phdr_segment(&mut self) -> Result<(), std::io::Error> {
+        let pht_size = self.header.ph_entry_num() as u64 * self.header.ph_entry_size() as u64;
+        for seg in &mut self.segments {
+            if seg.p_type() == PT_type::PT_PHDR as u32 {
+                seg.update_seg_header("p_filesz".to_string(), pht_size)?;
+                seg.update_seg_header("p_memsz".to_string(), pht_size)?;
+            }
         }
         Ok(())
     }
+
+    /*
+    * Add a new segment and program header table entry containing the provided bytes. If no
+    * vaddr is provided, the segment is placed at the first align-aligned address above all
+    * existing PT_LOAD segments. The file offset is chosen at the end of the file such that it
+    * is congruent to the vaddr modulo align. memsz may be larger than the provided bytes, in
+    * which case the remainder is zero filled by the loader. The new entry is placed after the
+    * last PT_LOAD entry with a lower vaddr, to keep PT_LOAD entries sorted by vaddr. Returns
+    * the index of the new program header.
+    */
+    pub fn add_segment(&mut self, p_type: u32, p_flags: u32, vaddr: Option<u64>,
+                       align: u64, memsz: Option<u64>,
+                       bytes: Vec<u8>) -> Result<usize, std::io::Error> {
+        let align = align.max(1);
+        let filesz = bytes.len() as u64;
+        let memsz = memsz.unwrap_or(filesz);
+        if memsz < filesz {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid new segment memsz: smaller than the segment contents"))
+        }
+
+        //grow the PHT first, since it may be moved under a new PT_LOAD at the end of the file
+        self.grow_pht(1)?;
+
+        let (offset, vaddr) = self.new_load_placement(vaddr, memsz, align)?;
+
+        let header = match self.header.class {
+            EXEC::EI_CLASS::ELFCLASS32 => {
+                ProgHeader::ThirtyTwo(ProgHeader32::new(p_type, offset as u32, vaddr as u32,
+                                                        vaddr as u32, filesz as u32,
+                                                        memsz as u32, p_flags, align as u32))
+            }
+            EXEC::EI_CLASS::ELFCLASS64 => {
+                ProgHeader::SixtyFour(ProgHeader64::new(p_type, offset, vaddr, vaddr, filesz,
+                                                        memsz, p_flags, align))
+            }
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported"))
+        };
+
+        let seg_idx = self.load_insert_idx(vaddr);
+        self.segments.insert(seg_idx, Segment {
+            PH: header,
+            raw_bytes: bytes,
+        });
+        self.move_sht_to_end()?;
+        Ok(seg_idx)
+    }
+
+    /*
+    * Pick the file offset and vaddr for new loadable contents of memsz bytes placed at the end
+    * of the file. If no vaddr is provided, the first align-aligned address above all existing
+    * PT_LOAD segments is used (plus the page offset of the end of the file); a provided vaddr
+    * is checked for overlap with existing PT_LOAD segments. The file offset is congruent to
+    * the vaddr modulo align. Returns (offset, vaddr).
+    */
+    fn new_load_placement(&self, vaddr: Option<u64>, memsz: u64,
+                          align: u64) -> Result<(u64, u64), std::io::Error> {
+        let align = align.max(1);
+        let loads: Vec<(u64, u64)> = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32)
+            .map(|seg| (seg.vaddr(), seg.vaddr() + seg.mem_size()))
+            .collect();
+
+        /* Pick a page offset within the alignment first, so that the file offset is congruent */
+        let file_end = self.file_end();
+        let vaddr = match vaddr {
+            Some(vaddr) => {
+                let end = vaddr + memsz;
+                if loads.iter().any(|&(left, right)| {
+                    vaddr / align * align < align_up(right, align) && left / align * align < end
+                }) {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid new segment vaddr: overlaps an existing \
+                                                   PT_LOAD segment"))
+                }
+                vaddr
+            }
+            None => {
+                let top = loads.iter().map(|&(_, right)| right).max().unwrap_or(0);
+                align_up(top, align) + file_end % align
+            }
+        };
+        let offset = align_up(file_end, align) + vaddr % align;
+        let offset = if offset >= file_end + align { offset - align } else { offset };
+        Ok((offset, vaddr))
+    }
+
+    /*
+    * Index to insert a new PT_LOAD entry with the provided vaddr at: after the last PT_LOAD
+    * entry with a lower vaddr, to keep PT_LOAD entries sorted by vaddr
+    */
+    fn load_insert_idx(&self, vaddr: u64) -> usize {
+        let mut seg_idx = self.segments.len();
+        for i in (0..self.segments.len()).rev() {
+            if self.segments[i].p_type() == PT_type::PT_LOAD as u32 {
+                seg_idx = i + 1;
+                if self.segments[i].vaddr() < vaddr {
+                    break
+                }
+                seg_idx = i;
+            }
+        }
+        seg_idx
+    }
+
+    /*
+    * Convert a PT_NOTE segment into a PT_LOAD segment containing the provided bytes, placed at
+    * the end of the file above all existing PT_LOAD segments. Since the PT_NOTE entry is
+    * reused, the PHT does not need to grow. If no index is provided, the last PT_NOTE segment
+    * is used. The entry is moved after the last PT_LOAD entry, to keep PT_LOAD entries sorted
+    * by vaddr. Returns the new index of the program header.
+    */
+    pub fn convert_note_segment(&mut self, note_idx: Option<usize>, p_flags: u32,
+                                bytes: Vec<u8>) -> Result<usize, std::io::Error> {
+        let note_idx = match note_idx {
+            Some(idx) => idx,
+            None => match self.segments.iter()
+                .rposition(|seg| seg.p_type() == PT_type::PT_NOTE as u32) {
+                Some(idx) => idx,
+                None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                       "No PT_NOTE segment found")),
+            }
+        };
+        if note_idx >= self.segments.len()
+            || self.segments[note_idx].p_type() != PT_type::PT_NOTE as u32 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid program header index: not a PT_NOTE segment"))
+        }
+
+        let align = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32)
+            .map(|seg| seg.align())
+            .max()
+            .unwrap_or(0)
+            .max(0x1000);
+        let size = bytes.len() as u64;
+        let mut seg = self.segments.remove(note_idx);
+        let (offset, vaddr) = match self.new_load_placement(None, size, align) {
+            Ok(placement) => placement,
+            Err(err) => {
+                self.segments.insert(note_idx, seg);
+                return Err(err)
+            }
+        };
+
+        seg.update_seg_header("p_type".to_string(), PT_type::PT_LOAD as u64)?;
+        seg.update_seg_header("p_flags".to_string(), p_flags as u64)?;
+        seg.update_seg_header("p_offset".to_string(), offset)?;
+        seg.update_seg_header("p_vaddr".to_string(), vaddr)?;
+        seg.update_seg_header("p_paddr".to_string(), vaddr)?;
+        seg.update_seg_header("p_filesz".to_string(), size)?;
+        seg.update_seg_header("p_memsz".to_string(), size)?;
+        seg.update_seg_header("p_align".to_string(), align)?;
+        seg.set_bytes(bytes);
+
+        let seg_idx = self.load_insert_idx(vaddr);
+        self.segments.insert(seg_idx, seg);
+        self.move_sht_to_end()?;
+        Ok(seg_idx)
+    }
+
+    /*
+    * Index of the text segment: the executable PT_LOAD segment containing the entry point,
+    * or the first executable PT_LOAD segment if none contains it
+    */
+    pub fn text_segment_idx(&self) -> Option<usize> {
+        let entry = match self.header.entry() {
+            Entry::ThirtyTwo(entry) => entry as u64,
+            Entry::SixtyFour(entry) => entry,
+        };
+        let is_text = |seg: &Segment| {
+            seg.p_type() == PT_type::PT_LOAD as u32 && seg.flags() & P_flag::PF_X as u32 != 0
+        };
+        self.segments.iter()
+            .position(|seg| {
+                is_text(seg) && seg.vaddr() <= entry && entry < seg.vaddr() + seg.mem_size()
+            })
+            .or_else(|| self.segments.iter().position(|seg| is_text(seg)))
+    }
+
+    /*
+    * Number of bytes the text segment can grow by in place: the unused file bytes after the
+    * end of the segment, limited by the page padding before the next PT_LOAD segment in memory.
+    * There is no slack if the segment has a bss (p_memsz larger than p_filesz).
+    */
+    pub fn text_padding_slack(&self, text_idx: usize) -> u64 {
+        let text = &self.segments[text_idx];
+        if text.mem_size() != text.file_size() {
+            return 0
+        }
+        let align = text.align().max(0x1000);
+        let file_end = text.file_offset() + text.file_size();
+        let vaddr_end = text.vaddr() + text.mem_size();
+
+        let next_vaddr = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32 && seg.vaddr() >= vaddr_end)
+            .map(|seg| seg.vaddr() / align * align)
+            .min()
+            .unwrap_or(align_up(vaddr_end, align));
+        let mem_slack = next_vaddr.saturating_sub(vaddr_end);
+
+        /* file content starting at or after the end of the text segment limits the slack */
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        let mut next_offset = self.file_end().max(file_end + mem_slack);
+        if pht_offset >= file_end {
+            next_offset = next_offset.min(pht_offset);
+        }
+        for sec in &self.sections {
+            if sec.shtype_as_u32() != SH_Type::SHT_NOBITS as u32 && sec.size() > 0
+                && sec.offset() >= file_end {
+                next_offset = next_offset.min(sec.offset());
+            }
+        }
+        for (i, seg) in self.segments.iter().enumerate() {
+            if i != text_idx && !seg.raw_bytes.is_empty() && seg.file_offset() >= file_end {
+                next_offset = next_offset.min(seg.file_offset());
+            }
+        }
+        mem_slack.min(next_offset - file_end)
+    }
+
+    /*
+    * Inject the provided bytes into the padding after the end of the text segment (see
+    * text_padding_slack), growing p_filesz and p_memsz. No other file content is moved.
+    */
+    pub fn inject_text_padding(&mut self, bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        let text_idx = match self.text_segment_idx() {
+            Some(idx) => idx,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No executable PT_LOAD segment found")),
+        };
+        let slack = self.text_padding_slack(text_idx);
+        if bytes.len() as u64 > slack {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Injection bytes ({:#x}) do not fit in the text \
+                                                   segment padding ({:#x} bytes available)",
+                                                   bytes.len(), slack)))
+        }
+
+        let text = &mut self.segments[text_idx];
+        let filesz = text.file_size();
+        let vaddr = text.vaddr() + filesz;
+        let inj_offset = text.file_offset() + filesz;
+        text.raw_bytes.truncate(filesz as usize);
+        text.raw_bytes.extend(&bytes);
+        text.increase_size(bytes.len() as u64);
+
+        /* the SHT is written after the segments, so it must not sit in the padding */
+        let sht_offset = match self.header.sht_offset() {
+            SHTOffset::ThirtyTwo(offset) => offset as u64,
+            SHTOffset::SixtyFour(offset) => offset,
+        };
+        let sht_end = sht_offset + self.header.sh_entry_num() as u64 * self.header.sh_entry_size() as u64;
+        if sht_offset < inj_offset + bytes.len() as u64 && inj_offset < sht_end {
+            self.move_sht_to_end()?;
+        }
+        Ok(InjSite {
+            seg_idx: text_idx,
+            offset: inj_offset,
+            vaddr: vaddr,
+        })
+    }
+
+    /*
+    * Inject the provided bytes by growing the text segment downward by whole alignment units
+    * (at least a page): p_vaddr and p_paddr are lowered and p_filesz and p_memsz grown by the
+    * same amount, and the new bytes are inserted in the file at the start of the segment (or
+    * after the exec header, if the segment contains it). All later file content is shifted,
+    * so vaddrs of the existing contents are unchanged. The lowered segment must not overlap
+    * the previous PT_LOAD segment in memory.
+    */
+    pub fn inject_text_reverse(&mut self, bytes: Vec<u8>) -> Result<InjSite, std::io::Error> {
+        let text_idx = match self.text_segment_idx() {
+            Some(idx) => idx,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No executable PT_LOAD segment found")),
+        };
+        let (text_offset, text_vaddr, align) = {
+            let text = &self.segments[text_idx];
+            (text.file_offset(), text.vaddr(), text.align().max(0x1000))
+        };
+        let insert_at = if text_offset == 0 { self.exec_header_size() } else { text_offset };
+        let grow = align_up(bytes.len() as u64, align);
+
+        let prev_end = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32 && seg.vaddr() < text_vaddr)
+            .map(|seg| align_up(seg.vaddr() + seg.mem_size(), align))
+            .max()
+            .unwrap_or(0);
+        let slack = (text_vaddr / align * align).saturating_sub(prev_end);
+        if grow > slack {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Injection bytes ({:#x}) do not fit below the \
+                                                   text segment ({:#x} bytes available)",
+                                                   bytes.len(), slack)))
+        }
+
+        /* shift all file content at or after the insertion point */
+        for (i, seg) in self.segments.iter_mut().enumerate() {
+            if i != text_idx && seg.file_offset() >= insert_at {
+                seg.increase_offset(grow);
+            }
+        }
+        for sec in &mut self.sections {
+            if sec.offset() >= insert_at {
+                sec.increase_offset(grow);
+            }
+        }
+        for off in self.sec_offsets.values_mut() {
+            if *off as u64 >= insert_at {
+                *off += grow as usize;
+            }
+        }
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        if pht_offset >= insert_at {
+            self.header.update_exec_header("e_phoff".to_string(), pht_offset + grow, None)?;
+        }
+        self.increase_sht_offset(grow)?;
+
+        let text = &mut self.segments[text_idx];
+        let split = (insert_at - text_offset) as usize;
+        let mut inj_bytes = bytes;
+        inj_bytes.resize(grow as usize, 0);
+        let tail = text.raw_bytes.split_off(split);
+        text.raw_bytes.extend(inj_bytes);
+        text.raw_bytes.extend(tail);
+        text.update_seg_header("p_vaddr".to_string(), text_vaddr - grow)?;
+        text.update_seg_header("p_paddr".to_string(), text_vaddr - grow)?;
+        text.increase_size(grow);
+
+        Ok(InjSite {
+            seg_idx: text_idx,
+            offset: insert_at,
+            vaddr: text_vaddr - grow + split as u64,
+        })
+    }
+
+    /*
+    * Get the contents of a section or a symbol by name, e.g. to use another ELF file as an
+    * injection payload. Sections are matched first, then symbols in .symtab and .dynsym (which
+    * must be defined and have a non-zero size).
+    */
+    pub fn get_named_bytes(&self, name: &str) -> Result<Vec<u8>, std::io::Error> {
+        if let Some(sec) = self.sections.iter().find(|sec| sec.name() == name) {
+            if sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32 {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               "Section has no file contents"))
+            }
+            return Ok(sec.raw_bytes())
+        }
+
+        let (value, size, shndx) = match self.lookup_symbol(name) {
+            Some(sym) => sym,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("No section or symbol named {}", name))),
+        };
+        if size == 0 || shndx == SHN::SHN_UNDEF as u16 || shndx as usize >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Symbol {} is undefined or has no size", name)))
+        }
+
+        //symbol values are section offsets in relocatable files, and vaddrs otherwise
+        let sec = &self.sections[shndx as usize];
+        let start = if self.header.elf_type() == EXEC::EI_TYPE::ET_REL as u16 {
+            value
+        } else {
+            value.wrapping_sub(sec.addr())
+        };
+        let bytes = sec.raw_bytes();
+        if sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32 || start + size > bytes.len() as u64 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Symbol {} is not within its section contents", name)))
+        }
+        Ok(bytes[start as usize..(start + size) as usize].to_vec())
+    }
+
+    /* Find a symbol by name in .symtab, then .dynsym, returning (st_value, st_size, st_shndx) */
+    pub fn lookup_symbol(&self, name: &str) -> Option<(u64, u64, u16)> {
+        self.find_symbol(name).map(|sym| (sym.value, sym.size, sym.shndx))
+    }
+
+    /*
+    * Find code caves: runs of a single padding byte (see CAVE_BYTES) at least min_size long
+    * within the file backed bytes of executable PT_LOAD segments. The exec header and the PHT
+    * are excluded, since they are rewritten from the parsed headers.
+    */
+    pub fn find_code_caves(&self, min_size: usize) -> Vec<CodeCave> {
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        let pht_end = pht_offset
+            + self.header.ph_entry_num() as u64 * self.header.ph_entry_size() as u64;
+        let header_end = self.exec_header_size();
+        let reserved = |off: u64| off < header_end || (pht_offset <= off && off < pht_end);
+
+        let mut caves = Vec::new();
+        for (seg_idx, seg) in self.segments.iter().enumerate() {
+            if seg.p_type() != PT_type::PT_LOAD as u32 || seg.flags() & P_flag::PF_X as u32 == 0 {
+                continue;
+            }
+
+            let seg_offset = seg.file_offset();
+            let len = (seg.file_size() as usize).min(seg.raw_bytes.len());
+            let mut i = 0;
+            while i < len {
+                let byte = seg.raw_bytes[i];
+                if !CAVE_BYTES.contains(&byte) || reserved(seg_offset + i as u64) {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < len && seg.raw_bytes[i] == byte && !reserved(seg_offset + i as u64) {
+                    i += 1;
+                }
+                if i - start >= min_size.max(1) {
+                    caves.push(CodeCave {
+                        seg_idx: seg_idx,
+                        offset: seg_offset + start as u64,
+                        vaddr: seg.vaddr() + start as u64,
+                        size: (i - start) as u64,
+                    });
+                }
+            }
+        }
+        caves
+    }
+
+    /*
+    * Overwrite bytes of a segment in place starting at the provided file offset, without
+    * changing the size of the segment or the file layout
+    */
+    pub fn overwrite_segment_bytes(&mut self, seg_idx: usize, file_offset: u64,
+                                   bytes: &[u8]) -> Result<(), std::io::Error> {
+        let seg = &mut self.segments[seg_idx];
+        let start = match file_offset.checked_sub(seg.file_offset()) {
+            Some(start) => start as usize,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Invalid byte offset provided")),
+        };
+        if start + bytes.len() > seg.file_size() as usize || start + bytes.len() > seg.raw_bytes.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Injection bytes do not fit within the segment"))
+        }
+        seg.raw_bytes[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /*
+    * Number of unused bytes directly after an alloc section that it can grow into in place:
+    * bytes still within the file image of the PT_LOAD segment covering the section, before
+    * the next section or the PHT
+    */
+    pub fn section_slack(&self, sec_idx: usize) -> u64 {
+        let sec = &self.sections[sec_idx];
+        let start = sec.offset() + sec.size();
+        let load = self.segments.iter().find(|seg| {
+            seg.p_type() == PT_type::PT_LOAD as u32 && seg.file_offset() <= sec.offset() &&
+                start <= seg.file_offset() + seg.file_size()
+        });
+        let mut end = match load {
+            Some(seg) => seg.file_offset() + seg.file_size().min(seg.raw_bytes.len() as u64),
+            None => return 0,
+        };
+
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        };
+        if pht_offset >= start {
+            end = end.min(pht_offset);
+        }
+        for i in 0..self.sections.len() {
+            let other = &self.sections[i];
+            if i == sec_idx || other.size() == 0 ||
+                other.shtype_as_u32() == SH_Type::SHT_NOBITS as u32 {
+                continue
+            }
+            if other.offset() >= start {
+                end = end.min(other.offset());
+            } else if other.offset() + other.size() > start {
+                return 0
+            }
+        }
+        end.saturating_sub(start)
+    }
+
+    /*
+    * Grow the PT_LOAD segment ending at the provided file offset by by_size bytes, if it is
+    * both the last content of the file and the highest PT_LOAD in memory (e.g. a segment
+    * previously added by set_section_bytes), so that a section at its end can keep growing
+    * in place. Returns true if the segment was grown.
+    */
+    fn extend_last_load(&mut self, end: u64, by_size: u64) -> Result<bool, std::io::Error> {
+        let top = self.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32)
+            .map(|seg| seg.vaddr() + seg.mem_size())
+            .max()
+            .unwrap_or(0);
+        let file_end = self.file_end();
+        let seg_idx = self.segments.iter().position(|seg| {
+            seg.p_type() == PT_type::PT_LOAD as u32 &&
+                seg.file_offset() + seg.file_size() == end &&
+                seg.raw_bytes.len() as u64 == seg.file_size() &&
+                seg.file_size() == seg.mem_size() &&
+                seg.vaddr() + seg.mem_size() == top && end >= file_end
+        });
+        let seg_idx = match seg_idx {
+            Some(seg_idx) => seg_idx,
+            None => return Ok(false),
+        };
+
+        let new_size = self.segments[seg_idx].file_size() + by_size;
+        let mut seg_bytes = self.segments[seg_idx].raw_bytes.clone();
+        seg_bytes.resize(new_size as usize, 0);
+        self.segments[seg_idx].set_bytes(seg_bytes);
+        self.segments[seg_idx].update_seg_header("p_filesz".to_string(), new_size)?;
+        self.segments[seg_idx].update_seg_header("p_memsz".to_string(), new_size)?;
+        self.move_sht_to_end()?;
+        Ok(true)
+    }
+
+    /*
+    * Copy bytes to the provided file offset into the bytes of every segment overlapping them,
+    * so that writing the segments does not overwrite new section contents with stale bytes
+    */
+    fn patch_segment_bytes(&mut self, file_offset: u64, bytes: &[u8]) {
+        let end = file_offset + bytes.len() as u64;
+        for seg in &mut self.segments {
+            let seg_start = seg.file_offset();
+            let seg_end = seg_start + seg.file_size().min(seg.raw_bytes.len() as u64);
+            let start = file_offset.max(seg_start);
+            let stop = end.min(seg_end);
+            if start >= stop {
+                continue
+            }
+            seg.raw_bytes[(start - seg_start) as usize..(stop - seg_start) as usize]
+                .copy_from_slice(&bytes[(start - file_offset) as usize..(stop - file_offset) as usize]);
+        }
+    }
+
+    /*
+    * Replace the contents of a section, updating sh_size. Contents that fit in the current
+    * section plus the unused bytes after it (see section_slack), or at the end of the last
+    * PT_LOAD segment (see extend_last_load), are written in place, including into the bytes
+    * of the segments covering it (the rest of the old contents is zeroed). Larger contents of
+    * a non-alloc section are moved to the end of the file, and larger contents of an alloc
+    * section are moved into a new PT_LOAD segment above all others, with room for the section
+    * to double in size before it has to move again, and sh_offset and sh_addr updated.
+    * Returns true if the section was moved.
+    */
+    pub fn set_section_bytes(&mut self, sec_idx: usize,
+                             bytes: Vec<u8>) -> Result<bool, std::io::Error> {
+        if sec_idx >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid sec header index provided"))
+        }
+        let offset = self.sections[sec_idx].offset();
+        let old_size = self.sections[sec_idx].size();
+        let new_size = bytes.len() as u64;
+
+        if self.sections[sec_idx].flags() & SH_Flags::SHF_ALLOC as u64 == 0 {
+            self.set_nonalloc_section_bytes(sec_idx, bytes)?;
+            let moved = self.sections[sec_idx].offset() != offset;
+            //a section growing in place at the end of the file may run into the SHT after it
+            let sht_offset = match self.header.sht_offset() {
+                SHTOffset::ThirtyTwo(offset) => offset as u64,
+                SHTOffset::SixtyFour(offset) => offset,
+            };
+            let sht_end = sht_offset + self.header.sh_entry_num() as u64 * self.header.sh_entry_size() as u64;
+            let sec_end = self.sections[sec_idx].offset() + new_size;
+            if moved || (sht_offset < sec_end && self.sections[sec_idx].offset() < sht_end) {
+                self.move_sht_to_end()?;
+            }
+            return Ok(moved)
+        }
+
+        if new_size > old_size + self.section_slack(sec_idx) {
+            self.extend_last_load(offset + old_size, new_size - old_size)?;
+        }
+        if new_size <= old_size + self.section_slack(sec_idx) {
+            //zero the rest of the old contents if the section shrinks
+            let mut seg_bytes = bytes.clone();
+            seg_bytes.resize(old_size.max(new_size) as usize, 0);
+            self.patch_segment_bytes(offset, &seg_bytes);
+            self.sections[sec_idx].set_size(new_size);
+            self.sections[sec_idx].raw_bytes = bytes;
+            return Ok(false)
+        }
+
+        let sec_flags = self.sections[sec_idx].flags();
+        let mut p_flags = P_flag::PF_R as u32;
+        if sec_flags & SH_Flags::SHF_WRITE as u64 != 0 {
+            p_flags |= P_flag::PF_W as u32;
+        }
+        if sec_flags & SH_Flags::SHF_EXECINSTR as u64 != 0 {
+            p_flags |= P_flag::PF_X as u32;
+        }
+        //the segment vaddr is only known once it is added, so leave room to align the section,
+        // and as much room again as the section takes for it to keep growing in place (e.g.
+        // when adding symbols one at a time)
+        let sec_align = self.sections[sec_idx].addralign().max(1);
+        let slack = (sec_align - 1) as usize + bytes.len();
+        let mut seg_bytes = vec![0; slack];
+        seg_bytes.extend(&bytes);
+        let seg_idx = self.add_segment(PT_type::PT_LOAD as u32, p_flags, None, 0x1000, None,
+                                       seg_bytes)?;
+        let seg_vaddr = self.segments[seg_idx].vaddr();
+        let lead = (align_up(seg_vaddr, sec_align) - seg_vaddr) as usize;
+        let mut seg_bytes = vec![0; lead];
+        seg_bytes.extend(&bytes);
+        seg_bytes.resize(slack + bytes.len(), 0);
+        self.segments[seg_idx].set_bytes(seg_bytes);
+        let new_offset = self.segments[seg_idx].file_offset() + lead as u64;
+        let new_addr = seg_vaddr + lead as u64;
+
+        self.sections[sec_idx].set_offset(new_offset);
+        self.sections[sec_idx].update_sec_header("sh_addr".to_string(), new_addr)?;
+        self.sections[sec_idx].set_size(new_size);
+        self.sections[sec_idx].raw_bytes = bytes;
+        self.sec_offsets.insert(self.sections[sec_idx].name(), new_offset as usize);
+        self.move_sht_to_end()?;
+        Ok(true)
+    }
+
+    /* Value of the first .dynamic entry with the provided tag */
+    pub fn dynamic_value(&self, tag: DynTag) -> Option<u64> {
+        self.dynamic.as_ref()?.get(tag)
+    }
+
+    /*
+    * Set the value of the first .dynamic entry with the provided tag, if it is present, and
+    * write the table back
+    */
+    pub fn set_dynamic_value(&mut self, tag: DynTag, val: u64) -> Result<(), std::io::Error> {
+        let updated = match &mut self.dynamic {
+            Some(dyntab) => dyntab.set(tag, val)?,
+            None => false,
+        };
+        if updated {
+            self.write_dynamic()?;
+        }
+        Ok(())
+    }
+
+    /*
+    * Write the .dynamic entries back. A table that grew past the end of the section is grown
+    * in place or moved into a new segment (see set_section_bytes), and PT_DYNAMIC is updated
+    * to match the section. The loader finds the table of an executable through PT_DYNAMIC,
+    * so the link-time _DYNAMIC references (e.g. the first GOT entry) are left alone.
+    */
+    pub fn write_dynamic(&mut self) -> Result<(), std::io::Error> {
+        let (sec_idx, bytes) = match &self.dynamic {
+            Some(dyntab) => (dyntab.section_idx as usize, dyntab.to_bytes()?),
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        };
+        self.set_section_bytes(sec_idx, bytes)?;
+        self.sync_segment(PT_type::PT_DYNAMIC as u32, sec_idx)?;
+        self.link_dynamic_strings();
+        Ok(())
+    }
+
+    /*
+    * Set and clear bits of a flags valued .dynamic entry (DT_FLAGS or DT_FLAGS_1). If there is
+    * no entry with the tag and any bits end up set, one is inserted before the terminating
+    * DT_NULL.
+    */
+    pub fn update_dynamic_flags(&mut self, tag: DynTag, set: u64,
+                                clear: u64) -> Result<(), std::io::Error> {
+        if set == 0 && clear == 0 {
+            return Ok(())
+        }
+        let dyntab = match &mut self.dynamic {
+            Some(dyntab) => dyntab,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        };
+        let flags = (dyntab.get(tag).unwrap_or(0) | set) & !clear;
+        if dyntab.set(tag, flags)? {
+            return self.write_dynamic()
+        }
+        if flags == 0 {
+            return Ok(())
+        }
+        let idx = dyntab.len() - 1;
+        self.insert_dynamic(idx, tag, flags)
+    }
+
+    /*
+    * Mark the stack as executable or not through the PF_X flag of PT_GNU_STACK (the loader
+    * and kernel assume an executable stack if there is no PT_GNU_STACK)
+    */
+    pub fn set_stack_executable(&mut self, exec: bool) -> Result<(), std::io::Error> {
+        let seg = match self.segments.iter_mut()
+            .find(|seg| seg.p_type() == PT_type::PT_GNU_STACK as u32) {
+            Some(seg) => seg,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No PT_GNU_STACK segment")),
+        };
+        let flags = if exec {
+            seg.flags() | P_flag::PF_X as u32
+        } else {
+            seg.flags() & !(P_flag::PF_X as u32)
+        };
+        seg.update_seg_header("p_flags".to_string(), flags as u64)
+    }
+
+    /*
+    * Point the segments of the provided type (e.g. PT_DYNAMIC or PT_INTERP, which cover
+    * exactly one section) at the current location, size and contents of the section
+    */
+    fn sync_segment(&mut self, p_type: u32, sec_idx: usize) -> Result<(), std::io::Error> {
+        let offset = self.sections[sec_idx].offset();
+        let addr = self.sections[sec_idx].addr();
+        let size = self.sections[sec_idx].size();
+        let bytes = self.sections[sec_idx].raw_bytes();
+        for seg in &mut self.segments {
+            if seg.p_type() != p_type {
+                continue
+            }
+            seg.update_seg_header("p_offset".to_string(), offset)?;
+            seg.update_seg_header("p_vaddr".to_string(), addr)?;
+            seg.update_seg_header("p_paddr".to_string(), addr)?;
+            seg.update_seg_header("p_filesz".to_string(), size)?;
+            seg.update_seg_header("p_memsz".to_string(), size)?;
+            seg.set_bytes(bytes.clone());
+        }
+        Ok(())
+    }
+
+    /*
+    * Set the path of the program interpreter (the dynamic loader) in PT_INTERP and the .interp
+    * section it covers. A longer path is moved into new space like any other section (see
+    * set_section_bytes), and PT_INTERP is updated to match. If there is no section covering
+    * PT_INTERP, the segment is updated on its own, in place or in a new PT_LOAD segment.
+    */
+    pub fn set_interpreter(&mut self, path: &str) -> Result<(), std::io::Error> {
+        let interp_idx = match self.segments.iter()
+            .position(|seg| seg.p_type() == PT_type::PT_INTERP as u32) {
+            Some(idx) => idx,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No PT_INTERP segment, the file is not \
+                                                   dynamically linked")),
+        };
+        let mut bytes = path.as_bytes().to_vec();
+        bytes.push(0);
+        let offset = self.segments[interp_idx].file_offset();
+        let old_size = self.segments[interp_idx].file_size();
+
+        let sec_idx = self.sections.iter().position(|sec| {
+            sec.offset() == offset && sec.size() == old_size &&
+                sec.flags() & SH_Flags::SHF_ALLOC as u64 != 0
+        });
+        if let Some(sec_idx) = sec_idx {
+            self.set_section_bytes(sec_idx, bytes)?;
+            return self.sync_segment(PT_type::PT_INTERP as u32, sec_idx)
+        }
+
+        let new_size = bytes.len() as u64;
+        if new_size <= old_size {
+            let mut seg_bytes = bytes.clone();
+            seg_bytes.resize(old_size as usize, 0);
+            self.patch_segment_bytes(offset, &seg_bytes);
+        } else {
+            let seg_idx = self.add_segment(PT_type::PT_LOAD as u32, P_flag::PF_R as u32, None,
+                                           0x1000, None, bytes.clone())?;
+            let new_offset = self.segments[seg_idx].file_offset();
+            let new_addr = self.segments[seg_idx].vaddr();
+            //the new PT_LOAD may have been inserted before PT_INTERP
+            let interp = self.segments.iter_mut()
+                .find(|seg| seg.p_type() == PT_type::PT_INTERP as u32).unwrap();
+            interp.update_seg_header("p_offset".to_string(), new_offset)?;
+            interp.update_seg_header("p_vaddr".to_string(), new_addr)?;
+            interp.update_seg_header("p_paddr".to_string(), new_addr)?;
+        }
+        let interp = self.segments.iter_mut()
+            .find(|seg| seg.p_type() == PT_type::PT_INTERP as u32).unwrap();
+        interp.update_seg_header("p_filesz".to_string(), new_size)?;
+        interp.update_seg_header("p_memsz".to_string(), new_size)?;
+        interp.set_bytes(bytes);
+        Ok(())
+    }
+
+    /*
+    * Get the offset of a string in the dynamic string table (the section .dynamic links to),
+    * appending it if it is not already present (see add_str), and update DT_STRTAB/DT_STRSZ
+    * if the table was moved or grown
+    */
+    pub fn add_dynstr(&mut self, new_str: &str) -> Result<u32, std::io::Error> {
+        let strtab_idx = match &self.dynamic {
+            Some(dyntab) => dyntab.link_idx as usize,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        };
+        if strtab_idx == 0 || strtab_idx >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "No dynamic string table"))
+        }
+
+        let old_addr = self.sections[strtab_idx].addr();
+        let old_size = self.sections[strtab_idx].size();
+        let str_idx = self.add_str(strtab_idx, new_str)?;
+        if self.sections[strtab_idx].size() != old_size {
+            if self.dynamic_value(DynTag::DT_STRTAB) == Some(old_addr) {
+                self.set_dynamic_value(DynTag::DT_STRTAB, self.sections[strtab_idx].addr())?;
+            }
+            self.set_dynamic_value(DynTag::DT_STRSZ, self.sections[strtab_idx].size())?;
+        }
+        Ok(str_idx)
+    }
+
+    /*
+    * Get the offset of a string in the string table section at strtab_idx, appending it if it
+    * is not already present. The table is grown in place if there are unused bytes after it,
+    * otherwise it is moved (see set_section_bytes). Existing offsets into the table stay valid
+    * either way.
+    */
+    pub fn add_str(&mut self, strtab_idx: usize, new_str: &str) -> Result<u32, std::io::Error> {
+        if self.sections.get(strtab_idx).map_or(true, |sec| {
+            sec.shtype_as_u32() != SH_Type::SHT_STRTAB as u32
+        }) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid string table section provided"))
+        }
+
+        //a string may share the tail of a longer string, e.g. "c.so.6" within "libc.so.6"
+        let mut needle = new_str.as_bytes().to_vec();
+        needle.push(0);
+        let bytes = self.sections[strtab_idx].raw_bytes();
+        if let Some(pos) = bytes.windows(needle.len()).position(|window| window == &needle[..]) {
+            return Ok(pos as u32)
+        }
+
+        let name = self.sections[strtab_idx].name();
+        let mut strtab = match self.string_tables.iter().find(|strtab| strtab.name == name) {
+            Some(strtab) => strtab.clone(),
+            None => Strtab::parse_str_table(name.clone(), bytes),
+        };
+        let str_idx = strtab.add_str(new_str);
+        let new_bytes = strtab.strtab.clone();
+        match self.string_tables.iter_mut().find(|strtab| strtab.name == name) {
+            Some(old) => *old = strtab,
+            None => self.string_tables.push(strtab),
+        }
+        self.set_section_bytes(strtab_idx, new_bytes)?;
+        Ok(str_idx)
+    }
+
+    /*
+    * Rename entries of the .symtab or .dynsym section at symtab_sec, given as (index, new name)
+    * pairs, rebuilding the linked string table (see rewrite_strtab)
+    */
+    pub fn rename_symbols(&mut self, symtab_sec: usize,
+                          renames: &[(usize, String)]) -> Result<(), std::io::Error> {
+        let count = self.symbol_names_infos(symtab_sec)?.len();
+        if let Some((idx, _)) = renames.iter().find(|(idx, _)| *idx >= count) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Invalid symbol index {:#x} provided", idx)))
+        }
+        let strtab_idx = self.sections[symtab_sec].link_idx() as usize;
+        let renames: Vec<(StrRef, Vec<u8>)> = renames.iter()
+            .map(|(idx, name)| (StrRef::SymName(symtab_sec, *idx), name.as_bytes().to_vec()))
+            .collect();
+        self.rewrite_strtab(strtab_idx, &renames)
+    }
+
+    /* Rename the section at sec_idx, rebuilding the section header string table (see rewrite_strtab) */
+    pub fn rename_section(&mut self, sec_idx: usize, new_name: &str) -> Result<(), std::io::Error> {
+        if sec_idx == 0 || sec_idx >= self.sections.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid sec header index provided"))
+        }
+        if self.sec_offsets.contains_key(new_name) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Section name already exists"))
+        }
+        let shstrndx = self.header.shstrndx() as usize;
+        self.rewrite_strtab(shstrndx, &[(StrRef::SecName(sec_idx), new_name.as_bytes().to_vec())])
+    }
+
+    /*
+    * Every reference to a string of the string table section at strtab_idx, with its current
+    * offset: section names if it is the section header string table, the names of the symbol
+    * tables linking to it, string valued .dynamic entries and the file and version names of
+    * .gnu.version_r/.gnu.version_d if they link to it
+    */
+    pub fn strtab_refs(&self, strtab_idx: usize) -> Result<Vec<(StrRef, u32)>, std::io::Error> {
+        let mut refs: Vec<(StrRef, u32)> = Vec::new();
+        if self.header.shstrndx() as usize == strtab_idx {
+            for (sec_idx, sec) in self.sections.iter().enumerate() {
+                refs.push((StrRef::SecName(sec_idx), sec.name_idx()));
+            }
+        }
+
+        let big_endian = match self.header.data {
+            EXEC::EI_DATA::ELFDATA2MSB => true,
+            _ => false,
+        };
+        let read_u32 = |bytes: &[u8], pos: usize| if big_endian {
+            BigEndian::read_u32(&bytes[pos..])
+        } else {
+            LittleEndian::read_u32(&bytes[pos..])
+        };
+        let read_u16 = |bytes: &[u8], pos: usize| if big_endian {
+            BigEndian::read_u16(&bytes[pos..])
+        } else {
+            LittleEndian::read_u16(&bytes[pos..])
+        };
+
+        for (sec_idx, sec) in self.sections.iter().enumerate() {
+            if sec.link_idx() as usize != strtab_idx {
+                continue
+            }
+            let sh_type = sec.shtype_as_u32();
+            if sh_type == SH_Type::SHT_SYMTAB as u32 || sh_type == SH_Type::SHT_DYNSYM as u32 {
+                for (idx, (st_name, _)) in self.symbol_names_infos(sec_idx)?.into_iter().enumerate() {
+                    refs.push((StrRef::SymName(sec_idx, idx), st_name));
+                }
+            } else if sh_type == SH_Type::SHT_GNU_verneed as u32 {
+                //Elfxx_Verneed: vn_version, vn_cnt (u16), vn_file, vn_aux, vn_next (u32), and
+                // Elfxx_Vernaux: vna_hash (u32), vna_flags, vna_other (u16), vna_name, vna_next (u32)
+                let bytes = sec.raw_bytes();
+                let mut pos = 0;
+                while pos + 16 <= bytes.len() {
+                    refs.push((StrRef::VerName(sec_idx, pos + 4), read_u32(&bytes, pos + 4)));
+                    let mut aux = pos + read_u32(&bytes, pos + 8) as usize;
+                    for _ in 0..read_u16(&bytes, pos + 2) {
+                        if aux + 16 > bytes.len() {
+                            break
+                        }
+                        refs.push((StrRef::VerName(sec_idx, aux + 8), read_u32(&bytes, aux + 8)));
+                        match read_u32(&bytes, aux + 12) {
+                            0 => break,
+                            next => aux += next as usize,
+                        }
+                    }
+                    match read_u32(&bytes, pos + 12) {
+                        0 => break,
+                        next => pos += next as usize,
+                    }
+                }
+            } else if sh_type == SH_Type::SHT_GNU_verdef as u32 {
+                //Elfxx_Verdef: vd_version, vd_flags, vd_ndx, vd_cnt (u16), vd_hash, vd_aux,
+                // vd_next (u32), and Elfxx_Verdaux: vda_name, vda_next (u32)
+                let bytes = sec.raw_bytes();
+                let mut pos = 0;
+                while pos + 20 <= bytes.len() {
+                    let mut aux = pos + read_u32(&bytes, pos + 12) as usize;
+                    for _ in 0..read_u16(&bytes, pos + 6) {
+                        if aux + 8 > bytes.len() {
+                            break
+                        }
+                        refs.push((StrRef::VerName(sec_idx, aux), read_u32(&bytes, aux)));
+                        match read_u32(&bytes, aux + 4) {
+                            0 => break,
+                            next => aux += next as usize,
+                        }
+                    }
+                    match read_u32(&bytes, pos + 16) {
+                        0 => break,
+                        next => pos += next as usize,
+                    }
+                }
+            }
+        }
+
+        if let Some(dyntab) = &self.dynamic {
+            if dyntab.link_idx as usize == strtab_idx {
+                for (idx, entry) in dyntab.entries.iter().enumerate() {
+                    if entry.tag.is_string() {
+                        refs.push((StrRef::Dynamic(idx), entry.dynamic.val() as u32));
+                    }
+                }
+            }
+        }
+        Ok(refs)
+    }
+
+    /*
+    * Rebuild the string table section at strtab_idx from the strings it is referred to by
+    * (see strtab_refs), replacing the strings of the provided references with new ones, and
+    * point every reference at its string in the new table. Strings no longer referred to are
+    * dropped. The table is written with set_section_bytes, and DT_STRTAB/DT_STRSZ follow the
+    * dynamic string table. Renamed sections are renamed in the parsed tables too.
+    */
+    fn rewrite_strtab(&mut self, strtab_idx: usize,
+                      renames: &[(StrRef, Vec<u8>)]) -> Result<(), std::io::Error> {
+        if self.sections.get(strtab_idx).map_or(true, |sec| {
+            sec.shtype_as_u32() != SH_Type::SHT_STRTAB as u32
+        }) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid string table section provided"))
+        }
+        let refs = self.strtab_refs(strtab_idx)?;
+        for (r, _) in renames {
+            if !refs.iter().any(|(other, _)| other == r) {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("{:?} does not refer to the string table \
+                                                       being rewritten", r)))
+            }
+        }
+
+        let old_bytes = self.sections[strtab_idx].raw_bytes();
+        let name = self.sections[strtab_idx].name();
+        let mut strtab = Strtab::new();
+        strtab.name = name.clone();
+        let mut offsets: HashMap<Vec<u8>, u32> = HashMap::new();
+        offsets.insert(Vec::new(), strtab.add_bytes(&[]));
+        let mut new_refs: Vec<(StrRef, u32)> = Vec::new();
+        for (r, old_idx) in refs {
+            //strings are kept as raw bytes, names need not be valid UTF-8
+            let string = match renames.iter().find(|(renamed, _)| *renamed == r) {
+                Some((_, new_name)) => new_name.clone(),
+                None => {
+                    let start = (old_idx as usize).min(old_bytes.len());
+                    let end = old_bytes[start..].iter().position(|&b| b == 0)
+                        .map_or(old_bytes.len(), |pos| start + pos);
+                    old_bytes[start..end].to_vec()
+                }
+            };
+            let str_idx = match offsets.get(&string) {
+                Some(&str_idx) => str_idx,
+                None => {
+                    let str_idx = strtab.add_bytes(&string);
+                    offsets.insert(string, str_idx);
+                    str_idx
+                }
+            };
+            new_refs.push((r, str_idx));
+        }
+
+        let old_addr = self.sections[strtab_idx].addr();
+        self.set_section_bytes(strtab_idx, strtab.strtab.clone())?;
+        match self.string_tables.iter_mut().find(|old| old.name == name) {
+            Some(old) => *old = strtab,
+            None => self.string_tables.push(strtab),
+        }
+
+        let mut sym_secs: Vec<usize> = Vec::new();
+        let mut ver_bytes: HashMap<usize, Vec<u8>> = HashMap::new();
+        let mut dynamic_changed = false;
+        for (r, str_idx) in new_refs {
+            match r {
+                StrRef::SecName(sec_idx) => {
+                    self.sections[sec_idx].update_sec_header("sh_name".to_string(), str_idx as u64)?;
+                }
+                StrRef::SymName(symtab_sec, idx) => {
+                    self.modify_symbol(symtab_sec, idx, "st_name", str_idx as u64)?;
+                    if !sym_secs.contains(&symtab_sec) {
+                        sym_secs.push(symtab_sec);
+                    }
+                }
+                StrRef::Dynamic(idx) => {
+                    if let Some(dyntab) = &mut self.dynamic {
+                        dyntab.entries[idx].dynamic.set_val(str_idx as u64)?;
+                        dynamic_changed = true;
+                    }
+                }
+                StrRef::VerName(sec_idx, pos) => {
+                    let sections = &self.sections;
+                    let bytes = ver_bytes.entry(sec_idx).or_insert_with(|| sections[sec_idx].raw_bytes());
+                    match self.header.data {
+                        EXEC::EI_DATA::ELFDATA2MSB => BigEndian::write_u32(&mut bytes[pos..], str_idx),
+                        _ => LittleEndian::write_u32(&mut bytes[pos..], str_idx),
+                    }
+                }
+            }
+        }
+
+        for (sec_idx, bytes) in ver_bytes {
+            self.set_section_bytes(sec_idx, bytes)?;
+        }
+        let dynstr = self.dynamic.as_ref().map_or(false, |dyntab| dyntab.link_idx as usize == strtab_idx);
+        if dynstr {
+            if let Some(dyntab) = &mut self.dynamic {
+                let addr = self.sections[strtab_idx].addr();
+                let size = self.sections[strtab_idx].size();
+                for entry in dyntab.entries.iter_mut() {
+                    if (entry.tag == DynTag::DT_STRTAB && entry.dynamic.val() == old_addr) ||
+                        entry.tag == DynTag::DT_STRSZ {
+                        let val = if entry.tag == DynTag::DT_STRSZ { size } else { addr };
+                        entry.dynamic.set_val(val)?;
+                        dynamic_changed = true;
+                    }
+                }
+            }
+        }
+        if dynamic_changed {
+            self.write_dynamic()?;
+        }
+        if self.header.shstrndx() as usize == strtab_idx {
+            self.link_section_names();
+        }
+        for symtab_sec in sym_secs {
+            self.write_symbol_table(symtab_sec)?;
+        }
+        self.link_symbols();
+        Ok(())
+    }
+
+    /*
+    * Resolve the section names from the section header string table again (as done in
+    * parse_sections), renaming the sections in the name keyed and parsed tables too
+    */
+    fn link_section_names(&mut self) {
+        let shstrtab = match self.sections.get(self.header.shstrndx() as usize) {
+            Some(sec) => sec.raw_bytes(),
+            None => return,
+        };
+        for sec_idx in 0..self.sections.len() {
+            let start = (self.sections[sec_idx].name_idx() as usize).min(shstrtab.len());
+            let end = shstrtab[start..].iter().position(|&b| b == 0)
+                .map_or(shstrtab.len(), |pos| start + pos);
+            let name = String::from_utf8_lossy(&shstrtab[start..end]).to_string();
+            let old_name = self.sections[sec_idx].name();
+            if name == old_name {
+                continue
+            }
+            self.sections[sec_idx].set_name(name.clone());
+            if !self.sections.iter().any(|sec| sec.name() == old_name) {
+                self.sec_offsets.remove(&old_name);
+            }
+            self.sec_offsets.insert(name.clone(), self.sections[sec_idx].offset() as usize);
+
+            for strtab in self.string_tables.iter_mut().filter(|strtab| strtab.name == old_name) {
+                strtab.name = name.clone();
+            }
+            for symtab in self.sym_tables.iter_mut().filter(|symtab| symtab.sec_name == old_name) {
+                symtab.sec_name = name.clone();
+            }
+            for symtab in self.dynsym_tables.iter_mut().filter(|symtab| symtab.sec_name == old_name) {
+                symtab.sec_name = name.clone();
+            }
+            for table in self.rel_tables.iter_mut().filter(|table| table.section_idx as usize == sec_idx) {
+                table.sec_name = name.clone();
+            }
+            for table in self.hash_tables.iter_mut().filter(|table| table.section_idx as usize == sec_idx) {
+                table.sec_name = name.clone();
+            }
+            if let Some(dyntab) = &mut self.dynamic {
+                if dyntab.section_idx as usize == sec_idx {
+                    dyntab.sec_name = name.clone();
+                }
+            }
+            for relsym in [&mut self.relasym, &mut self.relsym] {
+                if let Some(link) = relsym.remove(&old_name) {
+                    relsym.insert(name.clone(), link);
+                }
+            }
+        }
+    }
+
+    /*
+    * Insert a .dynamic entry before the entry at idx, using a spare DT_NULL slot if there is
+    * one and growing or moving the table otherwise (see write_dynamic)
+    */
+    pub fn insert_dynamic(&mut self, idx: usize, tag: DynTag,
+                          val: u64) -> Result<(), std::io::Error> {
+        match &mut self.dynamic {
+            Some(dyntab) => dyntab.insert(idx, tag, val)?,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        }
+        self.write_dynamic()
+    }
+
+    /* Remove the .dynamic entry at idx, leaving a spare DT_NULL slot at the end of the table */
+    pub fn remove_dynamic(&mut self, idx: usize) -> Result<(), std::io::Error> {
+        match &mut self.dynamic {
+            Some(dyntab) => dyntab.remove(idx)?,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        }
+        self.write_dynamic()
+    }
+
+    /* Index of the DT_NEEDED entry naming the provided library */
+    fn needed_idx(&self, lib: &str) -> Option<usize> {
+        let dyntab = self.dynamic.as_ref()?;
+        dyntab.entries[..dyntab.len()].iter().position(|entry| {
+            entry.tag == DynTag::DT_NEEDED && entry.string.as_ref().map_or(false, |s| s == lib)
+        })
+    }
+
+    /*
+    * Add a DT_NEEDED entry for the provided library after the existing DT_NEEDED entries, so
+    * that it is loaded after them. Returns false if the library is already needed.
+    */
+    pub fn add_needed(&mut self, lib: &str) -> Result<bool, std::io::Error> {
+        if self.needed_idx(lib).is_some() {
+            return Ok(false)
+        }
+        let str_idx = self.add_dynstr(lib)?;
+        let idx = match &self.dynamic {
+            Some(dyntab) => {
+                match dyntab.entries[..dyntab.len()].iter()
+                    .rposition(|entry| entry.tag == DynTag::DT_NEEDED) {
+                    Some(last) => last + 1,
+                    None => 0,
+                }
+            }
+            None => 0,
+        };
+        self.insert_dynamic(idx, DynTag::DT_NEEDED, str_idx as u64)?;
+        Ok(true)
+    }
+
+    pub fn remove_needed(&mut self, lib: &str) -> Result<(), std::io::Error> {
+        match self.needed_idx(lib) {
+            Some(idx) => self.remove_dynamic(idx),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("Library {} is not needed", lib))),
+        }
+    }
+
+    /*
+    * Set the string of a string valued .dynamic entry (e.g. DT_RUNPATH or DT_SONAME). The new
+    * string is added to .dynstr (see add_dynstr) and the old one is left in place, since
+    * other entries or symbols may share it. If there is no entry with the tag, one is
+    * inserted after the DT_NEEDED entries, where linkers place these entries.
+    */
+    pub fn set_dynamic_string(&mut self, tag: DynTag, val: &str) -> Result<(), std::io::Error> {
+        if !tag.is_string() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("{} is not a string valued dynamic tag",
+                                                   tag.name())))
+        }
+        let str_idx = self.add_dynstr(val)? as u64;
+        let dyntab = match &mut self.dynamic {
+            Some(dyntab) => dyntab,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        };
+        if dyntab.set(tag, str_idx)? {
+            return self.write_dynamic()
+        }
+        let idx = match dyntab.entries[..dyntab.len()].iter()
+            .rposition(|entry| entry.tag == DynTag::DT_NEEDED) {
+            Some(last) => last + 1,
+            None => 0,
+        };
+        self.insert_dynamic(idx, tag, str_idx)
+    }
+
+    /* Remove the first .dynamic entry with the provided tag */
+    pub fn remove_dynamic_tag(&mut self, tag: DynTag) -> Result<(), std::io::Error> {
+        let idx = self.dynamic.as_ref().and_then(|dyntab| dyntab.find(tag));
+        match idx {
+            Some(idx) => self.remove_dynamic(idx),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("No {} entry in the dynamic section",
+                                                    tag.name()))),
+        }
+    }
+
+    /*
+    * Change the tag of the first .dynamic entry with tag from to to, keeping its value, e.g.
+    * to turn a DT_RPATH into a DT_RUNPATH (searched after LD_LIBRARY_PATH rather than before)
+    */
+    pub fn convert_dynamic_tag(&mut self, from: DynTag, to: DynTag) -> Result<(), std::io::Error> {
+        let dyntab = match &mut self.dynamic {
+            Some(dyntab) => dyntab,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "No dynamic section")),
+        };
+        if dyntab.find(to).is_some() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("The dynamic section already has a {} entry",
+                                                   to.name())))
+        }
+        match dyntab.find(from) {
+            Some(idx) => dyntab.retag(idx, to),
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("No {} entry in the dynamic section",
+                                                           from.name()))),
+        }
+        self.write_dynamic()
+    }
+
+    /*
+    * Point the DT_NEEDED entry for old_lib at new_lib, along with the file name of the
+    * matching .gnu.version_r entry (if any) so that symbol versions are looked up in new_lib
+    */
+    pub fn replace_needed(&mut self, old_lib: &str, new_lib: &str) -> Result<(), std::io::Error> {
+        let idx = match self.needed_idx(old_lib) {
+            Some(idx) => idx,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   format!("Library {} is not needed", old_lib))),
+        };
+        let str_idx = self.add_dynstr(new_lib)?;
+        if let Some(dyntab) = &mut self.dynamic {
+            dyntab.entries[idx].dynamic.set_val(str_idx as u64)?;
+        }
+        self.write_dynamic()?;
+        self.replace_verneed_file(old_lib, str_idx)
+    }
+
+    /*
+    * Set vn_file of every .gnu.version_r entry naming old_lib to the provided dynamic string
+    * table offset. Each Elfxx_Verneed entry is vn_version (u16), vn_cnt (u16), vn_file (u32),
+    * vn_aux (u32) and vn_next (u32, byte offset to the next entry or 0), in both classes.
+    */
+    fn replace_verneed_file(&mut self, old_lib: &str, str_idx: u32) -> Result<(), std::io::Error> {
+        let verneed_idx = match self.sections.iter()
+            .position(|sec| sec.shtype_as_u32() == SH_Type::SHT_GNU_verneed as u32) {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+        let strtab = self.sections[self.sections[verneed_idx].link_idx() as usize].raw_bytes();
+        let mut bytes = self.sections[verneed_idx].raw_bytes();
+        let big_endian = match self.header.data {
+            EXEC::EI_DATA::ELFDATA2MSB => true,
+            _ => false,
+        };
+
+        let mut changed = false;
+        let mut pos = 0;
+        while pos + 16 <= bytes.len() {
+            let (vn_file, vn_next) = if big_endian {
+                (BigEndian::read_u32(&bytes[pos + 4..]), BigEndian::read_u32(&bytes[pos + 12..]))
+            } else {
+                (LittleEndian::read_u32(&bytes[pos + 4..]), LittleEndian::read_u32(&bytes[pos + 12..]))
+            };
+            let file = strtab.get(vn_file as usize..)
+                .map(|file| &file[..file.iter().position(|&b| b == 0).unwrap_or(file.len())]);
+            if file == Some(old_lib.as_bytes()) {
+                if big_endian {
+                    BigEndian::write_u32(&mut bytes[pos + 4..], str_idx);
+                } else {
+                    LittleEndian::write_u32(&mut bytes[pos + 4..], str_idx);
+                }
+                changed = true;
+            }
+            if vn_next == 0 {
+                break
+            }
+            pos += vn_next as usize;
+        }
+        if changed {
+            self.set_section_bytes(verneed_idx, bytes)?;
+        }
+        Ok(())
+    }
+
+    /* Names of the symbols of the .dynsym section at dynsym_sec, read from its string table */
+    pub fn dynsym_names(&self, dynsym_sec: usize) -> Vec<Vec<u8>> {
+        let strtab_idx = match self.sections.get(dynsym_sec) {
+            Some(sec) => sec.link_idx(),
+            None => return Vec::new(),
+        };
+        let strtab = match self.sections.get(strtab_idx as usize) {
+            Some(sec) => sec.raw_bytes(),
+            None => return Vec::new(),
+        };
+        let symtab = match self.dynsym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx) {
+            Some(symtab) => symtab,
+            None => return Vec::new(),
+        };
+        symtab.entries.iter().map(|sym| {
+            let start = (sym.name_idx() as usize).min(strtab.len());
+            let end = strtab[start..].iter().position(|&b| b == 0)
+                .map_or(strtab.len(), |pos| start + pos);
+            strtab[start..end].to_vec()
+        }).collect()
+    }
+
+    /*
+    * Regenerate the .hash and .gnu.hash tables of the .dynsym section after dynamic symbols
+    * were added, renamed or removed. .dynsym is first reordered the way GNU hash requires:
+    * the null symbol, then the locals (sh_info is set to the first non-local), the undefined
+    * symbols, and the defined symbols sorted by GNU hash bucket. .gnu.version and the symbol
+    * indexes of the relocation tables linked to .dynsym are updated to match. The existing
+    * bucket counts and bloom filter parameters are kept, the tables are grown or moved as
+    * needed (see set_section_bytes) and DT_HASH/DT_GNU_HASH repointed.
+    */
+    pub fn rebuild_hash_tables(&mut self) -> Result<(), std::io::Error> {
+        let dynsym_sec = match self.sections.iter()
+            .position(|sec| sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32) {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+        let strtab_idx = self.sections[dynsym_sec].link_idx();
+        let t = match self.dynsym_tables.iter().position(|symtab| symtab.section_idx == strtab_idx) {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let names = self.dynsym_names(dynsym_sec);
+        let gnu_buckets = self.hash_tables.iter()
+            .filter(|table| table.link_idx as usize == dynsym_sec)
+            .find_map(|table| match &table.hash {
+                Hash::Gnu(hash) => Some(hash.buckets.len().max(1)),
+                _ => None,
+            });
+
+        //null symbol, locals, undefined, then defined symbols (sorted by bucket for GNU hash)
+        let entries = &self.dynsym_tables[t].entries;
+        let is_local = |idx: &usize| entries[*idx].info() >> 4 == ST_bind::STB_LOCAL as u8;
+        let is_undef = |idx: &usize| entries[*idx].shndx() == SHN::SHN_UNDEF as u16;
+        let mut order: Vec<usize> = (0..entries.len().min(1)).collect();
+        order.extend((1..entries.len()).filter(|idx| is_local(idx)));
+        let first_global = order.len();
+        let mut hashed: Vec<usize> = (1..entries.len()).filter(|idx| !is_local(idx)).collect();
+        if let Some(nbuckets) = gnu_buckets {
+            order.extend(hashed.iter().filter(|idx| is_undef(idx)));
+            hashed.retain(|idx| !is_undef(idx));
+            hashed.sort_by_key(|&idx| GnuHash::bucket_of(&names[idx], nbuckets));
+        }
+        let symoffset = order.len();
+        order.extend(hashed);
+
+        if order.iter().enumerate().any(|(new, &old)| new != old) {
+            self.reorder_symbols(dynsym_sec, &order)?;
+        }
+        self.sections[dynsym_sec].update_sec_header("sh_info".to_string(), first_global as u64)?;
+
+        let names = self.dynsym_names(dynsym_sec);
+        for h in 0..self.hash_tables.len() {
+            if self.hash_tables[h].link_idx as usize != dynsym_sec {
+                continue
+            }
+            let hash = match &self.hash_tables[h].hash {
+                Hash::Sysv(hash) => Hash::Sysv(SysvHash::build(&names, hash.buckets.len())),
+                Hash::Gnu(hash) => Hash::Gnu(GnuHash::build(&names, symoffset,
+                                                            hash.buckets.len(),
+                                                            hash.bloom.len(),
+                                                            hash.bloom_shift.max(1),
+                                                            self.header.class)?),
+            };
+            let tag = match hash {
+                Hash::Sysv(_) => DynTag::DT_HASH,
+                Hash::Gnu(_) => DynTag::DT_GNU_HASH,
+            };
+            self.hash_tables[h].hash = hash;
+
+            let sec_idx = self.hash_tables[h].section_idx as usize;
+            let old_addr = self.sections[sec_idx].addr();
+            let bytes = self.hash_tables[h].to_bytes()?;
+            self.set_section_bytes(sec_idx, bytes)?;
+            let addr = self.sections[sec_idx].addr();
+            if addr != old_addr && self.dynamic_value(tag) == Some(old_addr) {
+                self.set_dynamic_value(tag, addr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /* Index of the .symtab or .dynsym section with the provided name or index */
+    pub fn symtab_sec_idx(&self, name: Option<String>,
+                          index: Option<usize>) -> Result<usize, std::io::Error> {
+        let is_symtab = |sec: &Section| {
+            sec.shtype_as_u32() == SH_Type::SHT_SYMTAB as u32 ||
+                sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32
+        };
+        let found = match (name, index) {
+            (Some(name), _) => self.sections.iter().position(|sec| sec.name() == name && is_symtab(sec)),
+            (None, Some(index)) => self.sections.get(index).filter(|sec| is_symtab(sec)).map(|_| index),
+            (None, None) => None,
+        };
+        match found {
+            Some(idx) => Ok(idx),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid symbol table section provided")),
+        }
+    }
+
+    /*
+    * Names (st_name) and infos (binding and type) of the entries of the .symtab or .dynsym
+    * section at symtab_sec
+    */
+    fn symbol_names_infos(&self, symtab_sec: usize) -> Result<Vec<(u32, u8)>, std::io::Error> {
+        let strtab_idx = self.sections[symtab_sec].link_idx();
+        //section_idx of a parsed symbol table is its sh_link i.e. the index of its string table
+        let infos = if self.sections[symtab_sec].shtype_as_u32() == SH_Type::SHT_DYNSYM as u32 {
+            self.dynsym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx)
+                .map(|symtab| symtab.entries.iter().map(|sym| (sym.name_idx(), sym.info())).collect())
+        } else {
+            self.sym_tables.iter().find(|symtab| symtab.section_idx == strtab_idx)
+                .map(|symtab| symtab.entries.iter().map(|sym| (sym.name_idx(), sym.info())).collect())
+        };
+        match infos {
+            Some(infos) => Ok(infos),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid symbol table section provided")),
+        }
+    }
+
+    /*
+    * Apply f to the entries of the .symtab or .dynsym section at symtab_sec, then serialize
+    * them into the section bytes
+    */
+    fn map_symbol_table<F>(&mut self, symtab_sec: usize, f: F) -> Result<Vec<u8>, std::io::Error>
+        where F: Fn(&mut Vec<DynSymbol>, &mut Vec<Symbol>) -> Result<(), std::io::Error> {
+        let strtab_idx = self.sections[symtab_sec].link_idx();
+        let data = self.header.data;
+        if self.sections[symtab_sec].shtype_as_u32() == SH_Type::SHT_DYNSYM as u32 {
+            if let Some(symtab) = self.dynsym_tables.iter_mut().find(|symtab| symtab.section_idx == strtab_idx) {
+                f(&mut symtab.entries, &mut Vec::new())?;
+                return symtab.to_bytes(data)
+            }
+        } else if let Some(symtab) = self.sym_tables.iter_mut().find(|symtab| symtab.section_idx == strtab_idx) {
+            f(&mut Vec::new(), &mut symtab.entries)?;
+            return symtab.to_bytes(data)
+        }
+        Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                "Invalid symbol table section provided"))
+    }
+
+    /*
+    * Update a field of an entry of the .symtab or .dynsym section at symtab_sec (see
+    * update_symbol). The entry is only written back by write_symbol_table.
+    */
+    pub fn modify_symbol(&mut self, symtab_sec: usize, sym_idx: usize, field: &str,
+                         val: u64) -> Result<(), std::io::Error> {
+        self.map_symbol_table(symtab_sec, |dynsyms, syms| {
+            match (dynsyms.get_mut(sym_idx), syms.get_mut(sym_idx)) {
+                (Some(sym), _) => sym.update_symbol(field, val),
+                (_, Some(sym)) => sym.update_symbol(field, val),
+                (None, None) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                        "Invalid symbol index provided")),
+            }
+        })?;
+        Ok(())
+    }
+
+    /*
+    * Add a symbol to the .symtab or .dynsym section at symtab_sec, appending its name to the
+    * linked string table (see add_str and add_dynstr). A .dynsym entry also gets a .gnu.version
+    * entry, global (1) or local (0) by its binding. The table is then written back (see
+    * write_symbol_table), which may move the new entry. Returns its final index.
+    */
+    pub fn add_symbol(&mut self, symtab_sec: usize, name: &str, value: u64, size: u64, info: u8,
+                      other: u8, shndx: u16) -> Result<usize, std::io::Error> {
+        self.symbol_names_infos(symtab_sec)?;
+        let dynamic = self.sections[symtab_sec].shtype_as_u32() == SH_Type::SHT_DYNSYM as u32;
+        let strtab_idx = self.sections[symtab_sec].link_idx() as usize;
+        let st_name = match &self.dynamic {
+            Some(dyntab) if dyntab.link_idx as usize == strtab_idx => self.add_dynstr(name)?,
+            _ => self.add_str(strtab_idx, name)?,
+        };
+
+        let class = self.header.class;
+        let fields = [("st_name", st_name as u64), ("st_value", value), ("st_size", size),
+            ("st_info", info as u64), ("st_other", other as u64), ("st_shndx", shndx as u64)];
+        self.map_symbol_table(symtab_sec, |dynsyms, syms| {
+            if dynamic {
+                let mut sym = DynSymbol::new(class)?;
+                for (field, val) in fields.iter() {
+                    sym.update_symbol(field, *val)?;
+                }
+                dynsyms.push(sym);
+            } else {
+                let mut sym = Symbol::new(class)?;
+                for (field, val) in fields.iter() {
+                    sym.update_symbol(field, *val)?;
+                }
+                syms.push(sym);
+            }
+            Ok(())
+        })?;
+
+        //one 16 bit version index per symbol
+        if let Some(versym_idx) = self.sections.iter().position(|sec| {
+            sec.shtype_as_u32() == SH_Type::SHT_GNU_versym as u32 &&
+                sec.link_idx() as usize == symtab_sec
+        }) {
+            let ver_ndx: u16 = if info >> 4 == ST_bind::STB_LOCAL as u8 { 0 } else { 1 };
+            let mut bytes = self.sections[versym_idx].raw_bytes();
+            match self.header.data {
+                EXEC::EI_DATA::ELFDATA2MSB => bytes.extend(&ver_ndx.to_be_bytes()),
+                _ => bytes.extend(&ver_ndx.to_le_bytes()),
+            }
+            let old_addr = self.sections[versym_idx].addr();
+            self.set_section_bytes(versym_idx, bytes)?;
+            let addr = self.sections[versym_idx].addr();
+            if addr != old_addr && self.dynamic_value(DynTag::DT_VERSYM) == Some(old_addr) {
+                self.set_dynamic_value(DynTag::DT_VERSYM, addr)?;
+            }
+        }
+
+        self.write_symbol_table(symtab_sec)?;
+        //the new entry is the last one of its name and value, wherever write_symbol_table put it
+        match self.elf_symbols.iter().rev().find(|sym| {
+            sym.symtab_idx as usize == symtab_sec && sym.name == name && sym.value == value
+        }) {
+            Some(sym) => Ok(sym.index as usize),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            format!("Symbol {} was not added", name))),
+        }
+    }
+
+    /*
+    * Write the entries of the .symtab or .dynsym section at symtab_sec back to the section
+    * (growing or moving it as needed, see set_section_bytes), keeping the local symbols first
+    * with sh_info set to the first non-local. .dynsym is also reordered for GNU hash and its
+    * hash tables rebuilt (see rebuild_hash_tables), and DT_SYMTAB repointed if it moved.
+    */
+    pub fn write_symbol_table(&mut self, symtab_sec: usize) -> Result<(), std::io::Error> {
+        let old_addr = self.sections[symtab_sec].addr();
+        let bytes = self.map_symbol_table(symtab_sec, |_, _| Ok(()))?;
+        self.set_section_bytes(symtab_sec, bytes)?;
+
+        if self.sections[symtab_sec].shtype_as_u32() == SH_Type::SHT_DYNSYM as u32 {
+            let addr = self.sections[symtab_sec].addr();
+            if addr != old_addr && self.dynamic_value(DynTag::DT_SYMTAB) == Some(old_addr) {
+                self.set_dynamic_value(DynTag::DT_SYMTAB, addr)?;
+            }
+            self.rebuild_hash_tables()?;
+        } else {
+            let infos = self.symbol_names_infos(symtab_sec)?;
+            let is_local = |idx: &usize| infos[*idx].1 >> 4 == ST_bind::STB_LOCAL as u8;
+            let mut order: Vec<usize> = (0..infos.len().min(1)).collect();
+            order.extend((1..infos.len()).filter(|idx| is_local(idx)));
+            let first_global = order.len();
+            order.extend((1..infos.len()).filter(|idx| !is_local(idx)));
+            if order.iter().enumerate().any(|(new, &old)| new != old) {
+                self.reorder_symbols(symtab_sec, &order)?;
+            }
+            self.sections[symtab_sec].update_sec_header("sh_info".to_string(), first_global as u64)?;
+        }
+        self.link_symbols();
+        self.link_relocation_symbols();
+        Ok(())
+    }
+
+    /*
+    * Move the entry of the .symtab or .dynsym section at symtab_sec at order[i] to index i,
+    * then update what refers to symbols by index: .gnu.version, the symbol indexes of the
+    * relocation tables linked to the section and the signature symbols of section groups
+    */
+    fn reorder_symbols(&mut self, symtab_sec: usize, order: &[usize]) -> Result<(), std::io::Error> {
+        let mut new_idx = vec![0u32; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_idx[old] = new as u32;
+        }
+
+        let bytes = self.map_symbol_table(symtab_sec, |dynsyms, syms| {
+            if !dynsyms.is_empty() {
+                *dynsyms = order.iter().map(|&old| dynsyms[old].clone()).collect();
+            }
+            if !syms.is_empty() {
+                *syms = order.iter().map(|&old| syms[old].clone()).collect();
+            }
+            Ok(())
+        })?;
+        self.set_section_bytes(symtab_sec, bytes)?;
+
+        //one 16 bit version index per symbol
+        if let Some(versym_idx) = self.sections.iter().position(|sec| {
+            sec.shtype_as_u32() == SH_Type::SHT_GNU_versym as u32 &&
+                sec.link_idx() as usize == symtab_sec
+        }) {
+            let old_bytes = self.sections[versym_idx].raw_bytes();
+            if old_bytes.len() >= order.len() * 2 {
+                let mut bytes = old_bytes.clone();
+                for (new, &old) in order.iter().enumerate() {
+                    bytes[new * 2..new * 2 + 2].copy_from_slice(&old_bytes[old * 2..old * 2 + 2]);
+                }
+                self.set_section_bytes(versym_idx, bytes)?;
+            }
+        }
+
+        for sec in self.sections.iter_mut() {
+            if sec.shtype_as_u32() != SH_Type::SHT_GROUP as u32 || sec.link_idx() as usize != symtab_sec {
+                continue
+            }
+            let sym_idx = sec.info_idx() as usize;
+            if sym_idx < new_idx.len() {
+                sec.update_sec_header("sh_info".to_string(), new_idx[sym_idx] as u64)?;
+            }
+        }
+
+        for r in 0..self.rel_tables.len() {
+            if self.rel_tables[r].link_idx as usize != symtab_sec {
+                continue
+            }
+            let mut changed = false;
+            for entry in self.rel_tables[r].entries.iter_mut() {
+                let sym_idx = entry.reloc.sym_idx() as usize;
+                if sym_idx != 0 && sym_idx < new_idx.len() && new_idx[sym_idx] as usize != sym_idx {
+                    entry.reloc.set_sym_idx(new_idx[sym_idx])?;
+                    changed = true;
+                }
+            }
+            if changed {
+                self.write_rel_table(r)?;
+            }
+        }
+        self.link_symbols();
+        Ok(())
+    }
+
+    /* Index into rel_tables of the relocation table in the section with the provided name or index */
+    pub fn rel_table_idx(&self, name: Option<String>,
+                         index: Option<usize>) -> Result<usize, std::io::Error> {
+        let found = match (name, index) {
+            (Some(name), _) => self.rel_tables.iter().position(|table| table.sec_name == name),
+            (None, Some(index)) => {
+                self.rel_tables.iter().position(|table| table.section_idx as usize == index)
+            }
+            (None, None) => None,
+        };
+        match found {
+            Some(t) => Ok(t),
+            None => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                            "Invalid relocation section provided: not a SHT_REL \
+                                            or SHT_RELA section")),
+        }
+    }
+
+    /* Indexes of the entries of a relocation table referring to the symbol with the provided name */
+    pub fn relocations_for_symbol(&self, t: usize, name: &str) -> Vec<usize> {
+        self.rel_tables[t].entries.iter().enumerate()
+            .filter(|(_, entry)| entry.symbol.as_ref().map_or(false, |sym| sym.name == name))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    /* Index of the symbol with the provided name in the symbol table a relocation table links to */
+    pub fn rel_symbol_idx(&self, t: usize, name: &str) -> Option<u32> {
+        let link_idx = self.rel_tables[t].link_idx as usize;
+        let mut sym_idx = 1;
+        while let Some(sym) = self.symbol_by_index(link_idx, sym_idx) {
+            if sym.name == name {
+                return Some(sym_idx)
+            }
+            sym_idx += 1;
+        }
+        None
+    }
+
+    /*
+    * Modify a field of a relocation entry: r_offset, r_info, r_sym, r_type or r_addend (RELA
+    * only, the value is interpreted as a two's complement i64)
+    */
+    pub fn modify_relocation(&mut self, t: usize, e: usize, field: String,
+                             val: u64) -> Result<(), std::io::Error> {
+        if e >= self.rel_tables[t].entries.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid relocation entry index provided"))
+        }
+        let reloc = &mut self.rel_tables[t].entries[e].reloc;
+        match field.as_str() {
+            "r_offset" => reloc.set_offset(val)?,
+            "r_info" => reloc.set_info(val)?,
+            "r_sym" => reloc.set_sym_idx(val as u32)?,
+            "r_type" => reloc.set_rtype(val as u32)?,
+            "r_addend" => reloc.set_addend(val as i64)?,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Invalid relocation field provided")),
+        }
+        self.write_rel_table(t)
+    }
+
+    /* Append an entry to a relocation table, returning its index */
+    pub fn add_relocation(&mut self, t: usize, offset: u64, sym_idx: u32, rtype: u32,
+                          addend: i64) -> Result<usize, std::io::Error> {
+        let table = &self.rel_tables[t];
+        let reloc = Relocation::new(table.class, table.rela, offset, sym_idx, rtype, addend)?;
+        self.rel_tables[t].entries.push(crate::relocations::RelocationEntry {
+            reloc: reloc,
+            symbol: None,
+        });
+        self.write_rel_table(t)?;
+        //RELR tables are kept sorted, so the new entry is not necessarily the last
+        let entries = &self.rel_tables[t].entries;
+        Ok(entries.iter().rposition(|entry| entry.reloc.offset() == offset)
+            .unwrap_or(entries.len() - 1))
+    }
+
+    pub fn delete_relocation(&mut self, t: usize, e: usize) -> Result<(), std::io::Error> {
+        if e >= self.rel_tables[t].entries.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Invalid relocation entry index provided"))
+        }
+        self.rel_tables[t].entries.remove(e);
+        self.write_rel_table(t)
+    }
+
+    /*
+    * Write the entries of a relocation table back to its section (moving it if it grew), then
+    * update the .dynamic entries describing it and the symbols of its entries
+    */
+    fn write_rel_table(&mut self, t: usize) -> Result<(), std::io::Error> {
+        let sec_idx = self.rel_tables[t].section_idx as usize;
+        let old_addr = self.sections[sec_idx].addr();
+        let bytes = self.rel_tables[t].to_bytes()?;
+        self.rel_tables[t].sort_entries();
+        self.set_section_bytes(sec_idx, bytes)?;
+        self.update_rel_table_dynamic(t, old_addr)?;
+
+        let link_idx = self.rel_tables[t].link_idx as usize;
+        for e in 0..self.rel_tables[t].entries.len() {
+            let sym_idx = self.rel_tables[t].entries[e].reloc.sym_idx();
+            let symbol = if sym_idx == 0 { None } else { self.symbol_by_index(link_idx, sym_idx) };
+            self.rel_tables[t].entries[e].symbol = symbol;
+        }
+        Ok(())
+    }
+
+    /*
+    * Update the .dynamic entries describing a relocation table previously at old_addr: the
+    * table address and size (DT_JMPREL/DT_PLTRELSZ for the PLT relocations, otherwise
+    * DT_RELA/DT_RELASZ or DT_REL/DT_RELSZ, or their RELR and Android packed counterparts) and
+    * the count of leading RELATIVE entries (DT_RELACOUNT or DT_RELCOUNT, plain tables only).
+    * Tables not referenced by .dynamic are left alone.
+    */
+    fn update_rel_table_dynamic(&mut self, t: usize, old_addr: u64) -> Result<(), std::io::Error> {
+        let sec_idx = self.rel_tables[t].section_idx as usize;
+        let addr = self.sections[sec_idx].addr();
+        let size = self.sections[sec_idx].size();
+        if self.sections[sec_idx].flags() & SH_Flags::SHF_ALLOC as u64 == 0 {
+            return Ok(())
+        }
+
+        if self.dynamic_value(DynTag::DT_JMPREL) == Some(old_addr) {
+            self.set_dynamic_value(DynTag::DT_JMPREL, addr)?;
+            self.set_dynamic_value(DynTag::DT_PLTRELSZ, size)?;
+            return Ok(())
+        }
+        let tags = match (self.rel_tables[t].encoding, self.rel_tables[t].rela) {
+            (RelocEncoding::Plain, true) => {
+                vec![(DynTag::DT_RELA, DynTag::DT_RELASZ, Some(DynTag::DT_RELACOUNT))]
+            }
+            (RelocEncoding::Plain, false) => {
+                vec![(DynTag::DT_REL, DynTag::DT_RELSZ, Some(DynTag::DT_RELCOUNT))]
+            }
+            (RelocEncoding::Relr, _) => {
+                vec![(DynTag::DT_RELR, DynTag::DT_RELRSZ, None),
+                     (DynTag::DT_ANDROID_RELR, DynTag::DT_ANDROID_RELRSZ, None)]
+            }
+            (RelocEncoding::Aps2, true) => {
+                vec![(DynTag::DT_ANDROID_RELA, DynTag::DT_ANDROID_RELASZ, None)]
+            }
+            (RelocEncoding::Aps2, false) => {
+                vec![(DynTag::DT_ANDROID_REL, DynTag::DT_ANDROID_RELSZ, None)]
+            }
+        };
+        for (addr_tag, size_tag, count_tag) in tags {
+            if self.dynamic_value(addr_tag) != Some(old_addr) {
+                continue
+            }
+            self.set_dynamic_value(addr_tag, addr)?;
+            self.set_dynamic_value(size_tag, size)?;
+            if let Some(count_tag) = count_tag {
+                let count = self.rel_tables[t].relative_count();
+                self.set_dynamic_value(count_tag, count)?;
+            }
+            break
+        }
+        Ok(())
+    }
+
+    pub fn write_segments(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
+        for i in (0..self.segments.len()).rev() {
+            self.segments[i].write_segment(file_ptr)?;
+        }
+        Ok(())
+    }
+
+
+    pub fn write_exec_header(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
+        self.header.write_exec_header(file_ptr)?;
+        Ok(())
+    }
+
+    pub fn write_header_tables(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
+        let sht_offset = match self.header.sht_offset() {
+            SHTOffset::ThirtyTwo(off) => {
+                off as u64
+            }
+            SHTOffset::SixtyFour(off) => {
+                off
+            }
+        };
+
+        let pht_offset = match self.header.pht_offset() {
+            PHTOffset::ThirtyTwo(off) => {
+                off as u64
+            }
+            PHTOffset::SixtyFour(off) => {
+                off
+            }
+        };
+
+        file_ptr.seek(SeekFrom::Start(sht_offset))?;
+
+        for sec in &self.sections {
+            match &sec.SH {
+                SecHeader::ThirtyTwo(secheader) => {
+                    match self.header.data {
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            secheader.write_sec_header::<LittleEndian>(file_ptr)?;
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            secheader.write_sec_header::<BigEndian>(file_ptr)?;
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Cant write Elf"))
+                    }
+                }
+                SecHeader::SixtyFour(secheader) => {
+                    match self.header.data {
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            secheader.write_sec_header::<LittleEndian>(file_ptr)?;
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            secheader.write_sec_header::<BigEndian>(file_ptr)?;
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Cant write Elf"))
+                    }
+                }
+            }
+        }
+
+        file_ptr.seek(SeekFrom::Start(pht_offset))?;
+
+        for seg in &self.segments {
+            match &seg.PH {
+                ProgHeader::ThirtyTwo(progheader) => {
+                    match self.header.data {
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            progheader.write_prog_header::<byteorder::LittleEndian>(file_ptr)?;
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            progheader.write_prog_header::<byteorder::BigEndian>(file_ptr)?;
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Cant write Elf"))
+                    }
+                }
+                ProgHeader::SixtyFour(progheader) => {
+                    match self.header.data {
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            progheader.write_prog_header::<byteorder::LittleEndian>(file_ptr)?;
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            progheader.write_prog_header::<byteorder::BigEndian>(file_ptr)?;
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Cant write Elf"))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_sections(&self, file_ptr: &mut File) -> Result<(), std::io::Error> {
+        for sec in &self.sections {
+            sec.write_section(file_ptr)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Parse one of the checked-in fixtures (see tests/fixtures/Makefile) */
+    fn fixture(name: &str) -> ElfParser {
+        ElfParser::new(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+
+    /* Write the parser out the same way as Elf::write, and parse the written file back */
+    fn round_trip(parser: &ElfParser, name: &str) -> ElfParser {
+        let mut path = std::env::temp_dir();
+        path.push(format!("dedelf-{}-{}", name, std::process::id()));
+        {
+            let mut fp = File::create(&path).unwrap();
+            parser.write_sections(&mut fp).unwrap();
+            parser.write_segments(&mut fp).unwrap();
+            parser.write_header_tables(&mut fp).unwrap();
+            parser.write_exec_header(&mut fp).unwrap();
+        }
+        let reparsed = ElfParser::new(path.to_str().unwrap().to_string()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        reparsed
+    }
+
+    fn pht_offset(parser: &ElfParser) -> u64 {
+        match parser.header.pht_offset() {
+            PHTOffset::ThirtyTwo(offset) => offset as u64,
+            PHTOffset::SixtyFour(offset) => offset,
+        }
+    }
+
+    fn sht_offset(parser: &ElfParser) -> u64 {
+        match parser.header.sht_offset() {
+            SHTOffset::ThirtyTwo(offset) => offset as u64,
+            SHTOffset::SixtyFour(offset) => offset,
+        }
+    }
+
+    fn section<'a>(parser: &'a ElfParser, name: &str) -> &'a Section {
+        parser.sections.iter().find(|sec| sec.name() == name).unwrap()
+    }
+
+    fn section_names(parser: &ElfParser) -> Vec<String> {
+        parser.sections.iter().map(|sec| sec.name()).collect()
+    }
+
+    /* Check that DT_STRTAB/DT_STRSZ and PT_DYNAMIC match the .dynstr and .dynamic sections */
+    fn assert_dynamic_synced(parser: &ElfParser) {
+        let dyntab = parser.dynamic.as_ref().unwrap();
+        let dynstr = &parser.sections[dyntab.link_idx as usize];
+        assert_eq!(dyntab.get(DynTag::DT_STRTAB), Some(dynstr.addr()));
+        assert_eq!(dyntab.get(DynTag::DT_STRSZ), Some(dynstr.size()));
+
+        let dynamic = &parser.sections[dyntab.section_idx as usize];
+        let seg = parser.segments.iter()
+            .find(|seg| seg.p_type() == PT_type::PT_DYNAMIC as u32)
+            .unwrap();
+        assert_eq!(seg.file_offset(), dynamic.offset());
+        assert_eq!(seg.vaddr(), dynamic.addr());
+        assert_eq!(seg.file_size(), dynamic.size());
+    }
+
+    #[test]
+    fn add_needed_into_spare_null() {
+        let mut parser = fixture("pie");
+        let (offset, size) = {
+            let dynamic = section(&parser, ".dynamic");
+            (dynamic.offset(), dynamic.size())
+        };
+        let dyntab = parser.dynamic.as_ref().unwrap();
+        assert!(dyntab.entries.len() > dyntab.len() + 1);
+        assert!(parser.add_needed("libdedelf.so").unwrap());
+
+        let parser = round_trip(&parser, "add-needed-spare");
+        let dynamic = section(&parser, ".dynamic");
+        assert_eq!((dynamic.offset(), dynamic.size()), (offset, size));
+        assert_eq!(parser.dynamic.as_ref().unwrap().needed(),
+                   vec!["libc.so.6".to_string(), "libdedelf.so".to_string()]);
+        assert_dynamic_synced(&parser);
+    }
+
+    #[test]
+    fn add_needed_moves_full_table() {
+        let mut parser = fixture("pie");
+        let offset = section(&parser, ".dynamic").offset();
+        let mut libs = vec!["libc.so.6".to_string()];
+        while section(&parser, ".dynamic").offset() == offset {
+            let lib = format!("libdedelf{}.so", libs.len());
+            assert!(parser.add_needed(lib.as_str()).unwrap());
+            libs.push(lib);
+        }
+
+        let parser = round_trip(&parser, "add-needed-move");
+        let dyntab = parser.dynamic.as_ref().unwrap();
+        assert_eq!(dyntab.needed(), libs);
+        assert_ne!(section(&parser, ".dynamic").offset(), offset);
+        assert_dynamic_synced(&parser);
+        //the moved table is still mapped, at the vaddr matching its file offset
+        let dynamic = section(&parser, ".dynamic");
+        let load = &parser.segments[parser.segment_at(dynamic.addr()).unwrap()];
+        assert_eq!(dynamic.addr() - load.vaddr(), dynamic.offset() - load.file_offset());
+        assert!(dynamic.offset() + dynamic.size() <= load.file_offset() + load.file_size());
+    }
+
+    #[test]
+    fn set_runpath_grows_dynstr() {
+        let mut parser = fixture("pie");
+        let strtab = parser.dynamic_value(DynTag::DT_STRTAB).unwrap();
+        let strsz = parser.dynamic_value(DynTag::DT_STRSZ).unwrap();
+        let runpath = "/opt/dedelf/lib:".repeat(32);
+        parser.set_dynamic_string(DynTag::DT_RUNPATH, runpath.as_str()).unwrap();
+
+        let parser = round_trip(&parser, "set-runpath");
+        let dyntab = parser.dynamic.as_ref().unwrap();
+        assert_eq!(dyntab.runpath(), Some(runpath.clone()));
+        assert_eq!(dyntab.needed(), vec!["libc.so.6".to_string()]);
+        //too long for the padding after .dynstr, so the table moved
+        assert_ne!(dyntab.get(DynTag::DT_STRTAB), Some(strtab));
+        assert!(dyntab.get(DynTag::DT_STRSZ).unwrap() >= strsz + runpath.len() as u64 + 1);
+        assert_dynamic_synced(&parser);
+    }
+
+    fn dynsym_sec(parser: &ElfParser) -> usize {
+        parser.sections.iter()
+            .position(|sec| sec.shtype_as_u32() == SH_Type::SHT_DYNSYM as u32)
+            .unwrap()
+    }
+
+    /* Version index of each .dynsym entry, paired with the symbol name */
+    fn versyms(parser: &ElfParser) -> Vec<(Vec<u8>, u16)> {
+        let names = parser.dynsym_names(dynsym_sec(parser));
+        let bytes = section(parser, ".gnu.version").raw_bytes();
+        names.into_iter().zip(bytes.chunks(2))
+            .map(|(name, ver)| (name, u16::from_le_bytes([ver[0], ver[1]])))
+            .collect()
+    }
+
+    /* (offset, symbol name) of each relocation against a .dynsym entry */
+    fn dynamic_relocations(parser: &ElfParser) -> Vec<(u64, String)> {
+        let dynsym = dynsym_sec(parser);
+        parser.rel_tables.iter()
+            .filter(|table| table.link_idx as usize == dynsym)
+            .flat_map(|table| table.entries.iter())
+            .filter_map(|entry| {
+                entry.symbol.as_ref().map(|sym| (entry.reloc.offset(), sym.name.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn add_export_rebuilds_hash_tables() {
+        let mut parser = fixture("libvers.so");
+        let dynsym = dynsym_sec(&parser);
+        let relocs = dynamic_relocations(&parser);
+        let mut vers = versyms(&parser);
+        assert!(relocs.iter().any(|(_, name)| name == "vers_get"));
+
+        let (value, size, shndx) = parser.lookup_symbol("vers_get").unwrap();
+        let info = (ST_bind::STB_GLOBAL as u8) << 4 | ST_type::STT_FUNC as u8;
+        parser.add_symbol(dynsym, "vers_added", value, size, info, 0, shndx).unwrap();
+
+        let parser = round_trip(&parser, "add-export");
+        let dynsym = dynsym_sec(&parser);
+        let names = parser.dynsym_names(dynsym);
+        let symtab = parser.dynsym_tables.iter()
+            .find(|symtab| symtab.section_idx == parser.sections[dynsym].link_idx())
+            .unwrap();
+        let hashes: Vec<&HashTable> = parser.hash_tables.iter().collect();
+        assert!(hashes.iter().any(|table| match table.hash { Hash::Gnu(_) => true, _ => false }));
+        assert!(hashes.iter().any(|table| match table.hash { Hash::Sysv(_) => true, _ => false }));
+        for (idx, sym) in symtab.entries.iter().enumerate().skip(1) {
+            if sym.shndx() == SHN::SHN_UNDEF as u16 || sym.info() >> 4 == ST_bind::STB_LOCAL as u8 {
+                continue
+            }
+            for table in &hashes {
+                assert_eq!(table.lookup(&names[idx], &names), Some(idx as u32));
+            }
+        }
+        assert!(names.contains(&b"vers_added".to_vec()));
+
+        assert_eq!(dynamic_relocations(&parser), relocs);
+        vers.push((b"vers_added".to_vec(), 1));
+        let mut new_vers = versyms(&parser);
+        vers.sort();
+        new_vers.sort();
+        assert_eq!(new_vers, vers);
+    }
+
+    /* Every reference to a string of the string table at strtab_idx, with the string */
+    fn strtab_strings(parser: &ElfParser, strtab_idx: usize) -> Vec<(StrRef, Vec<u8>)> {
+        let strtab = parser.sections[strtab_idx].raw_bytes();
+        parser.strtab_refs(strtab_idx).unwrap().into_iter().map(|(str_ref, offset)| {
+            let start = offset as usize;
+            let end = start + strtab[start..].iter().position(|&b| b == 0).unwrap();
+            (str_ref, strtab[start..end].to_vec())
+        }).collect()
+    }
+
+    #[test]
+    fn rename_dynamic_symbol_rebuilds_dynstr() {
+        let mut parser = fixture("libvers.so");
+        let dynsym = dynsym_sec(&parser);
+        let dynstr = parser.sections[dynsym].link_idx() as usize;
+        let others = |parser: &ElfParser| -> Vec<(StrRef, Vec<u8>)> {
+            strtab_strings(parser, dynstr).into_iter()
+                .filter(|(str_ref, _)| match str_ref { StrRef::SymName(..) => false, _ => true })
+                .collect()
+        };
+        let refs = others(&parser);
+        assert!(refs.iter().any(|(str_ref, name)| {
+            match str_ref { StrRef::VerName(..) => name == b"VERS_1", _ => false }
+        }));
+        assert!(refs.iter().any(|(str_ref, name)| {
+            match str_ref { StrRef::VerName(..) => name == b"GLIBC_2.2.5", _ => false }
+        }));
+        let mut names = parser.dynsym_names(dynsym);
+        let idx = names.iter().position(|name| name == b"vers_get").unwrap();
+        let new_name = "vers_get_with_a_much_longer_name";
+        parser.rename_symbols(dynsym, &[(idx, new_name.to_string())]).unwrap();
+
+        let parser = round_trip(&parser, "rename-dynsym");
+        assert_eq!(others(&parser), refs);
+        names[idx] = new_name.as_bytes().to_vec();
+        let mut new_names = parser.dynsym_names(dynsym_sec(&parser));
+        names.sort();
+        new_names.sort();
+        assert_eq!(new_names, names);
+        assert_eq!(parser.dynamic_value(DynTag::DT_STRSZ), Some(parser.sections[dynstr].size()));
+        assert_dynamic_synced(&parser);
+    }
+
+    #[test]
+    fn rename_section_rebuilds_shstrtab() {
+        let mut parser = fixture("pie");
+        let shstrndx = parser.header.shstrndx() as usize;
+        let mut names = section_names(&parser);
+        let idx = names.iter().position(|name| name == ".comment").unwrap();
+        parser.rename_section(idx, ".dedelf.comment").unwrap();
+
+        let parser = round_trip(&parser, "rename-section");
+        names[idx] = ".dedelf.comment".to_string();
+        assert_eq!(section_names(&parser), names);
+        let strings: Vec<(StrRef, Vec<u8>)> = names.iter().enumerate()
+            .map(|(sec_idx, name)| (StrRef::SecName(sec_idx), name.as_bytes().to_vec()))
+            .collect();
+        assert_eq!(strtab_strings(&parser, shstrndx), strings);
+    }
+
+    fn symtab_sec(parser: &ElfParser) -> usize {
+        parser.sections.iter()
+            .position(|sec| sec.shtype_as_u32() == SH_Type::SHT_SYMTAB as u32)
+            .unwrap()
+    }
+
+    /* (name, binding) of each entry of the symbol table at symtab_sec, in table order */
+    fn symbol_binds(parser: &ElfParser, symtab_sec: usize) -> Vec<(String, u8)> {
+        let mut syms: Vec<&ElfSymbol> = parser.elf_symbols.iter()
+            .filter(|sym| sym.symtab_idx as usize == symtab_sec)
+            .collect();
+        syms.sort_by_key(|sym| sym.index);
+        syms.iter().map(|sym| (sym.name.clone(), sym.info >> 4)).collect()
+    }
+
+    /* Check that the local symbols come first, with sh_info set to the first non-local */
+    fn assert_locals_first(parser: &ElfParser, symtab_sec: usize) {
+        let binds = symbol_binds(parser, symtab_sec);
+        let locals = binds.iter().take_while(|(_, bind)| *bind == ST_bind::STB_LOCAL as u8).count();
+        assert!(binds[locals..].iter().all(|(_, bind)| *bind != ST_bind::STB_LOCAL as u8));
+        assert_eq!(parser.sections[symtab_sec].info_idx() as usize, locals);
+    }
+
+    #[test]
+    fn add_global_symbol_to_symtab() {
+        let mut parser = fixture("pie");
+        let symtab = symtab_sec(&parser);
+        let count = symbol_binds(&parser, symtab).len();
+        let (value, _, shndx) = parser.lookup_symbol("main").unwrap();
+        let info = (ST_bind::STB_GLOBAL as u8) << 4 | ST_type::STT_FUNC as u8;
+        parser.add_symbol(symtab, "dedelf_global", value, 0x10, info, 0, shndx).unwrap();
+        let info = (ST_bind::STB_LOCAL as u8) << 4 | ST_type::STT_FUNC as u8;
+        parser.add_symbol(symtab, "dedelf_local", value, 0x10, info, 0, shndx).unwrap();
+
+        let parser = round_trip(&parser, "add-symtab-symbol");
+        let symtab = symtab_sec(&parser);
+        let binds = symbol_binds(&parser, symtab);
+        assert_eq!(binds.len(), count + 2);
+        assert!(binds.contains(&("dedelf_global".to_string(), ST_bind::STB_GLOBAL as u8)));
+        assert!(binds.contains(&("dedelf_local".to_string(), ST_bind::STB_LOCAL as u8)));
+        assert_locals_first(&parser, symtab);
+    }
+
+    #[test]
+    fn add_symbols_to_dynsym_grow_versym() {
+        let mut parser = fixture("pie");
+        let dynsym = dynsym_sec(&parser);
+        let count = parser.dynsym_names(dynsym).len();
+        let dynsym_addr = parser.sections[dynsym].addr();
+        let (value, size, shndx) = parser.lookup_symbol("main").unwrap();
+        let info = (ST_bind::STB_GLOBAL as u8) << 4 | ST_type::STT_FUNC as u8;
+        //.dynsym first grows into the room left behind by .dynstr moving, then moves itself
+        let mut added = Vec::new();
+        while parser.sections[dynsym].addr() == dynsym_addr {
+            let name = format!("dedelf_export{}", added.len());
+            parser.add_symbol(dynsym, name.as_str(), value, size, info, 0, shndx).unwrap();
+            added.push(name);
+        }
+
+        let parser = round_trip(&parser, "add-dynsym-symbols");
+        let dynsym = dynsym_sec(&parser);
+        let vers = versyms(&parser);
+        assert_eq!(parser.dynsym_names(dynsym).len(), count + added.len());
+        assert_eq!(section(&parser, ".gnu.version").size() as usize, (count + added.len()) * 2);
+        for name in &added {
+            assert!(vers.contains(&(name.as_bytes().to_vec(), 1)));
+        }
+        assert_eq!(parser.dynamic_value(DynTag::DT_SYMTAB), Some(parser.sections[dynsym].addr()));
+        assert_eq!(parser.dynamic_value(DynTag::DT_VERSYM),
+                   Some(section(&parser, ".gnu.version").addr()));
+        assert_locals_first(&parser, dynsym);
+    }
+
+    #[test]
+    fn modify_binding_across_local_split() {
+        let mut parser = fixture("pie");
+        let symtab = symtab_sec(&parser);
+        let binds = symbol_binds(&parser, symtab);
+        let locals = parser.sections[symtab].info_idx();
+        let idx = binds.iter().position(|(name, _)| name == "frame_dummy").unwrap();
+        parser.modify_symbol(symtab, idx, "st_bind", ST_bind::STB_GLOBAL as u64).unwrap();
+        parser.write_symbol_table(symtab).unwrap();
+
+        let mut parser = round_trip(&parser, "bind-global");
+        let symtab = symtab_sec(&parser);
+        let binds = symbol_binds(&parser, symtab);
+        assert!(binds.contains(&("frame_dummy".to_string(), ST_bind::STB_GLOBAL as u8)));
+        assert_eq!(parser.sections[symtab].info_idx(), locals - 1);
+        assert_locals_first(&parser, symtab);
+
+        let idx = binds.iter().position(|(name, _)| name == "main").unwrap();
+        parser.modify_symbol(symtab, idx, "st_bind", ST_bind::STB_LOCAL as u64).unwrap();
+        parser.write_symbol_table(symtab).unwrap();
+
+        let parser = round_trip(&parser, "bind-local");
+        let symtab = symtab_sec(&parser);
+        let binds = symbol_binds(&parser, symtab);
+        assert!(binds.contains(&("main".to_string(), ST_bind::STB_LOCAL as u8)));
+        assert_eq!(parser.sections[symtab].info_idx(), locals);
+        assert_locals_first(&parser, symtab);
+        assert_eq!(binds.len(), symbol_binds(&fixture("pie"), symtab).len());
+    }
+
+    #[test]
+    fn add_nonalloc_section_at_end_of_file() {
+        let mut parser = fixture("pie");
+        let file_end = parser.file_end();
+        let names = section_names(&parser);
+        parser.add_section(".dedelf".to_string(), b"dedelf".to_vec(),
+                           SH_Type::SHT_PROGBITS as u32, 0, 0, 0, 1, 0, None).unwrap();
+
+        let parser = round_trip(&parser, "add-nonalloc-section");
+        assert_eq!(parser.header.sh_entry_num() as usize, names.len() + 1);
+        assert_eq!(&section_names(&parser)[..names.len()], &names[..]);
+        let sec = section(&parser, ".dedelf");
+        assert!(sec.offset() >= file_end);
+        assert_eq!(sec.addr(), 0);
+        assert_eq!(sec.raw_bytes(), b"dedelf".to_vec());
+        assert!(sht_offset(&parser) >= sec.offset() + sec.size());
+    }
+
+    #[test]
+    fn add_alloc_section_in_covering_segment() {
+        let mut parser = fixture("pie");
+        let names = section_names(&parser);
+        let shstrndx = parser.header.shstrndx() as usize;
+        let shstrtab_size = parser.sections[shstrndx].size();
+        parser.add_section(".dedelf.rodata".to_string(), vec![0xaa; 0x20],
+                           SH_Type::SHT_PROGBITS as u32, SH_Flags::SHF_ALLOC as u64,
+                           0, 0, 8, 0, None).unwrap();
+
+        let parser = round_trip(&parser, "add-alloc-section");
+        assert_eq!(parser.header.sh_entry_num() as usize, names.len() + 1);
+        assert_eq!(&section_names(&parser)[..names.len()], &names[..]);
+        assert_eq!(parser.sections[shstrndx].size(),
+                   shstrtab_size + ".dedelf.rodata".len() as u64 + 1);
+        let sht_end = sht_offset(&parser)
+            + parser.header.sh_entry_num() as u64 * parser.header.sh_entry_size() as u64;
+        assert!(parser.sections.iter().all(|sec| {
+            sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32
+                || sec.offset() + sec.size() <= sht_offset(&parser)
+                || sec.offset() >= sht_end
+        }));
+
+        let sec = section(&parser, ".dedelf.rodata");
+        assert_eq!(sec.addr() % 8, 0);
+        assert_eq!(sec.raw_bytes(), vec![0xaa; 0x20]);
+        //mapped read-only, at the vaddr matching its file offset
+        let load = &parser.segments[parser.segment_at(sec.addr()).unwrap()];
+        assert_eq!(load.p_type(), PT_type::PT_LOAD as u32);
+        assert_eq!(load.flags(), P_flag::PF_R as u32);
+        assert_eq!(sec.addr() - load.vaddr(), sec.offset() - load.file_offset());
+        assert!(sec.offset() + sec.size() <= load.file_offset() + load.file_size());
+    }
+
+    #[test]
+    fn add_segment_picks_free_congruent_placement() {
+        let mut parser = fixture("pie");
+        let flags = P_flag::PF_R as u32 | P_flag::PF_X as u32;
+        parser.add_segment(PT_type::PT_LOAD as u32, flags, None, 0x1000, None,
+                           vec![0xcc; 0x40]).unwrap();
+
+        let parser = round_trip(&parser, "add-segment");
+        let loads: Vec<&Segment> = parser.segments.iter()
+            .filter(|seg| seg.p_type() == PT_type::PT_LOAD as u32)
+            .collect();
+        let seg = loads.iter().find(|seg| seg.raw_bytes == vec![0xcc; 0x40]).unwrap();
+        assert_eq!(seg.flags(), flags);
+        assert_eq!(seg.file_offset() % 0x1000, seg.vaddr() % 0x1000);
+        //sorted by vaddr, with no overlapping pages or file contents
+        for pair in loads.windows(2) {
+            let (left, right) = (pair[0], pair[1]);
+            assert!(align_up(left.vaddr() + left.mem_size(), 0x1000)
+                    <= right.vaddr() / 0x1000 * 0x1000);
+        }
+        for other in loads.iter().filter(|other| other.vaddr() != seg.vaddr()) {
+            assert!(other.file_offset() + other.file_size() <= seg.file_offset()
+                    || seg.file_offset() + seg.file_size() <= other.file_offset());
+        }
+        assert!(parser.sections.iter().all(|sec| {
+            sec.offset() + sec.size() <= seg.file_offset()
+                || sec.shtype_as_u32() == SH_Type::SHT_NOBITS as u32
+        }));
+    }
+
+    #[test]
+    fn grow_pht_relocates_into_loaded_segment() {
+        let mut parser = fixture("pie");
+        let ph_num = parser.header.ph_entry_num();
+        let old_offset = pht_offset(&parser);
+        //the PHT of the fixture is directly followed by .interp
+        parser.grow_pht(1).unwrap();
+        assert_ne!(pht_offset(&parser), old_offset);
+
+        let parser = round_trip(&parser, "grow-pht");
+        //one entry for the caller, and one for the PT_LOAD covering the PHT
+        assert_eq!(parser.header.ph_entry_num(), ph_num + 2);
+        let pht_size = (ph_num + 2) as u64 * parser.header.ph_entry_size() as u64;
+
+        let phdr = parser.segments.iter()
+            .find(|seg| seg.p_type() == PT_type::PT_PHDR as u32)
+            .unwrap();
+        assert_eq!(pht_offset(&parser), phdr.file_offset());
+        assert_eq!(phdr.file_size(), pht_size);
+
+        let load = parser.segments.iter()
+            .find(|seg| {
+                seg.p_type() == PT_type::PT_LOAD as u32
+                    && seg.file_offset() <= phdr.file_offset()
+                    && phdr.file_offset() + pht_size <= seg.file_offset() + seg.file_size()
+            })
+            .unwrap();
+        assert_eq!(load.file_offset() % load.align(), load.vaddr() % load.align());
+        assert_eq!(phdr.vaddr() - load.vaddr(), phdr.file_offset() - load.file_offset());
+    }
 }
@@ -0,0 +1,1394 @@
+use std::io::{Read, Write};
+use byteorder::*;
+
+use crate::header::*;
+
+#[derive(Clone, Debug)]
+pub enum Relocation {
+    Rel32(Rel32),
+    Rel64(Rel64),
+    Rela32(Rela32),
+    Rela64(Rela64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Rel32 {
+    pub r_offset: u32,
+    pub r_info: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rel64 {
+    pub r_offset: u64,
+    pub r_info: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rela32 {
+    pub r_offset: u32,
+    pub r_info: u32,
+    pub r_addend: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rela64 {
+    pub r_offset: u64,
+    pub r_info: u64,
+    pub r_addend: i64,
+}
+
+impl Rel32 {
+    pub fn parse_rel<R, B: ByteOrder>(rdr: &mut R) -> Result<Rel32, std::io::Error>
+        where R: Read {
+        let offset = rdr.read_u32::<B>()?;
+        let info = rdr.read_u32::<B>()?;
+        Ok(Rel32 {
+            r_offset: offset,
+            r_info: info,
+        })
+    }
+
+    pub fn write_rel<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.r_offset)?;
+        wrtr.write_u32::<B>(self.r_info)?;
+        Ok(())
+    }
+}
+
+impl Rel64 {
+    pub fn parse_rel<R, B: ByteOrder>(rdr: &mut R) -> Result<Rel64, std::io::Error>
+        where R: Read {
+        let offset = rdr.read_u64::<B>()?;
+        let info = rdr.read_u64::<B>()?;
+        Ok(Rel64 {
+            r_offset: offset,
+            r_info: info,
+        })
+    }
+
+    pub fn write_rel<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u64::<B>(self.r_offset)?;
+        wrtr.write_u64::<B>(self.r_info)?;
+        Ok(())
+    }
+}
+
+impl Rela32 {
+    pub fn parse_rela<R, B: ByteOrder>(rdr: &mut R) -> Result<Rela32, std::io::Error>
+        where R: Read {
+        let offset = rdr.read_u32::<B>()?;
+        let info = rdr.read_u32::<B>()?;
+        let addend = rdr.read_i32::<B>()?;
+        Ok(Rela32 {
+            r_offset: offset,
+            r_info: info,
+            r_addend: addend,
+        })
+    }
+
+    pub fn write_rela<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.r_offset)?;
+        wrtr.write_u32::<B>(self.r_info)?;
+        wrtr.write_i32::<B>(self.r_addend)?;
+        Ok(())
+    }
+}
+
+impl Rela64 {
+    pub fn parse_rela<R, B: ByteOrder>(rdr: &mut R) -> Result<Rela64, std::io::Error>
+        where R: Read {
+        let offset = rdr.read_u64::<B>()?;
+        let info = rdr.read_u64::<B>()?;
+        let addend = rdr.read_i64::<B>()?;
+        Ok(Rela64 {
+            r_offset: offset,
+            r_info: info,
+            r_addend: addend,
+        })
+    }
+
+    pub fn write_rela<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u64::<B>(self.r_offset)?;
+        wrtr.write_u64::<B>(self.r_info)?;
+        wrtr.write_i64::<B>(self.r_addend)?;
+        Ok(())
+    }
+}
+
+/*
+* Accessors shared by all relocation entry kinds. r_info holds the symbol index and the
+* relocation type: ELF32_R_SYM(i) = i >> 8, ELF32_R_TYPE(i) = i & 0xff for 32 bit files, and
+* ELF64_R_SYM(i) = i >> 32, ELF64_R_TYPE(i) = i & 0xffffffff for 64 bit files.
+*/
+impl Relocation {
+    pub fn offset(&self) -> u64 {
+        match self {
+            Relocation::Rel32(rel) => rel.r_offset as u64,
+            Relocation::Rel64(rel) => rel.r_offset,
+            Relocation::Rela32(rela) => rela.r_offset as u64,
+            Relocation::Rela64(rela) => rela.r_offset,
+        }
+    }
+
+    pub fn info(&self) -> u64 {
+        match self {
+            Relocation::Rel32(rel) => rel.r_info as u64,
+            Relocation::Rel64(rel) => rel.r_info,
+            Relocation::Rela32(rela) => rela.r_info as u64,
+            Relocation::Rela64(rela) => rela.r_info,
+        }
+    }
+
+    /* Explicit addend, for RELA entries only */
+    pub fn addend(&self) -> Option<i64> {
+        match self {
+            Relocation::Rela32(rela) => Some(rela.r_addend as i64),
+            Relocation::Rela64(rela) => Some(rela.r_addend),
+            _ => None,
+        }
+    }
+
+    pub fn sym_idx(&self) -> u32 {
+        match self {
+            Relocation::Rel32(_) | Relocation::Rela32(_) => (self.info() >> 8) as u32,
+            _ => (self.info() >> 32) as u32,
+        }
+    }
+
+    pub fn rtype(&self) -> u32 {
+        match self {
+            Relocation::Rel32(_) | Relocation::Rela32(_) => self.info() as u32 & 0xff,
+            _ => self.info() as u32,
+        }
+    }
+
+    pub fn is_rela(&self) -> bool {
+        self.addend().is_some()
+    }
+
+    /* Size in bytes of the entry in the file */
+    pub fn entsize(&self) -> usize {
+        match self {
+            Relocation::Rel32(_) => 8,
+            Relocation::Rel64(_) | Relocation::Rela32(_) => 16,
+            Relocation::Rela64(_) => 24,
+        }
+    }
+
+    /* New entry of the kind used by a table of the given class; the addend is ignored for REL */
+    pub fn new(class: EXEC::EI_CLASS, rela: bool, offset: u64, sym_idx: u32, rtype: u32,
+               addend: i64) -> Result<Relocation, std::io::Error> {
+        let mut reloc = match (class, rela) {
+            (EXEC::EI_CLASS::ELFCLASS32, false) => Relocation::Rel32(Rel32 {
+                r_offset: 0,
+                r_info: 0,
+            }),
+            (EXEC::EI_CLASS::ELFCLASS32, true) => Relocation::Rela32(Rela32 {
+                r_offset: 0,
+                r_info: 0,
+                r_addend: 0,
+            }),
+            (EXEC::EI_CLASS::ELFCLASS64, false) => Relocation::Rel64(Rel64 {
+                r_offset: 0,
+                r_info: 0,
+            }),
+            (EXEC::EI_CLASS::ELFCLASS64, true) => Relocation::Rela64(Rela64 {
+                r_offset: 0,
+                r_info: 0,
+                r_addend: 0,
+            }),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported")),
+        };
+        reloc.set_offset(offset)?;
+        reloc.set_sym_idx(sym_idx)?;
+        reloc.set_rtype(rtype)?;
+        if rela {
+            reloc.set_addend(addend)?;
+        }
+        Ok(reloc)
+    }
+
+    pub fn set_offset(&mut self, offset: u64) -> Result<(), std::io::Error> {
+        match self {
+            Relocation::Rel32(rel) => rel.r_offset = narrow_u32(offset, "r_offset")?,
+            Relocation::Rel64(rel) => rel.r_offset = offset,
+            Relocation::Rela32(rela) => rela.r_offset = narrow_u32(offset, "r_offset")?,
+            Relocation::Rela64(rela) => rela.r_offset = offset,
+        }
+        Ok(())
+    }
+
+    pub fn set_info(&mut self, info: u64) -> Result<(), std::io::Error> {
+        match self {
+            Relocation::Rel32(rel) => rel.r_info = narrow_u32(info, "r_info")?,
+            Relocation::Rel64(rel) => rel.r_info = info,
+            Relocation::Rela32(rela) => rela.r_info = narrow_u32(info, "r_info")?,
+            Relocation::Rela64(rela) => rela.r_info = info,
+        }
+        Ok(())
+    }
+
+    pub fn set_sym_idx(&mut self, sym_idx: u32) -> Result<(), std::io::Error> {
+        let info = match self {
+            Relocation::Rel32(_) | Relocation::Rela32(_) => {
+                if sym_idx > 0xffffff {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Symbol index does not fit in a 32 bit r_info"))
+                }
+                (sym_idx as u64) << 8 | self.rtype() as u64
+            }
+            _ => (sym_idx as u64) << 32 | self.rtype() as u64,
+        };
+        self.set_info(info)
+    }
+
+    pub fn set_rtype(&mut self, rtype: u32) -> Result<(), std::io::Error> {
+        let info = match self {
+            Relocation::Rel32(_) | Relocation::Rela32(_) => {
+                if rtype > 0xff {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Relocation type does not fit in a 32 bit r_info"))
+                }
+                (self.sym_idx() as u64) << 8 | rtype as u64
+            }
+            _ => (self.sym_idx() as u64) << 32 | rtype as u64,
+        };
+        self.set_info(info)
+    }
+
+    /* Only RELA entries have an explicit addend */
+    pub fn set_addend(&mut self, addend: i64) -> Result<(), std::io::Error> {
+        match self {
+            Relocation::Rela32(rela) => {
+                if addend < i32::MIN as i64 || addend > i32::MAX as i64 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "r_addend does not fit in a 32 bit entry"))
+                }
+                rela.r_addend = addend as i32;
+            }
+            Relocation::Rela64(rela) => rela.r_addend = addend,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "REL entries have no r_addend field")),
+        }
+        Ok(())
+    }
+
+    pub fn write<W>(&self, wrtr: &mut W, data: EXEC::EI_DATA) -> Result<(), std::io::Error>
+        where W: Write {
+        match data {
+            EXEC::EI_DATA::ELFDATA2LSB => {
+                match self {
+                    Relocation::Rel32(rel) => rel.write_rel::<W, LittleEndian>(wrtr),
+                    Relocation::Rel64(rel) => rel.write_rel::<W, LittleEndian>(wrtr),
+                    Relocation::Rela32(rela) => rela.write_rela::<W, LittleEndian>(wrtr),
+                    Relocation::Rela64(rela) => rela.write_rela::<W, LittleEndian>(wrtr),
+                }
+            }
+            EXEC::EI_DATA::ELFDATA2MSB => {
+                match self {
+                    Relocation::Rel32(rel) => rel.write_rel::<W, BigEndian>(wrtr),
+                    Relocation::Rel64(rel) => rel.write_rel::<W, BigEndian>(wrtr),
+                    Relocation::Rela32(rela) => rela.write_rela::<W, BigEndian>(wrtr),
+                    Relocation::Rela64(rela) => rela.write_rela::<W, BigEndian>(wrtr),
+                }
+            }
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Elf not supported")),
+        }
+    }
+}
+
+fn narrow_u32(val: u64, field: &str) -> Result<u32, std::io::Error> {
+    if val > u32::MAX as u64 {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                       format!("{} value {:#x} does not fit in a 32 bit entry",
+                                               field, val)))
+    }
+    Ok(val as u32)
+}
+
+/* The symbol a relocation entry refers to, resolved from the symbol table the section links to */
+#[derive(Clone, Debug)]
+pub struct RelSymbol {
+    pub index: u32,
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub shndx: u16,
+}
+
+#[derive(Clone, Debug)]
+pub struct RelocationEntry {
+    pub reloc: Relocation,
+    //None for entries without a symbol (index 0), e.g. R_X86_64_RELATIVE
+    pub symbol: Option<RelSymbol>,
+}
+
+/* Android packed relocation (APS2) group flags */
+const RELOCATION_GROUPED_BY_INFO_FLAG: i64 = 1;
+const RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG: i64 = 2;
+const RELOCATION_GROUPED_BY_ADDEND_FLAG: i64 = 4;
+const RELOCATION_GROUP_HAS_ADDEND_FLAG: i64 = 8;
+
+/*
+* How the entries of a relocation table are stored in its section: as plain REL/RELA entries,
+* as a RELR bitmap of relative relocation offsets (SHT_RELR, SHT_ANDROID_RELR), or as Android
+* packed APS2 varints (SHT_ANDROID_REL, SHT_ANDROID_RELA)
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelocEncoding {
+    Plain,
+    Relr,
+    Aps2,
+}
+
+/*
+* Relocation table object to hold the parsed entries of a SHT_REL or SHT_RELA section, or the
+* decoded entries of a RELR or APS2 section. RELR entries are decoded into REL entries of the
+* machine's RELATIVE type. link_idx is the index of the symbol table section the entries refer
+* to (sh_link), and info_idx the index of the section they apply to (sh_info, 0 for dynamic
+* relocations).
+*/
+#[derive(Clone, Debug)]
+pub struct RelocationTable {
+    pub section_idx: u32,
+    pub sec_name: String,
+    pub link_idx: u32,
+    pub info_idx: u32,
+    pub machine: u16,
+    pub rela: bool,
+    pub class: EXEC::EI_CLASS,
+    pub data: EXEC::EI_DATA,
+    pub encoding: RelocEncoding,
+    pub entries: Vec<RelocationEntry>,
+}
+
+impl RelocationTable {
+    pub fn parse_rel_table<R>(rdr: &mut R, sec_size: u64, rela: bool,
+                              idx: u32, name: String, link: u32, info: u32,
+                              machine: u16,
+                              data: EXEC::EI_DATA,
+                              class: EXEC::EI_CLASS) -> Result<RelocationTable, std::io::Error>
+        where R: Read {
+        let entsize = match (&class, rela) {
+            (EXEC::EI_CLASS::ELFCLASS32, false) => 8,
+            (EXEC::EI_CLASS::ELFCLASS32, true) | (EXEC::EI_CLASS::ELFCLASS64, false) => 16,
+            (EXEC::EI_CLASS::ELFCLASS64, true) => 24,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Elf not supported")),
+        };
+
+        let mut entries: Vec<RelocationEntry> = Vec::new();
+        for _ in 0..sec_size / entsize {
+            let reloc = match class {
+                EXEC::EI_CLASS::ELFCLASS32 => {
+                    match data {
+                        EXEC::EI_DATA::ELFDATA2LSB if rela => {
+                            Relocation::Rela32(Rela32::parse_rela::<R, LittleEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            Relocation::Rel32(Rel32::parse_rel::<R, LittleEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB if rela => {
+                            Relocation::Rela32(Rela32::parse_rela::<R, BigEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            Relocation::Rel32(Rel32::parse_rel::<R, BigEndian>(rdr)?)
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Elf not supported"))
+                    }
+                }
+                _ => {
+                    match data {
+                        EXEC::EI_DATA::ELFDATA2LSB if rela => {
+                            Relocation::Rela64(Rela64::parse_rela::<R, LittleEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2LSB => {
+                            Relocation::Rel64(Rel64::parse_rel::<R, LittleEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB if rela => {
+                            Relocation::Rela64(Rela64::parse_rela::<R, BigEndian>(rdr)?)
+                        }
+                        EXEC::EI_DATA::ELFDATA2MSB => {
+                            Relocation::Rel64(Rel64::parse_rel::<R, BigEndian>(rdr)?)
+                        }
+                        _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                            "Elf not supported"))
+                    }
+                }
+            };
+            entries.push(RelocationEntry {
+                reloc: reloc,
+                symbol: None,
+            });
+        }
+
+        Ok(RelocationTable {
+            section_idx: idx,
+            sec_name: name,
+            link_idx: link,
+            info_idx: info,
+            machine: machine,
+            rela: rela,
+            class: class,
+            data: data,
+            encoding: RelocEncoding::Plain,
+            entries: entries,
+        })
+    }
+
+    /*
+    * Decode a RELR section: each even word is the offset of a relative relocation, and each
+    * odd word is a bitmap of the following 31 (or 63) words that are relocated too
+    */
+    pub fn parse_relr_table(bytes: &[u8], idx: u32, name: String, link: u32, info: u32,
+                            machine: u16, data: EXEC::EI_DATA,
+                            class: EXEC::EI_CLASS) -> Result<RelocationTable, std::io::Error> {
+        let relative = relative_type(machine).unwrap_or(0);
+        let word = word_size(class)?;
+        let nbits = word * 8 - 1;
+
+        let mut entries: Vec<RelocationEntry> = Vec::new();
+        let mut push = |offset: u64| -> Result<(), std::io::Error> {
+            entries.push(RelocationEntry {
+                reloc: Relocation::new(class, false, offset, 0, relative, 0)?,
+                symbol: None,
+            });
+            Ok(())
+        };
+        let mut next: u64 = 0;
+        for chunk in bytes.chunks_exact(word as usize) {
+            let entry = read_word(chunk, data)?;
+            if entry & 1 == 0 {
+                push(entry)?;
+                next = entry + word;
+            } else {
+                for bit in 0..nbits {
+                    if (entry >> (bit + 1)) & 1 == 1 {
+                        push(next + bit * word)?;
+                    }
+                }
+                next += nbits * word;
+            }
+        }
+
+        Ok(RelocationTable {
+            section_idx: idx,
+            sec_name: name,
+            link_idx: link,
+            info_idx: info,
+            machine: machine,
+            rela: false,
+            class: class,
+            data: data,
+            encoding: RelocEncoding::Relr,
+            entries: entries,
+        })
+    }
+
+    /*
+    * Decode an Android packed (APS2) section: after the `APS2` magic, all values are SLEB128
+    * varints. The header holds the entry count and the initial r_offset, followed by groups of
+    * entries: each group has a size and flags, and the r_offset delta, r_info and r_addend
+    * delta are either shared by the whole group or given for each entry
+    */
+    pub fn parse_aps2_table(bytes: &[u8], rela: bool, idx: u32, name: String, link: u32,
+                            info: u32, machine: u16, data: EXEC::EI_DATA,
+                            class: EXEC::EI_CLASS) -> Result<RelocationTable, std::io::Error> {
+        if bytes.len() < 4 || &bytes[..4] != b"APS2" {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           format!("Packed relocation section {} does not start \
+                                                   with the APS2 magic", name)))
+        }
+        let word = word_size(class)?;
+        let mut pos = 4;
+        let count = read_sleb128(bytes, &mut pos)? as u64;
+        let mut offset = read_sleb128(bytes, &mut pos)? as u64;
+        let mut info_val: u64 = 0;
+        let mut addend: i64 = 0;
+
+        let mut entries: Vec<RelocationEntry> = Vec::new();
+        while (entries.len() as u64) < count {
+            let group_size = read_sleb128(bytes, &mut pos)?;
+            let flags = read_sleb128(bytes, &mut pos)?;
+            if group_size <= 0 || entries.len() as u64 + group_size as u64 > count {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("Invalid APS2 group size in {}", name)))
+            }
+            let has_addend = flags & RELOCATION_GROUP_HAS_ADDEND_FLAG != 0;
+            if has_addend && !rela {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("APS2 group with addends in REL section {}",
+                                                       name)))
+            }
+            let offset_delta = if flags & RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG != 0 {
+                Some(read_sleb128(bytes, &mut pos)? as u64)
+            } else {
+                None
+            };
+            if flags & RELOCATION_GROUPED_BY_INFO_FLAG != 0 {
+                info_val = read_sleb128(bytes, &mut pos)? as u64;
+            }
+            let grouped_addend = flags & RELOCATION_GROUPED_BY_ADDEND_FLAG != 0;
+            if has_addend && grouped_addend {
+                addend = addend.wrapping_add(read_sleb128(bytes, &mut pos)?);
+            } else if !has_addend {
+                addend = 0;
+            }
+
+            for _ in 0..group_size {
+                offset = offset.wrapping_add(match offset_delta {
+                    Some(delta) => delta,
+                    None => read_sleb128(bytes, &mut pos)? as u64,
+                });
+                if flags & RELOCATION_GROUPED_BY_INFO_FLAG == 0 {
+                    info_val = read_sleb128(bytes, &mut pos)? as u64;
+                }
+                if has_addend && !grouped_addend {
+                    addend = addend.wrapping_add(read_sleb128(bytes, &mut pos)?);
+                }
+
+                let mut reloc = Relocation::new(class, rela, 0, 0, 0, 0)?;
+                reloc.set_offset(truncate_word(offset, word))?;
+                reloc.set_info(truncate_word(info_val, word))?;
+                if rela {
+                    reloc.set_addend(if word == 4 { addend as i32 as i64 } else { addend })?;
+                }
+                entries.push(RelocationEntry {
+                    reloc: reloc,
+                    symbol: None,
+                });
+            }
+        }
+
+        Ok(RelocationTable {
+            section_idx: idx,
+            sec_name: name,
+            link_idx: link,
+            info_idx: info,
+            machine: machine,
+            rela: rela,
+            class: class,
+            data: data,
+            encoding: RelocEncoding::Aps2,
+            entries: entries,
+        })
+    }
+
+    /* Relocation type of an entry, named for the machine of the file */
+    pub fn rtype(&self, idx: usize) -> RelocType {
+        RelocType::new(self.machine, self.entries[idx].reloc.rtype())
+    }
+
+    /* Serialize the entries back into section contents, in the table's encoding */
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        match self.encoding {
+            RelocEncoding::Plain => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for entry in &self.entries {
+                    entry.reloc.write(&mut bytes, self.data)?;
+                }
+                Ok(bytes)
+            }
+            RelocEncoding::Relr => self.encode_relr(),
+            RelocEncoding::Aps2 => self.encode_aps2(),
+        }
+    }
+
+    /*
+    * Sort the entries into the order they are encoded in: RELR tables hold sorted offsets,
+    * so entries are kept sorted (and unique) by r_offset for their indexes to match the section
+    */
+    pub fn sort_entries(&mut self) {
+        if self.encoding == RelocEncoding::Relr {
+            self.entries.sort_by_key(|entry| entry.reloc.offset());
+            self.entries.dedup_by_key(|entry| entry.reloc.offset());
+        }
+    }
+
+    /*
+    * Encode the entries as a RELR bitmap. Every entry must be a RELATIVE relocation without a
+    * symbol at a word aligned offset, since RELR only stores offsets.
+    */
+    fn encode_relr(&self) -> Result<Vec<u8>, std::io::Error> {
+        let relative = relative_type(self.machine);
+        let word = word_size(self.class)?;
+        let nbits = word * 8 - 1;
+
+        let mut offsets: Vec<u64> = Vec::new();
+        for entry in &self.entries {
+            let reloc = &entry.reloc;
+            if Some(reloc.rtype()) != relative || reloc.sym_idx() != 0 || reloc.offset() % word != 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                               format!("RELR section {} can only hold RELATIVE \
+                                                       relocations without a symbol at word \
+                                                       aligned offsets", self.sec_name)))
+            }
+            offsets.push(reloc.offset());
+        }
+        offsets.sort();
+        offsets.dedup();
+
+        let mut words: Vec<u64> = Vec::new();
+        let mut i = 0;
+        while i < offsets.len() {
+            words.push(offsets[i]);
+            let mut next = offsets[i] + word;
+            i += 1;
+            loop {
+                let mut bitmap: u64 = 0;
+                while i < offsets.len() {
+                    let delta = offsets[i] - next;
+                    if delta >= nbits * word {
+                        break
+                    }
+                    bitmap |= 1 << (delta / word);
+                    i += 1;
+                }
+                if bitmap == 0 {
+                    break
+                }
+                words.push(bitmap << 1 | 1);
+                next += nbits * word;
+            }
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        for val in words {
+            write_word(&mut bytes, val, word, self.data)?;
+        }
+        Ok(bytes)
+    }
+
+    /*
+    * Encode the entries in the APS2 format. Consecutive entries with the same r_info are
+    * grouped, sharing the r_offset delta and r_addend when these are the same for the group.
+    */
+    fn encode_aps2(&self) -> Result<Vec<u8>, std::io::Error> {
+        let word = word_size(self.class)?;
+        //32 bit loaders decode 32 bit varints, so encode 32 bit values sign extended
+        let value = |val: u64| -> i64 {
+            if word == 4 { val as u32 as i32 as i64 } else { val as i64 }
+        };
+
+        let mut bytes: Vec<u8> = b"APS2".to_vec();
+        write_sleb128(&mut bytes, self.entries.len() as i64);
+        write_sleb128(&mut bytes, 0);
+
+        let mut prev_offset: u64 = 0;
+        let mut prev_addend: i64 = 0;
+        let mut i = 0;
+        while i < self.entries.len() {
+            let info = self.entries[i].reloc.info();
+            let mut j = i;
+            while j < self.entries.len() && self.entries[j].reloc.info() == info {
+                j += 1;
+            }
+            let group = &self.entries[i..j];
+
+            let mut deltas: Vec<u64> = Vec::new();
+            let mut last = prev_offset;
+            for entry in group {
+                deltas.push(entry.reloc.offset().wrapping_sub(last));
+                last = entry.reloc.offset();
+            }
+            let addends: Vec<i64> = group.iter().map(|entry| entry.reloc.addend().unwrap_or(0))
+                .collect();
+            let has_addend = addends.iter().any(|&addend| addend != 0);
+            let same_delta = deltas.iter().all(|&delta| delta == deltas[0]);
+            let same_addend = addends.iter().all(|&addend| addend == addends[0]);
+
+            let mut flags = RELOCATION_GROUPED_BY_INFO_FLAG;
+            if same_delta {
+                flags |= RELOCATION_GROUPED_BY_OFFSET_DELTA_FLAG;
+            }
+            if has_addend {
+                flags |= RELOCATION_GROUP_HAS_ADDEND_FLAG;
+                if same_addend {
+                    flags |= RELOCATION_GROUPED_BY_ADDEND_FLAG;
+                }
+            }
+
+            write_sleb128(&mut bytes, group.len() as i64);
+            write_sleb128(&mut bytes, flags);
+            if same_delta {
+                write_sleb128(&mut bytes, value(deltas[0]));
+            }
+            write_sleb128(&mut bytes, value(info));
+            if has_addend && same_addend {
+                write_sleb128(&mut bytes, addends[0].wrapping_sub(prev_addend));
+            }
+            for k in 0..group.len() {
+                if !same_delta {
+                    write_sleb128(&mut bytes, value(deltas[k]));
+                }
+                if has_addend && !same_addend {
+                    let prev = if k == 0 { prev_addend } else { addends[k - 1] };
+                    write_sleb128(&mut bytes, addends[k].wrapping_sub(prev));
+                }
+            }
+
+            prev_offset = last;
+            prev_addend = if has_addend { addends[addends.len() - 1] } else { 0 };
+            i = j;
+        }
+        Ok(bytes)
+    }
+
+    /*
+    * Number of RELATIVE entries at the start of the table, the value of DT_RELACOUNT or
+    * DT_RELCOUNT (the loader may process that many entries without symbol lookups)
+    */
+    pub fn relative_count(&self) -> u64 {
+        let relative = match relative_type(self.machine) {
+            Some(relative) => relative,
+            None => return 0,
+        };
+        self.entries.iter().take_while(|entry| entry.reloc.rtype() == relative).count() as u64
+    }
+}
+
+fn word_size(class: EXEC::EI_CLASS) -> Result<u64, std::io::Error> {
+    match class {
+        EXEC::EI_CLASS::ELFCLASS32 => Ok(4),
+        EXEC::EI_CLASS::ELFCLASS64 => Ok(8),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+fn truncate_word(val: u64, word: u64) -> u64 {
+    if word == 4 { val as u32 as u64 } else { val }
+}
+
+fn read_word(bytes: &[u8], data: EXEC::EI_DATA) -> Result<u64, std::io::Error> {
+    match (bytes.len(), data) {
+        (4, EXEC::EI_DATA::ELFDATA2LSB) => Ok(LittleEndian::read_u32(bytes) as u64),
+        (4, EXEC::EI_DATA::ELFDATA2MSB) => Ok(BigEndian::read_u32(bytes) as u64),
+        (8, EXEC::EI_DATA::ELFDATA2LSB) => Ok(LittleEndian::read_u64(bytes)),
+        (8, EXEC::EI_DATA::ELFDATA2MSB) => Ok(BigEndian::read_u64(bytes)),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+fn write_word<W>(wrtr: &mut W, val: u64, word: u64,
+                 data: EXEC::EI_DATA) -> Result<(), std::io::Error>
+    where W: Write {
+    match (word, data) {
+        (4, EXEC::EI_DATA::ELFDATA2LSB) => wrtr.write_u32::<LittleEndian>(val as u32),
+        (4, EXEC::EI_DATA::ELFDATA2MSB) => wrtr.write_u32::<BigEndian>(val as u32),
+        (8, EXEC::EI_DATA::ELFDATA2LSB) => wrtr.write_u64::<LittleEndian>(val),
+        (8, EXEC::EI_DATA::ELFDATA2MSB) => wrtr.write_u64::<BigEndian>(val),
+        _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                     "Elf not supported")),
+    }
+}
+
+fn read_sleb128(bytes: &[u8], pos: &mut usize) -> Result<i64, std::io::Error> {
+    let mut val: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = match bytes.get(*pos) {
+            Some(&byte) => byte,
+            None => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "Truncated SLEB128 value in packed relocations")),
+        };
+        *pos += 1;
+        if shift < 64 {
+            val |= ((byte & 0x7f) as i64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                val |= -1 << shift;
+            }
+            return Ok(val)
+        }
+    }
+}
+
+fn write_sleb128(bytes: &mut Vec<u8>, mut val: i64) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if (val == 0 && byte & 0x40 == 0) || (val == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/* The RELATIVE relocation type of a machine, if known */
+pub fn relative_type(machine: u16) -> Option<u32> {
+    match match_mach(machine) {
+        Ok(EXEC::EI_MACH::EM_X86_64) => Some(X86_64_Reloc::R_X86_64_RELATIVE as u32),
+        Ok(EXEC::EI_MACH::EM_386) => Some(I386_Reloc::R_386_RELATIVE as u32),
+        Ok(EXEC::EI_MACH::EM_ARM) => Some(ARM_Reloc::R_ARM_RELATIVE as u32),
+        Ok(EXEC::EI_MACH::EM_AARCH64) => Some(AArch64_Reloc::R_AARCH64_RELATIVE as u32),
+        Ok(EXEC::EI_MACH::EM_RISCV) => Some(RISCV_Reloc::R_RISCV_RELATIVE as u32),
+        _ => None,
+    }
+}
+
+/*
+* Generates a relocation type enum with conversions from the raw type value and from the
+* type name, e.g. "R_X86_64_PC32".
+*/
+macro_rules! reloc_types {
+    ($name:ident { $($variant:ident = $val:expr,)* }) => {
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum $name {
+            $($variant = $val,)*
+        }
+
+        impl $name {
+            pub fn from_u32(val: u32) -> Option<$name> {
+                match val {
+                    $($val => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<$name> {
+                match name {
+                    $(stringify!($variant) => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+reloc_types!(X86_64_Reloc {
+    R_X86_64_NONE = 0,
+    R_X86_64_64 = 1,
+    R_X86_64_PC32 = 2,
+    R_X86_64_GOT32 = 3,
+    R_X86_64_PLT32 = 4,
+    R_X86_64_COPY = 5,
+    R_X86_64_GLOB_DAT = 6,
+    R_X86_64_JUMP_SLOT = 7,
+    R_X86_64_RELATIVE = 8,
+    R_X86_64_GOTPCREL = 9,
+    R_X86_64_32 = 10,
+    R_X86_64_32S = 11,
+    R_X86_64_16 = 12,
+    R_X86_64_PC16 = 13,
+    R_X86_64_8 = 14,
+    R_X86_64_PC8 = 15,
+    R_X86_64_DTPMOD64 = 16,
+    R_X86_64_DTPOFF64 = 17,
+    R_X86_64_TPOFF64 = 18,
+    R_X86_64_TLSGD = 19,
+    R_X86_64_TLSLD = 20,
+    R_X86_64_DTPOFF32 = 21,
+    R_X86_64_GOTTPOFF = 22,
+    R_X86_64_TPOFF32 = 23,
+    R_X86_64_PC64 = 24,
+    R_X86_64_GOTOFF64 = 25,
+    R_X86_64_GOTPC32 = 26,
+    R_X86_64_GOT64 = 27,
+    R_X86_64_GOTPCREL64 = 28,
+    R_X86_64_GOTPC64 = 29,
+    R_X86_64_GOTPLT64 = 30,
+    R_X86_64_PLTOFF64 = 31,
+    R_X86_64_SIZE32 = 32,
+    R_X86_64_SIZE64 = 33,
+    R_X86_64_GOTPC32_TLSDESC = 34,
+    R_X86_64_TLSDESC_CALL = 35,
+    R_X86_64_TLSDESC = 36,
+    R_X86_64_IRELATIVE = 37,
+    R_X86_64_RELATIVE64 = 38,
+    R_X86_64_GOTPCRELX = 41,
+    R_X86_64_REX_GOTPCRELX = 42,
+});
+
+reloc_types!(I386_Reloc {
+    R_386_NONE = 0,
+    R_386_32 = 1,
+    R_386_PC32 = 2,
+    R_386_GOT32 = 3,
+    R_386_PLT32 = 4,
+    R_386_COPY = 5,
+    R_386_GLOB_DAT = 6,
+    R_386_JMP_SLOT = 7,
+    R_386_RELATIVE = 8,
+    R_386_GOTOFF = 9,
+    R_386_GOTPC = 10,
+    R_386_32PLT = 11,
+    R_386_TLS_TPOFF = 14,
+    R_386_TLS_IE = 15,
+    R_386_TLS_GOTIE = 16,
+    R_386_TLS_LE = 17,
+    R_386_TLS_GD = 18,
+    R_386_TLS_LDM = 19,
+    R_386_16 = 20,
+    R_386_PC16 = 21,
+    R_386_8 = 22,
+    R_386_PC8 = 23,
+    R_386_TLS_LDO_32 = 32,
+    R_386_TLS_IE_32 = 33,
+    R_386_TLS_LE_32 = 34,
+    R_386_TLS_DTPMOD32 = 35,
+    R_386_TLS_DTPOFF32 = 36,
+    R_386_TLS_TPOFF32 = 37,
+    R_386_SIZE32 = 38,
+    R_386_TLS_GOTDESC = 39,
+    R_386_TLS_DESC_CALL = 40,
+    R_386_TLS_DESC = 41,
+    R_386_IRELATIVE = 42,
+    R_386_GOT32X = 43,
+});
+
+reloc_types!(ARM_Reloc {
+    R_ARM_NONE = 0,
+    R_ARM_PC24 = 1,
+    R_ARM_ABS32 = 2,
+    R_ARM_REL32 = 3,
+    R_ARM_LDR_PC_G0 = 4,
+    R_ARM_ABS16 = 5,
+    R_ARM_ABS12 = 6,
+    R_ARM_THM_ABS5 = 7,
+    R_ARM_ABS8 = 8,
+    R_ARM_SBREL32 = 9,
+    R_ARM_THM_CALL = 10,
+    R_ARM_THM_PC8 = 11,
+    R_ARM_BREL_ADJ = 12,
+    R_ARM_TLS_DESC = 13,
+    R_ARM_TLS_DTPMOD32 = 17,
+    R_ARM_TLS_DTPOFF32 = 18,
+    R_ARM_TLS_TPOFF32 = 19,
+    R_ARM_COPY = 20,
+    R_ARM_GLOB_DAT = 21,
+    R_ARM_JUMP_SLOT = 22,
+    R_ARM_RELATIVE = 23,
+    R_ARM_GOTOFF32 = 24,
+    R_ARM_BASE_PREL = 25,
+    R_ARM_GOT_BREL = 26,
+    R_ARM_PLT32 = 27,
+    R_ARM_CALL = 28,
+    R_ARM_JUMP24 = 29,
+    R_ARM_THM_JUMP24 = 30,
+    R_ARM_BASE_ABS = 31,
+    R_ARM_TARGET1 = 38,
+    R_ARM_V4BX = 40,
+    R_ARM_TARGET2 = 41,
+    R_ARM_PREL31 = 42,
+    R_ARM_MOVW_ABS_NC = 43,
+    R_ARM_MOVT_ABS = 44,
+    R_ARM_MOVW_PREL_NC = 45,
+    R_ARM_MOVT_PREL = 46,
+    R_ARM_THM_MOVW_ABS_NC = 47,
+    R_ARM_THM_MOVT_ABS = 48,
+    R_ARM_THM_MOVW_PREL_NC = 49,
+    R_ARM_THM_MOVT_PREL = 50,
+    R_ARM_THM_JUMP19 = 51,
+    R_ARM_GOT_ABS = 95,
+    R_ARM_GOT_PREL = 96,
+    R_ARM_GOT_BREL12 = 97,
+    R_ARM_GOTOFF12 = 98,
+    R_ARM_GOTRELAX = 99,
+    R_ARM_THM_JUMP11 = 102,
+    R_ARM_THM_JUMP8 = 103,
+    R_ARM_TLS_GD32 = 104,
+    R_ARM_TLS_LDM32 = 105,
+    R_ARM_TLS_LDO32 = 106,
+    R_ARM_TLS_IE32 = 107,
+    R_ARM_TLS_LE32 = 108,
+    R_ARM_IRELATIVE = 160,
+});
+
+reloc_types!(AArch64_Reloc {
+    R_AARCH64_NONE = 0,
+    R_AARCH64_NULL = 256,
+    R_AARCH64_ABS64 = 257,
+    R_AARCH64_ABS32 = 258,
+    R_AARCH64_ABS16 = 259,
+    R_AARCH64_PREL64 = 260,
+    R_AARCH64_PREL32 = 261,
+    R_AARCH64_PREL16 = 262,
+    R_AARCH64_MOVW_UABS_G0 = 263,
+    R_AARCH64_MOVW_UABS_G0_NC = 264,
+    R_AARCH64_MOVW_UABS_G1 = 265,
+    R_AARCH64_MOVW_UABS_G1_NC = 266,
+    R_AARCH64_MOVW_UABS_G2 = 267,
+    R_AARCH64_MOVW_UABS_G2_NC = 268,
+    R_AARCH64_MOVW_UABS_G3 = 269,
+    R_AARCH64_LD_PREL_LO19 = 273,
+    R_AARCH64_ADR_PREL_LO21 = 274,
+    R_AARCH64_ADR_PREL_PG_HI21 = 275,
+    R_AARCH64_ADR_PREL_PG_HI21_NC = 276,
+    R_AARCH64_ADD_ABS_LO12_NC = 277,
+    R_AARCH64_LDST8_ABS_LO12_NC = 278,
+    R_AARCH64_TSTBR14 = 279,
+    R_AARCH64_CONDBR19 = 280,
+    R_AARCH64_JUMP26 = 282,
+    R_AARCH64_CALL26 = 283,
+    R_AARCH64_LDST16_ABS_LO12_NC = 284,
+    R_AARCH64_LDST32_ABS_LO12_NC = 285,
+    R_AARCH64_LDST64_ABS_LO12_NC = 286,
+    R_AARCH64_LDST128_ABS_LO12_NC = 299,
+    R_AARCH64_ADR_GOT_PAGE = 311,
+    R_AARCH64_LD64_GOT_LO12_NC = 312,
+    R_AARCH64_TLSGD_ADR_PAGE21 = 513,
+    R_AARCH64_TLSGD_ADD_LO12_NC = 514,
+    R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21 = 541,
+    R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC = 542,
+    R_AARCH64_TLSLE_ADD_TPREL_HI12 = 549,
+    R_AARCH64_TLSLE_ADD_TPREL_LO12_NC = 551,
+    R_AARCH64_TLSDESC_ADR_PAGE21 = 562,
+    R_AARCH64_TLSDESC_LD64_LO12 = 563,
+    R_AARCH64_TLSDESC_ADD_LO12 = 564,
+    R_AARCH64_TLSDESC_CALL = 569,
+    R_AARCH64_COPY = 1024,
+    R_AARCH64_GLOB_DAT = 1025,
+    R_AARCH64_JUMP_SLOT = 1026,
+    R_AARCH64_RELATIVE = 1027,
+    R_AARCH64_TLS_DTPMOD = 1028,
+    R_AARCH64_TLS_DTPREL = 1029,
+    R_AARCH64_TLS_TPREL = 1030,
+    R_AARCH64_TLSDESC = 1031,
+    R_AARCH64_IRELATIVE = 1032,
+});
+
+reloc_types!(RISCV_Reloc {
+    R_RISCV_NONE = 0,
+    R_RISCV_32 = 1,
+    R_RISCV_64 = 2,
+    R_RISCV_RELATIVE = 3,
+    R_RISCV_COPY = 4,
+    R_RISCV_JUMP_SLOT = 5,
+    R_RISCV_TLS_DTPMOD32 = 6,
+    R_RISCV_TLS_DTPMOD64 = 7,
+    R_RISCV_TLS_DTPREL32 = 8,
+    R_RISCV_TLS_DTPREL64 = 9,
+    R_RISCV_TLS_TPREL32 = 10,
+    R_RISCV_TLS_TPREL64 = 11,
+    R_RISCV_TLSDESC = 12,
+    R_RISCV_BRANCH = 16,
+    R_RISCV_JAL = 17,
+    R_RISCV_CALL = 18,
+    R_RISCV_CALL_PLT = 19,
+    R_RISCV_GOT_HI20 = 20,
+    R_RISCV_TLS_GOT_HI20 = 21,
+    R_RISCV_TLS_GD_HI20 = 22,
+    R_RISCV_PCREL_HI20 = 23,
+    R_RISCV_PCREL_LO12_I = 24,
+    R_RISCV_PCREL_LO12_S = 25,
+    R_RISCV_HI20 = 26,
+    R_RISCV_LO12_I = 27,
+    R_RISCV_LO12_S = 28,
+    R_RISCV_TPREL_HI20 = 29,
+    R_RISCV_TPREL_LO12_I = 30,
+    R_RISCV_TPREL_LO12_S = 31,
+    R_RISCV_TPREL_ADD = 32,
+    R_RISCV_ADD8 = 33,
+    R_RISCV_ADD16 = 34,
+    R_RISCV_ADD32 = 35,
+    R_RISCV_ADD64 = 36,
+    R_RISCV_SUB8 = 37,
+    R_RISCV_SUB16 = 38,
+    R_RISCV_SUB32 = 39,
+    R_RISCV_SUB64 = 40,
+    R_RISCV_ALIGN = 43,
+    R_RISCV_RVC_BRANCH = 44,
+    R_RISCV_RVC_JUMP = 45,
+    R_RISCV_RELAX = 51,
+    R_RISCV_SUB6 = 52,
+    R_RISCV_SET6 = 53,
+    R_RISCV_SET8 = 54,
+    R_RISCV_SET16 = 55,
+    R_RISCV_SET32 = 56,
+    R_RISCV_32_PCREL = 57,
+    R_RISCV_IRELATIVE = 58,
+    R_RISCV_PLT32 = 59,
+    R_RISCV_SET_ULEB128 = 60,
+    R_RISCV_SUB_ULEB128 = 61,
+});
+
+/* A relocation type, interpreted for the machine of the file it belongs to */
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelocType {
+    X86_64(X86_64_Reloc),
+    I386(I386_Reloc),
+    ARM(ARM_Reloc),
+    AARCH64(AArch64_Reloc),
+    RISCV(RISCV_Reloc),
+    Unknown(u32),
+}
+
+impl RelocType {
+    pub fn new(machine: u16, rtype: u32) -> RelocType {
+        let known = match match_mach(machine) {
+            Ok(EXEC::EI_MACH::EM_X86_64) => X86_64_Reloc::from_u32(rtype).map(RelocType::X86_64),
+            Ok(EXEC::EI_MACH::EM_386) => I386_Reloc::from_u32(rtype).map(RelocType::I386),
+            Ok(EXEC::EI_MACH::EM_ARM) => ARM_Reloc::from_u32(rtype).map(RelocType::ARM),
+            Ok(EXEC::EI_MACH::EM_AARCH64) => AArch64_Reloc::from_u32(rtype).map(RelocType::AARCH64),
+            Ok(EXEC::EI_MACH::EM_RISCV) => RISCV_Reloc::from_u32(rtype).map(RelocType::RISCV),
+            _ => None,
+        };
+        known.unwrap_or(RelocType::Unknown(rtype))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            RelocType::X86_64(rtype) => format!("{:?}", rtype),
+            RelocType::I386(rtype) => format!("{:?}", rtype),
+            RelocType::ARM(rtype) => format!("{:?}", rtype),
+            RelocType::AARCH64(rtype) => format!("{:?}", rtype),
+            RelocType::RISCV(rtype) => format!("{:?}", rtype),
+            RelocType::Unknown(rtype) => format!("{:#x}", rtype),
+        }
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            RelocType::X86_64(rtype) => *rtype as u32,
+            RelocType::I386(rtype) => *rtype as u32,
+            RelocType::ARM(rtype) => *rtype as u32,
+            RelocType::AARCH64(rtype) => *rtype as u32,
+            RelocType::RISCV(rtype) => *rtype as u32,
+            RelocType::Unknown(rtype) => *rtype,
+        }
+    }
+}
+
+/*
+* Get the raw relocation type value for a machine from a type name (e.g. `R_X86_64_RELATIVE`)
+* or a hex value
+*/
+pub fn match_reloc_type_as_str(machine: u16, rtype: String) -> Result<u32, std::io::Error> {
+    let known = match match_mach(machine) {
+        Ok(EXEC::EI_MACH::EM_X86_64) => X86_64_Reloc::from_name(&rtype).map(|r| r as u32),
+        Ok(EXEC::EI_MACH::EM_386) => I386_Reloc::from_name(&rtype).map(|r| r as u32),
+        Ok(EXEC::EI_MACH::EM_ARM) => ARM_Reloc::from_name(&rtype).map(|r| r as u32),
+        Ok(EXEC::EI_MACH::EM_AARCH64) => AArch64_Reloc::from_name(&rtype).map(|r| r as u32),
+        Ok(EXEC::EI_MACH::EM_RISCV) => RISCV_Reloc::from_name(&rtype).map(|r| r as u32),
+        _ => None,
+    };
+    if let Some(rtype) = known {
+        return Ok(rtype)
+    }
+    match u32::from_str_radix(rtype.trim_start_matches("0x"), 16) {
+        Ok(rtype) => Ok(rtype),
+        Err(_) => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                          format!("Invalid relocation type {} for this machine", rtype))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EM_386: u16 = EXEC::EI_MACH::EM_386 as u16;
+    const EM_X86_64: u16 = EXEC::EI_MACH::EM_X86_64 as u16;
+
+    /* A table of the given encoding holding (r_offset, sym_idx, r_type, r_addend) entries */
+    fn table(encoding: RelocEncoding, machine: u16, class: EXEC::EI_CLASS, rela: bool,
+             entries: &[(u64, u32, u32, i64)]) -> RelocationTable {
+        RelocationTable {
+            section_idx: 1,
+            sec_name: ".rela.dyn".to_string(),
+            link_idx: 0,
+            info_idx: 0,
+            machine: machine,
+            rela: rela,
+            class: class,
+            data: EXEC::EI_DATA::ELFDATA2LSB,
+            encoding: encoding,
+            entries: entries.iter().map(|&(offset, sym, rtype, addend)| RelocationEntry {
+                reloc: Relocation::new(class, rela, offset, sym, rtype, addend).unwrap(),
+                symbol: None,
+            }).collect(),
+        }
+    }
+
+    fn fields(table: &RelocationTable) -> Vec<(u64, u64, Option<i64>)> {
+        table.entries.iter()
+            .map(|entry| (entry.reloc.offset(), entry.reloc.info(), entry.reloc.addend()))
+            .collect()
+    }
+
+    fn words64(bytes: &[u8]) -> Vec<u64> {
+        bytes.chunks_exact(8).map(LittleEndian::read_u64).collect()
+    }
+
+    #[test]
+    fn sleb128_encodings() {
+        let cases: &[(i64, &[u8])] = &[
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (-1, &[0x7f]),
+            (63, &[0x3f]),
+            (64, &[0xc0, 0x00]),
+            (-64, &[0x40]),
+            (-65, &[0xbf, 0x7f]),
+            (127, &[0xff, 0x00]),
+            (128, &[0x80, 0x01]),
+            (-128, &[0x80, 0x7f]),
+        ];
+        for &(val, encoded) in cases {
+            let mut bytes = Vec::new();
+            write_sleb128(&mut bytes, val);
+            assert_eq!(bytes, encoded, "encoding {}", val);
+            let mut pos = 0;
+            assert_eq!(read_sleb128(&bytes, &mut pos).unwrap(), val);
+            assert_eq!(pos, bytes.len());
+        }
+    }
+
+    #[test]
+    fn sleb128_round_trip_boundaries() {
+        let vals = [i64::MAX, i64::MIN, i64::MAX - 1, i64::MIN + 1, i32::MAX as i64,
+                    i32::MIN as i64, u32::MAX as i64, -(u32::MAX as i64), 1 << 62, -(1 << 62)];
+        let mut bytes = Vec::new();
+        for &val in &vals {
+            write_sleb128(&mut bytes, val);
+        }
+        let mut pos = 0;
+        for &val in &vals {
+            assert_eq!(read_sleb128(&bytes, &mut pos).unwrap(), val);
+        }
+        assert_eq!(pos, bytes.len());
+    }
+
+    #[test]
+    fn sleb128_truncated() {
+        let mut pos = 0;
+        assert!(read_sleb128(&[0x80, 0x80], &mut pos).is_err());
+    }
+
+    #[test]
+    fn relr_multi_word_bitmap_and_gap() {
+        let relative = X86_64_Reloc::R_X86_64_RELATIVE as u32;
+        //0x1230 is past the first bitmap (63 words after 0x1008), 0x10000 needs a new address
+        let offsets = [0x1000, 0x1008, 0x1010, 0x1230, 0x10000];
+        let entries: Vec<(u64, u32, u32, i64)> = offsets.iter().map(|&off| (off, 0, relative, 0))
+            .collect();
+        let relr = table(RelocEncoding::Relr, EM_X86_64, EXEC::EI_CLASS::ELFCLASS64, false,
+                         &entries);
+        let bytes = relr.to_bytes().unwrap();
+        assert_eq!(words64(&bytes), vec![0x1000, 0b11 << 1 | 1, 1 << 7 | 1, 0x10000]);
+
+        let parsed = RelocationTable::parse_relr_table(&bytes, 1, ".relr.dyn".to_string(), 0, 0,
+                                                       EM_X86_64, EXEC::EI_DATA::ELFDATA2LSB,
+                                                       EXEC::EI_CLASS::ELFCLASS64).unwrap();
+        assert_eq!(fields(&parsed), fields(&relr));
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn relr_32_bit_round_trip() {
+        let relative = I386_Reloc::R_386_RELATIVE as u32;
+        let offsets: Vec<u64> = (0..40).map(|i| 0x2000 + 4 * i).chain(vec![0x3000, 0x3004])
+            .collect();
+        let entries: Vec<(u64, u32, u32, i64)> = offsets.iter().map(|&off| (off, 0, relative, 0))
+            .collect();
+        let relr = table(RelocEncoding::Relr, EM_386, EXEC::EI_CLASS::ELFCLASS32, false,
+                         &entries);
+        let bytes = relr.to_bytes().unwrap();
+        //address, full 31 bit bitmap, bitmap of the remaining 8 words, address, bitmap
+        assert_eq!(bytes.len(), 5 * 4);
+        let parsed = RelocationTable::parse_relr_table(&bytes, 1, ".relr.dyn".to_string(), 0, 0,
+                                                       EM_386, EXEC::EI_DATA::ELFDATA2LSB,
+                                                       EXEC::EI_CLASS::ELFCLASS32).unwrap();
+        assert_eq!(fields(&parsed), fields(&relr));
+    }
+
+    #[test]
+    fn relr_rejects_unaligned_offsets() {
+        let relative = X86_64_Reloc::R_X86_64_RELATIVE as u32;
+        let relr = table(RelocEncoding::Relr, EM_X86_64, EXEC::EI_CLASS::ELFCLASS64, false,
+                         &[(0x1004, 0, relative, 0)]);
+        assert!(relr.to_bytes().is_err());
+    }
+
+    /* Entries exercising grouped and ungrouped offset deltas, infos and addends */
+    fn aps2_entries(rela: bool) -> Vec<(u64, u32, u32, i64)> {
+        let relative = X86_64_Reloc::R_X86_64_RELATIVE as u32;
+        let glob_dat = X86_64_Reloc::R_X86_64_GLOB_DAT as u32;
+        let jump_slot = X86_64_Reloc::R_X86_64_JUMP_SLOT as u32;
+        let addend = |val: i64| if rela { val } else { 0 };
+        vec![
+            //same delta and info, different addends
+            (0x1000, 0, relative, addend(0x10)),
+            (0x1008, 0, relative, addend(0x20)),
+            (0x1010, 0, relative, addend(-0x30)),
+            //same delta and info, no addends
+            (0x2000, 1, glob_dat, 0),
+            (0x2010, 1, glob_dat, 0),
+            //different deltas, same addend
+            (0x3000, 2, jump_slot, addend(5)),
+            (0x3100, 2, jump_slot, addend(5)),
+            (0x3108, 2, jump_slot, addend(5)),
+            //a single entry at a lower offset
+            (0x800, 3, glob_dat, addend(-1)),
+        ]
+    }
+
+    #[test]
+    fn aps2_round_trip_with_addends() {
+        let aps2 = table(RelocEncoding::Aps2, EM_X86_64, EXEC::EI_CLASS::ELFCLASS64, true,
+                         &aps2_entries(true));
+        let bytes = aps2.to_bytes().unwrap();
+        assert_eq!(&bytes[..4], b"APS2");
+        let parsed = RelocationTable::parse_aps2_table(&bytes, true, 1, ".rela.dyn".to_string(),
+                                                       0, 0, EM_X86_64,
+                                                       EXEC::EI_DATA::ELFDATA2LSB,
+                                                       EXEC::EI_CLASS::ELFCLASS64).unwrap();
+        assert_eq!(fields(&parsed), fields(&aps2));
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn aps2_round_trip_without_addends() {
+        let aps2 = table(RelocEncoding::Aps2, EM_X86_64, EXEC::EI_CLASS::ELFCLASS64, false,
+                         &aps2_entries(false));
+        let bytes = aps2.to_bytes().unwrap();
+        let parsed = RelocationTable::parse_aps2_table(&bytes, false, 1, ".rel.dyn".to_string(),
+                                                       0, 0, EM_X86_64,
+                                                       EXEC::EI_DATA::ELFDATA2LSB,
+                                                       EXEC::EI_CLASS::ELFCLASS64).unwrap();
+        assert_eq!(fields(&parsed), fields(&aps2));
+
+        //groups with addends are invalid in a REL section
+        let rela = table(RelocEncoding::Aps2, EM_X86_64, EXEC::EI_CLASS::ELFCLASS64, true,
+                         &aps2_entries(true));
+        assert!(RelocationTable::parse_aps2_table(&rela.to_bytes().unwrap(), false, 1,
+                                                  ".rel.dyn".to_string(), 0, 0, EM_X86_64,
+                                                  EXEC::EI_DATA::ELFDATA2LSB,
+                                                  EXEC::EI_CLASS::ELFCLASS64).is_err());
+    }
+
+    #[test]
+    fn aps2_32_bit_round_trip() {
+        let relative = I386_Reloc::R_386_RELATIVE as u32;
+        let glob_dat = I386_Reloc::R_386_GLOB_DAT as u32;
+        let entries = [(0xfffff000, 0, relative, -8), (0xfffff004, 0, relative, 0x7fffffff),
+                       (0x100, 2, glob_dat, i32::MIN as i64)];
+        let aps2 = table(RelocEncoding::Aps2, EM_386, EXEC::EI_CLASS::ELFCLASS32, true, &entries);
+        let bytes = aps2.to_bytes().unwrap();
+        let parsed = RelocationTable::parse_aps2_table(&bytes, true, 1, ".rela.dyn".to_string(),
+                                                       0, 0, EM_386, EXEC::EI_DATA::ELFDATA2LSB,
+                                                       EXEC::EI_CLASS::ELFCLASS32).unwrap();
+        assert_eq!(fields(&parsed), fields(&aps2));
+    }
+
+    #[test]
+    fn aps2_bad_magic() {
+        assert!(RelocationTable::parse_aps2_table(b"APS1\x00\x00", true, 1, ".rela.dyn".to_string(),
+                                                  0, 0, EM_X86_64, EXEC::EI_DATA::ELFDATA2LSB,
+                                                  EXEC::EI_CLASS::ELFCLASS64).is_err());
+    }
+}
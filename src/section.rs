@@ -105,6 +105,39 @@ impl Section {
     }
 
 
+    pub fn flags(&self)-> u64 {
+        match &self.SH{
+            SecHeader::ThirtyTwo(sh)=>{
+                return sh.sh_flags as u64
+            },
+            SecHeader::SixtyFour(sh)=>{
+                return sh.sh_flags
+            }
+        }
+    }
+
+    pub fn addr(&self)-> u64 {
+        match &self.SH{
+            SecHeader::ThirtyTwo(sh)=>{
+                return sh.sh_addr as u64
+            },
+            SecHeader::SixtyFour(sh)=>{
+                return sh.sh_addr
+            }
+        }
+    }
+
+    pub fn addralign(&self)-> u64 {
+        match &self.SH{
+            SecHeader::ThirtyTwo(sh)=>{
+                return sh.sh_addralign as u64
+            },
+            SecHeader::SixtyFour(sh)=>{
+                return sh.sh_addralign
+            }
+        }
+    }
+
     /*
     NOTE: From ELf man page Some sections hold a table of fixed-sized entries, such as
                      a symbol table.  For such a section, this member gives the
@@ -144,6 +177,17 @@ impl Section {
         }
     }
 
+    pub fn info_idx(&self)-> u32 {
+        match &self.SH{
+            SecHeader::ThirtyTwo(sh)=>{
+                return sh.sh_info
+            },
+            SecHeader::SixtyFour(sh)=>{
+                return sh.sh_info
+            }
+        }
+    }
+
     pub fn shtype_as_u32(&self)-> u32 {
         match &self.SH{
             SecHeader::ThirtyTwo(sh)=>{
@@ -209,6 +253,33 @@ impl Strtab{
         }
     }
 
+    /*
+    * Append a string to the end of the table, returning its byte offset (index) for use in
+    * sh_name/st_name fields. The caller is responsible for writing the returned table bytes
+    * back to the string table section.
+    */
+    pub fn add_str(&mut self, new_str: &str) -> u32 {
+        self.add_bytes(new_str.as_bytes())
+    }
+
+    /* Append a string given as raw bytes (without the terminating null), see add_str */
+    pub fn add_bytes(&mut self, new_bytes: &[u8]) -> u32 {
+        let idx = self.strtab.len() as u32;
+        //the table normally ends with a null, which leaves an empty string last in parsed_strs
+        let terminated = self.strtab.last().map_or(true, |&b| b == 0);
+        self.strtab.extend(new_bytes);
+        self.strtab.push(0);
+        if terminated && self.parsed_strs.last().map_or(false, |last| last.is_empty()) {
+            self.parsed_strs.pop();
+            self.parsed_strs.push(new_bytes.iter().map(|b| char::from(*b)).collect());
+            self.parsed_strs.push(String::new());
+        } else {
+            self.parsed_strs = self.strtab.iter().map(|b| char::from(*b)).collect::<String>()
+                .split("\u{0}").map(|s| s.to_string()).collect();
+        }
+        idx
+    }
+
 
 }
 
@@ -248,8 +319,12 @@ pub enum SH_Type {
     SHT_PREINIT_ARRAY= 16,		/* Array of pre-constructors */
     SHT_GROUP	=  17,		/* Section group */
     SHT_SYMTAB_SHNDX = 18,		/* Extended section indeces */
-    SHT_NUM		=  19,		/* Number of defined types.  */
+    SHT_RELR	=  19,		/* RELR relative relocations */
+    SHT_NUM		=  20,		/* Number of defined types.  */
     SHT_LOOS=	  0x60000000,	/* Start OS-specific.  */
+    SHT_ANDROID_REL =  0x60000001,	/* Android packed (APS2) REL relocations */
+    SHT_ANDROID_RELA = 0x60000002,	/* Android packed (APS2) RELA relocations */
+    SHT_ANDROID_RELR = 0x6fffff00,	/* Android RELR relative relocations */
     SHT_GNU_ATTRIBUTES= 0x6ffffff5,	/* Object attributes.  */
     SHT_GNU_HASH	=  0x6ffffff6,	/* GNU-style hash table.  */
     SHT_GNU_LIBLIST	=  0x6ffffff7,	/* Prelink library list */
@@ -290,8 +365,12 @@ pub fn match_sh_type_as_str(sh_type: String) -> Result<u32, std::io::Error>{
     "SHT_PREINIT_ARRAY" =>Ok(16),		/* Array of pre-constructors */
     "SHT_GROUP" =>  Ok(17),		/* Section group */
     "SHT_SYMTAB_SHNDX" =>Ok(18),		/* Extended section indeces */
-    "SHT_NUM" => Ok(19),		/* Number of defined types.  */
+    "SHT_RELR" => Ok(19),		/* RELR relative relocations */
+    "SHT_NUM" => Ok(20),		/* Number of defined types.  */
     "SHT_LOOS" =>	  Ok(0x60000000),	/* Start OS-specific.  */
+    "SHT_ANDROID_REL" => Ok(0x60000001),	/* Android packed (APS2) REL relocations */
+    "SHT_ANDROID_RELA" => Ok(0x60000002),	/* Android packed (APS2) RELA relocations */
+    "SHT_ANDROID_RELR" => Ok(0x6fffff00),	/* Android RELR relative relocations */
     "SHT_GNU_ATTRIBUTES" => Ok(0x6ffffff5),	/* Object attributes.  */
     "SHT_GNU_HASH" =>  Ok(0x6ffffff6),	/* GNU-style hash table.  */
     "SHT_GNU_LIBLIST" =>  Ok(0x6ffffff7),	/* Prelink library list */
@@ -345,6 +424,27 @@ pub enum SH_Flags {
 */
 
 
+/*
+* Parse a combination of section header flags, provided as flag names separated by `|` (e.g.
+* `SHF_ALLOC|SHF_EXECINSTR`) and/or hex values
+*/
+pub fn match_sh_flags_as_str(sh_flags: String) -> Result<u64, std::io::Error> {
+    let mut flags: u64 = 0;
+    for flag in sh_flags.split("|") {
+        let flag = flag.trim();
+        flags |= match match_sh_flag_as_str(flag.to_string()) {
+            Ok(val) => val,
+            Err(err) => {
+                match u64::from_str_radix(flag.trim_start_matches("0x"), 16) {
+                    Ok(val) => val,
+                    Err(_) => return Err(err),
+                }
+            }
+        };
+    }
+    Ok(flags)
+}
+
 /* TODO need to look up any other potentially supported flags, likely processor specific?*/
 /*NOTE: Caller will need to downcast to u32 for 32bit section headers*/
 pub fn match_sh_flag_as_str(sh_flag: String) -> Result<u64, std::io::Error> {
@@ -558,9 +658,13 @@ impl SecHeader32{
             16 => Ok(SH_Type::SHT_PREINIT_ARRAY),		/* Array of pre-constructors */
             17 => Ok(SH_Type::SHT_GROUP)	,		/* Section group */
             18 => Ok(SH_Type::SHT_SYMTAB_SHNDX) ,		/* Extended section indeces */
-            19  => Ok(SH_Type::SHT_NUM)		,		/* Number of defined types.  */
+            19  => Ok(SH_Type::SHT_RELR)	,		/* RELR relative relocations */
+            20  => Ok(SH_Type::SHT_NUM)		,		/* Number of defined types.  */
             /* Start OS-specific.  */
             0x60000000 => Ok(SH_Type::SHT_LOOS),
+            0x60000001 => Ok(SH_Type::SHT_ANDROID_REL),	/* Android packed (APS2) REL relocations */
+            0x60000002 => Ok(SH_Type::SHT_ANDROID_RELA),	/* Android packed (APS2) RELA relocations */
+            0x6fffff00 => Ok(SH_Type::SHT_ANDROID_RELR),	/* Android RELR relative relocations */
             0x6ffffff5 => Ok(SH_Type::SHT_GNU_ATTRIBUTES),	/* Object attributes.  */
             0x6ffffff6 => Ok(SH_Type::SHT_GNU_HASH)	 ,	/* GNU-style hash table.  */
             0x6ffffff7 => Ok(SH_Type::SHT_GNU_LIBLIST)	 ,	/* Prelink library list */
@@ -731,9 +835,13 @@ impl SecHeader64{
             16 => Ok(SH_Type::SHT_PREINIT_ARRAY),		/* Array of pre-constructors */
             17 => Ok(SH_Type::SHT_GROUP)	,		/* Section group */
             18 => Ok(SH_Type::SHT_SYMTAB_SHNDX) ,		/* Extended section indeces */
-            19  => Ok(SH_Type::SHT_NUM)		,		/* Number of defined types.  */
+            19  => Ok(SH_Type::SHT_RELR)	,		/* RELR relative relocations */
+            20  => Ok(SH_Type::SHT_NUM)		,		/* Number of defined types.  */
             /* Start OS-specific.  */
             0x60000000 => Ok(SH_Type::SHT_LOOS),
+            0x60000001 => Ok(SH_Type::SHT_ANDROID_REL),	/* Android packed (APS2) REL relocations */
+            0x60000002 => Ok(SH_Type::SHT_ANDROID_RELA),	/* Android packed (APS2) RELA relocations */
+            0x6fffff00 => Ok(SH_Type::SHT_ANDROID_RELR),	/* Android RELR relative relocations */
             0x6ffffff5 => Ok(SH_Type::SHT_GNU_ATTRIBUTES),	/* Object attributes.  */
             0x6ffffff6 => Ok(SH_Type::SHT_GNU_HASH)	 ,	/* GNU-style hash table.  */
             0x6ffffff7 => Ok(SH_Type::SHT_GNU_LIBLIST)	 ,	/* Prelink library list */
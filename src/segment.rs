@@ -48,6 +48,40 @@ impl Segment{
     }
 
 
+    /*Same as offset(), but widened to u64 for offset arithmetic across both classes*/
+    pub fn file_offset(&self)->u64{
+        match &self.PH{
+            ProgHeader::ThirtyTwo(ph)=>{
+                return ph.p_offset as u64
+            },
+            ProgHeader::SixtyFour(ph)=>{
+                return ph.p_offset
+            }
+        }
+    }
+
+    pub fn p_type(&self)->u32{
+        match &self.PH{
+            ProgHeader::ThirtyTwo(ph)=>{
+                return ph.p_type
+            },
+            ProgHeader::SixtyFour(ph)=>{
+                return ph.p_type
+            }
+        }
+    }
+
+    pub fn flags(&self)->u32{
+        match &self.PH{
+            ProgHeader::ThirtyTwo(ph)=>{
+                return ph.p_flags
+            },
+            ProgHeader::SixtyFour(ph)=>{
+                return ph.p_flags
+            }
+        }
+    }
+
     pub fn increase_offset(&mut self, by_size: u64){
 
         match &mut self.PH{
@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{Read, Write};
 use byteorder::*;
 
 use crate::header::*;
@@ -61,6 +61,16 @@ impl Symbol32 {
             st_shndx: shndx,
         })
     }
+
+    pub fn write_symbol<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.st_name)?;
+        wrtr.write_u32::<B>(self.st_value)?;
+        wrtr.write_u32::<B>(self.st_size)?;
+        wrtr.write_all(&[self.st_info, self.st_other])?;
+        wrtr.write_u16::<B>(self.st_shndx)?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -99,6 +109,16 @@ impl Symbol64 {
             st_shndx: shndx,
         })
     }
+
+    pub fn write_symbol<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.st_name)?;
+        wrtr.write_all(&[self.st_info, self.st_other])?;
+        wrtr.write_u16::<B>(self.st_shndx)?;
+        wrtr.write_u64::<B>(self.st_value)?;
+        wrtr.write_u64::<B>(self.st_size)?;
+        Ok(())
+    }
 }
 
 
@@ -138,6 +158,16 @@ impl DynSymbol32 {
             st_shndx: shndx,
         })
     }
+
+    pub fn write_symbol<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.st_name)?;
+        wrtr.write_u32::<B>(self.st_value)?;
+        wrtr.write_u32::<B>(self.st_size)?;
+        wrtr.write_all(&[self.st_info, self.st_other])?;
+        wrtr.write_u16::<B>(self.st_shndx)?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -176,8 +206,164 @@ impl DynSymbol64 {
             st_shndx: shndx,
         })
     }
+
+    pub fn write_symbol<W, B: ByteOrder>(&self, wrtr: &mut W) -> Result<(), std::io::Error>
+        where W: Write {
+        wrtr.write_u32::<B>(self.st_name)?;
+        wrtr.write_all(&[self.st_info, self.st_other])?;
+        wrtr.write_u16::<B>(self.st_shndx)?;
+        wrtr.write_u64::<B>(self.st_value)?;
+        wrtr.write_u64::<B>(self.st_size)?;
+        Ok(())
+    }
+}
+
+
+
+/*
+* Generates the accessors shared by .symtab and .dynsym entries, along with serialization of
+* an entry back into its section bytes
+*/
+macro_rules! symbol_entry {
+    ($name:ident, $name32:ident, $name64:ident) => {
+        impl $name {
+            /* A null entry of the provided class, for filling in with update_symbol */
+            pub fn new(class: EXEC::EI_CLASS) -> Result<$name, std::io::Error> {
+                match class {
+                    EXEC::EI_CLASS::ELFCLASS32 => Ok($name::ThirtyTwo($name32 {
+                        st_name: 0,
+                        st_value: 0,
+                        st_size: 0,
+                        st_info: 0,
+                        st_other: 0,
+                        st_shndx: 0,
+                    })),
+                    EXEC::EI_CLASS::ELFCLASS64 => Ok($name::SixtyFour($name64 {
+                        st_name: 0,
+                        st_info: 0,
+                        st_other: 0,
+                        st_shndx: 0,
+                        st_value: 0,
+                        st_size: 0,
+                    })),
+                    _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                 "Elf not supported")),
+                }
+            }
+
+            pub fn name_idx(&self) -> u32 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_name,
+                    $name::SixtyFour(sym) => sym.st_name,
+                }
+            }
+
+            pub fn value(&self) -> u64 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_value as u64,
+                    $name::SixtyFour(sym) => sym.st_value,
+                }
+            }
+
+            pub fn size(&self) -> u64 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_size as u64,
+                    $name::SixtyFour(sym) => sym.st_size,
+                }
+            }
+
+            pub fn info(&self) -> u8 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_info,
+                    $name::SixtyFour(sym) => sym.st_info,
+                }
+            }
+
+            pub fn other(&self) -> u8 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_other,
+                    $name::SixtyFour(sym) => sym.st_other,
+                }
+            }
+
+            pub fn shndx(&self) -> u16 {
+                match self {
+                    $name::ThirtyTwo(sym) => sym.st_shndx,
+                    $name::SixtyFour(sym) => sym.st_shndx,
+                }
+            }
+
+            /*
+            * Update a field of the entry. Besides the Elf_Sym fields, st_bind, st_type and
+            * st_visibility update the corresponding bits of st_info or st_other.
+            */
+            pub fn update_symbol(&mut self, field: &str, val: u64) -> Result<(), std::io::Error> {
+                let (info, other) = (self.info(), self.other());
+                match self {
+                    $name::ThirtyTwo(sym) => {
+                        if val > u32::MAX as u64 && (field == "st_value" || field == "st_size") {
+                            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                           "Value does not fit in a 32 bit symbol"))
+                        }
+                        match field {
+                            "st_name" => sym.st_name = val as u32,
+                            "st_value" => sym.st_value = val as u32,
+                            "st_size" => sym.st_size = val as u32,
+                            "st_info" => sym.st_info = val as u8,
+                            "st_other" => sym.st_other = val as u8,
+                            "st_shndx" => sym.st_shndx = val as u16,
+                            "st_bind" => sym.st_info = (val as u8) << 4 | (info & 0xf),
+                            "st_type" => sym.st_info = (info & 0xf0) | (val as u8 & 0xf),
+                            "st_visibility" => sym.st_other = (other & !0x3) | (val as u8 & 0x3),
+                            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                                "Invalid symbol field provided")),
+                        }
+                    }
+                    $name::SixtyFour(sym) => {
+                        match field {
+                            "st_name" => sym.st_name = val as u32,
+                            "st_value" => sym.st_value = val,
+                            "st_size" => sym.st_size = val,
+                            "st_info" => sym.st_info = val as u8,
+                            "st_other" => sym.st_other = val as u8,
+                            "st_shndx" => sym.st_shndx = val as u16,
+                            "st_bind" => sym.st_info = (val as u8) << 4 | (info & 0xf),
+                            "st_type" => sym.st_info = (info & 0xf0) | (val as u8 & 0xf),
+                            "st_visibility" => sym.st_other = (other & !0x3) | (val as u8 & 0x3),
+                            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                                "Invalid symbol field provided")),
+                        }
+                    }
+                }
+                Ok(())
+            }
+
+            pub fn write<W>(&self, wrtr: &mut W, data: EXEC::EI_DATA) -> Result<(), std::io::Error>
+                where W: Write {
+                match data {
+                    EXEC::EI_DATA::ELFDATA2LSB => {
+                        match self {
+                            $name::ThirtyTwo(sym) => sym.write_symbol::<W, LittleEndian>(wrtr),
+                            $name::SixtyFour(sym) => sym.write_symbol::<W, LittleEndian>(wrtr),
+                        }
+                    }
+                    EXEC::EI_DATA::ELFDATA2MSB => {
+                        match self {
+                            $name::ThirtyTwo(sym) => sym.write_symbol::<W, BigEndian>(wrtr),
+                            $name::SixtyFour(sym) => sym.write_symbol::<W, BigEndian>(wrtr),
+                        }
+                    }
+                    _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                 "Elf not supported")),
+                }
+            }
+        }
+    }
 }
 
+symbol_entry!(Symbol, Symbol32, Symbol64);
+symbol_entry!(DynSymbol, DynSymbol32, DynSymbol64);
+
 
 
 #[derive(Clone, Debug)]
@@ -255,6 +441,15 @@ impl Symtable {
         })
     }
 
+    /* Serialize the entries back into the bytes of a .symtab section */
+    pub fn to_bytes(&self, data: EXEC::EI_DATA) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        for sym in &self.entries {
+            sym.write(&mut bytes, data)?;
+        }
+        Ok(bytes)
+    }
+
 }
 
 
@@ -326,19 +521,90 @@ impl DynSymtable {
         })
     }
 
+    /* Serialize the entries back into the bytes of a .dynsym section */
+    pub fn to_bytes(&self, data: EXEC::EI_DATA) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        for sym in &self.entries {
+            sym.write(&mut bytes, data)?;
+        }
+        Ok(bytes)
+    }
+
 }
 
-#[allow(non_camel_case_types)]
-pub enum ST_bind{
+/*
+* A symbol of a .symtab or .dynsym section resolved against the rest of the ELF: its name read
+* from the linked string table, st_info/st_other decoded into binding, type and visibility
+* (None for values outside the known ones), and the name of the section it is defined in.
+* symtab_idx is the section index of its symbol table and index its position within it.
+*/
+#[derive(Clone, Debug)]
+pub struct ElfSymbol {
+    pub symtab_idx: u32,
+    pub index: u32,
+    pub dynamic: bool,
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub other: u8,
+    pub shndx: u16,
+    pub bind: Option<ST_bind>,
+    pub sym_type: Option<ST_type>,
+    pub visibility: Option<ST_visibility>,
+    pub section: Option<String>,
+}
+
+impl ElfSymbol {
+    pub fn is_local(&self) -> bool {
+        self.bind == Some(ST_bind::STB_LOCAL)
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.shndx == SHN::SHN_UNDEF as u16
+    }
+}
+
+/*
+* Generates a symbol attribute enum with conversions from the raw value (decoded from st_info
+* or st_other) and from the name, e.g. "STB_GLOBAL".
+*/
+macro_rules! symbol_enum {
+    ($name:ident { $($variant:ident = $val:expr,)* }) => {
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum $name {
+            $($variant = $val,)*
+        }
+
+        impl $name {
+            pub fn from_u8(val: u8) -> Option<$name> {
+                match val {
+                    $($val => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<$name> {
+                match name {
+                    $(stringify!($variant) => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+symbol_enum!(ST_bind {
     STB_LOCAL = 0,
     STB_GLOBAL = 1,
     STB_WEAK = 2,
+    STB_GNU_UNIQUE = 10,
     STB_LOPROC = 13,
     STB_HIPROC = 15,
-}
+});
 
-#[allow(non_camel_case_types)]
-pub enum ST_type{
+symbol_enum!(ST_type {
     STT_NOTYPE = 0,
     STT_OBJECT = 1,
     STT_FUNC = 2,
@@ -346,15 +612,60 @@ pub enum ST_type{
     STT_FILE = 4,
     STT_COMMON = 5,
     STT_TLS = 6,
+    STT_GNU_IFUNC = 10,
     STT_LOPROC = 13,
     STT_HIPROC = 15,
+});
+
+symbol_enum!(ST_visibility {
+    STV_DEFAULT = 0,
+    STV_INTERNAL = 1,
+    STV_HIDDEN = 2,
+    STV_PROTECTED = 3,
+});
+
+/* Binding given by name (e.g. `STB_GLOBAL`) or as a hex value */
+pub fn match_st_bind_as_str(bind: String) -> Result<u8, std::io::Error> {
+    match ST_bind::from_name(bind.as_str()) {
+        Some(bind) => Ok(bind as u8),
+        None => match u8::from_str_radix(bind.trim_start_matches("0x"), 16) {
+            Ok(val) if val < 0x10 => Ok(val),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Invalid symbol binding provided")),
+        }
+    }
+}
+
+/* Type given by name (e.g. `STT_FUNC`) or as a hex value */
+pub fn match_st_type_as_str(sym_type: String) -> Result<u8, std::io::Error> {
+    match ST_type::from_name(sym_type.as_str()) {
+        Some(sym_type) => Ok(sym_type as u8),
+        None => match u8::from_str_radix(sym_type.trim_start_matches("0x"), 16) {
+            Ok(val) if val < 0x10 => Ok(val),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Invalid symbol type provided")),
+        }
+    }
+}
+
+/* Visibility given by name (e.g. `STV_HIDDEN`) or as a hex value */
+pub fn match_st_visibility_as_str(vis: String) -> Result<u8, std::io::Error> {
+    match ST_visibility::from_name(vis.as_str()) {
+        Some(vis) => Ok(vis as u8),
+        None => match u8::from_str_radix(vis.trim_start_matches("0x"), 16) {
+            Ok(val) if val < 0x4 => Ok(val),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                         "Invalid symbol visibility provided")),
+        }
+    }
 }
 
 #[allow(non_camel_case_types)]
 pub enum SHN {
     SHN_UNDEF = 0,
-    SHN_ABS,
-    SHN_COMMON,
+    SHN_ABS = 0xfff1,
+    SHN_COMMON = 0xfff2,
+    SHN_XINDEX = 0xffff,
 }
 
 /*
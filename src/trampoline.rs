@@ -0,0 +1,531 @@
+use crate::header::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum Arch {
+    X86,
+    X86_64,
+    ARM,
+    THUMB,
+    AARCH64,
+    RISCV32,
+    RISCV64,
+}
+
+/*
+* A return-to-original-entry trampoline wrapped around injected bytes: a prologue saving the
+* registers the injected bytes may clobber, and an epilogue restoring them and jumping to the
+* original entry point. All jumps are pc-relative, so position independent executables are
+* supported. The injected bytes must fall through to the epilogue rather than exit.
+*/
+#[derive(Clone, Debug)]
+pub struct Trampoline {
+    pub arch: Arch,
+}
+
+impl Trampoline {
+    /*
+    * Pick the trampoline architecture from the exec header. For ARM, thumb selects a Thumb
+    * trampoline instead of an ARM one.
+    */
+    pub fn new(header: &ExecutiveHeader, thumb: bool) -> Result<Trampoline, std::io::Error> {
+        match header.data {
+            EXEC::EI_DATA::ELFDATA2LSB => {}
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Trampolines are only supported for little \
+                                                endian ELF files")),
+        }
+        let sixty_four = match header.class {
+            EXEC::EI_CLASS::ELFCLASS64 => true,
+            _ => false,
+        };
+
+        if thumb && header.machine() != EXEC::EI_MACH::EM_ARM as u16 {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                           "Thumb trampolines are only supported for ARM ELF files"))
+        }
+
+        let arch = match match_mach(header.machine()) {
+            Ok(EXEC::EI_MACH::EM_386) => Arch::X86,
+            Ok(EXEC::EI_MACH::EM_X86_64) => Arch::X86_64,
+            Ok(EXEC::EI_MACH::EM_ARM) if thumb => Arch::THUMB,
+            Ok(EXEC::EI_MACH::EM_ARM) => Arch::ARM,
+            Ok(EXEC::EI_MACH::EM_AARCH64) => Arch::AARCH64,
+            Ok(EXEC::EI_MACH::EM_RISCV) if sixty_four => Arch::RISCV64,
+            Ok(EXEC::EI_MACH::EM_RISCV) => Arch::RISCV32,
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                "Trampolines are not supported for this \
+                                                machine type")),
+        };
+        Ok(Trampoline {
+            arch: arch,
+        })
+    }
+
+    /* Total size of the injected bytes wrapped in the trampoline */
+    pub fn wrapped_size(&self, inj_size: usize) -> usize {
+        self.prologue().len() + inj_size + self.max_epilogue_size()
+    }
+
+    /*
+    * Wrap the injected bytes, to be placed at vaddr, in the trampoline jumping to target. The
+    * result is always wrapped_size bytes long (zero padded after the final jump).
+    */
+    pub fn wrap(&self, inj_bytes: &[u8], vaddr: u64, target: u64) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes = self.prologue();
+        bytes.extend(inj_bytes);
+        let epilogue = self.epilogue(vaddr + bytes.len() as u64, target)?;
+        bytes.extend(epilogue);
+        bytes.resize(self.wrapped_size(inj_bytes.len()), 0);
+        Ok(bytes)
+    }
+
+    /* Size of a call stub, see call_stub */
+    pub fn call_stub_size(&self) -> usize {
+        self.prologue().len() + self.max_call_size() + self.max_epilogue_size()
+    }
+
+    /*
+    * A trampoline calling the injected code at entry as a function rather than falling through
+    * it, for linked relocatable payloads (e.g. compiled C functions), which return to their
+    * caller. The stub is placed at vaddr and is always call_stub_size bytes long.
+    */
+    pub fn call_stub(&self, vaddr: u64, entry: u64, target: u64) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes = self.prologue();
+        bytes.extend(self.call(vaddr + bytes.len() as u64, entry)?);
+        let epilogue = self.epilogue(vaddr + bytes.len() as u64, target)?;
+        bytes.extend(epilogue);
+        bytes.resize(self.call_stub_size(), 0);
+        Ok(bytes)
+    }
+
+    /* Save all general purpose registers (and flags, on x86) on the stack */
+    pub fn prologue(&self) -> Vec<u8> {
+        match self.arch {
+            Arch::X86 => vec![0x9c, 0x60], //pushfd; pushad
+            Arch::X86_64 => {
+                //pushfq; push rax, rcx, rdx, rbx, rbp, rsi, rdi, r8-r15
+                let mut bytes = vec![0x9c, 0x50, 0x51, 0x52, 0x53, 0x55, 0x56, 0x57];
+                for reg in 0..8 {
+                    bytes.extend(&[0x41, 0x50 + reg]);
+                }
+                bytes
+            }
+            Arch::ARM => words(&[0xe92d5fff]), //push {r0-r12, lr}
+            Arch::THUMB => halfwords(&[0xe92d, 0x5fff]), //push.w {r0-r12, lr}
+            Arch::AARCH64 => {
+                //stp x0, x1, [sp, #-16]! ... stp x28, x29, [sp, #-16]!; stp x30, xzr, [sp, #-16]!
+                let insns: Vec<u32> = (0..16)
+                    .map(|pair| 0xa9bf03e0 | ((2 * pair + 1).min(31) << 10) | (2 * pair))
+                    .collect();
+                words(&insns)
+            }
+            Arch::RISCV32 | Arch::RISCV64 => {
+                let xlen = self.xlen();
+                let store = if xlen == 8 { 3 } else { 2 }; //sd or sw
+                let mut insns = vec![itype(-(32 * xlen as i32), 2, 0, 2, 0x13)]; //addi sp, sp, -frame
+                for reg in riscv_saved_regs() {
+                    let off = (reg * xlen) as i32;
+                    insns.push(stype(off, reg, 2, store, 0x23));
+                }
+                words(&insns)
+            }
+        }
+    }
+
+    /*
+    * Restore the registers saved by the prologue and jump to target. vaddr is the address
+    * the epilogue is placed at. Scratch registers used for the jump (ip on ARM, t1 on RISC-V)
+    * are not restored; they carry no state at the entry point.
+    */
+    pub fn epilogue(&self, vaddr: u64, target: u64) -> Result<Vec<u8>, std::io::Error> {
+        match self.arch {
+            Arch::X86 | Arch::X86_64 => {
+                let mut bytes = match self.arch {
+                    Arch::X86 => vec![0x61, 0x9d], //popad; popfd
+                    _ => {
+                        //pop r15-r8, rdi, rsi, rbp, rbx, rdx, rcx, rax; popfq
+                        let mut bytes = vec![];
+                        for reg in (0..8).rev() {
+                            bytes.extend(&[0x41, 0x58 + reg]);
+                        }
+                        bytes.extend(&[0x5f, 0x5e, 0x5d, 0x5b, 0x5a, 0x59, 0x58, 0x9d]);
+                        bytes
+                    }
+                };
+                //jmp rel32
+                let disp = target.wrapping_sub(vaddr + bytes.len() as u64 + 5) as i64;
+                let disp = if self.arch == Arch::X86 { disp as i32 as i64 } else { disp };
+                if disp < i32::MIN as i64 || disp > i32::MAX as i64 {
+                    return Err(out_of_range())
+                }
+                bytes.push(0xe9);
+                bytes.extend(&(disp as i32).to_le_bytes());
+                Ok(bytes)
+            }
+            Arch::ARM => {
+                check_alignment(vaddr, 4)?;
+                //pop {r0-r12, lr}; ldr ip, [pc, #4]; add ip, pc, ip; bx ip; .word target - .
+                let lit = (target as u32).wrapping_sub(vaddr as u32 + 16);
+                Ok(words(&[0xe8bd5fff, 0xe59fc004, 0xe08fc00c, 0xe12fff1c, lit]))
+            }
+            Arch::THUMB => {
+                check_alignment(vaddr, 2)?;
+                let mut insns = vec![0xe8bd, 0x5fff]; //pop.w {r0-r12, lr}
+                //the literal load must be word aligned
+                if !(vaddr + 4).is_multiple_of(4) {
+                    insns.push(0xbf00); //nop
+                }
+                let ldr = vaddr + insns.len() as u64 * 2;
+                //ldr.w ip, [pc, #4]; add ip, pc; bx ip; .word target - .
+                let lit = (target as u32).wrapping_sub(ldr as u32 + 8);
+                insns.extend(&[0xf8df, 0xc004, 0x44fc, 0x4760,
+                               lit as u16 as u32, lit >> 16]);
+                Ok(halfwords(&insns))
+            }
+            Arch::AARCH64 => {
+                check_alignment(vaddr, 4)?;
+                //ldp x30, xzr, [sp], #16; ... ldp x0, x1, [sp], #16
+                let mut insns: Vec<u32> = (0..16).rev()
+                    .map(|pair| 0xa8c103e0 | ((2 * pair + 1).min(31) << 10) | (2 * pair))
+                    .collect();
+                //b target
+                let b_addr = vaddr + insns.len() as u64 * 4;
+                let disp = target.wrapping_sub(b_addr) as i64;
+                if !(-(1 << 27)..1 << 27).contains(&disp) {
+                    return Err(out_of_range())
+                }
+                insns.push(0x14000000 | ((disp >> 2) as u32 & 0x3ffffff));
+                Ok(words(&insns))
+            }
+            Arch::RISCV32 | Arch::RISCV64 => {
+                check_alignment(vaddr, 2)?;
+                let mut bytes = vec![];
+                //instructions are 4 byte aligned without the compressed extension
+                if !vaddr.is_multiple_of(4) {
+                    bytes.extend(&[0x01, 0x00]); //c.nop
+                }
+                let xlen = self.xlen();
+                let load = if xlen == 8 { 3 } else { 2 }; //ld or lw
+                let mut insns = vec![];
+                for reg in riscv_saved_regs() {
+                    let off = (reg * xlen) as i32;
+                    insns.push(itype(off, 2, load, reg, 0x03));
+                }
+                insns.push(itype(32 * xlen as i32, 2, 0, 2, 0x13)); //addi sp, sp, frame
+
+                //auipc t1, %hi(target - .); jalr x0, %lo(target - .)(t1)
+                let auipc = vaddr + bytes.len() as u64 + insns.len() as u64 * 4;
+                //the whole address space is in range on RV32, since addresses wrap around
+                let disp = target.wrapping_sub(auipc);
+                if xlen == 8 && ((disp as i64) < i32::MIN as i64
+                    || disp as i64 > i32::MAX as i64 - 0x800) {
+                    return Err(out_of_range())
+                }
+                let hi = (disp as u32).wrapping_add(0x800) >> 12;
+                let lo = (disp as u32).wrapping_sub(hi << 12);
+                insns.push(hi << 12 | 6 << 7 | 0x17);
+                insns.push(itype(lo as i32, 6, 0, 0, 0x67));
+                bytes.extend(words(&insns));
+                Ok(bytes)
+            }
+        }
+    }
+
+    /*
+    * Call entry from vaddr. The registers saved by the prologue keep the stack aligned as it
+    * was at the entry point, except on x86 where it is realigned around the call.
+    */
+    pub fn call(&self, vaddr: u64, entry: u64) -> Result<Vec<u8>, std::io::Error> {
+        match self.arch {
+            Arch::X86 => {
+                //sub esp, 12; call rel32; add esp, 12
+                let disp = entry.wrapping_sub(vaddr + 8) as u32;
+                let mut bytes = vec![0x83, 0xec, 0x0c, 0xe8];
+                bytes.extend(&disp.to_le_bytes());
+                bytes.extend(&[0x83, 0xc4, 0x0c]);
+                Ok(bytes)
+            }
+            Arch::X86_64 => {
+                //call rel32
+                let disp = entry.wrapping_sub(vaddr + 5) as i64;
+                if disp < i32::MIN as i64 || disp > i32::MAX as i64 {
+                    return Err(out_of_range())
+                }
+                let mut bytes = vec![0xe8];
+                bytes.extend(&(disp as i32).to_le_bytes());
+                Ok(bytes)
+            }
+            Arch::ARM => {
+                check_alignment(vaddr, 4)?;
+                //ldr ip, [pc, #8]; add ip, pc, ip; blx ip; b . + 8; .word entry - .
+                let lit = (entry as u32).wrapping_sub(vaddr as u32 + 12);
+                Ok(words(&[0xe59fc008, 0xe08fc00c, 0xe12fff3c, 0xea000000, lit]))
+            }
+            Arch::THUMB => {
+                check_alignment(vaddr, 2)?;
+                let mut insns = vec![];
+                //the literal load must be word aligned
+                if !vaddr.is_multiple_of(4) {
+                    insns.push(0xbf00); //nop
+                }
+                let ldr = vaddr + insns.len() as u64 * 2;
+                //ldr.w ip, [pc, #8]; add ip, pc; blx ip; b.n . + 8; nop; .word entry - .
+                let lit = (entry as u32).wrapping_sub(ldr as u32 + 8);
+                insns.extend(&[0xf8df, 0xc008, 0x44fc, 0x47e0, 0xe002, 0xbf00,
+                               lit as u16 as u32, lit >> 16]);
+                Ok(halfwords(&insns))
+            }
+            Arch::AARCH64 => {
+                check_alignment(vaddr, 4)?;
+                //bl entry
+                let disp = entry.wrapping_sub(vaddr) as i64;
+                if !(-(1 << 27)..1 << 27).contains(&disp) {
+                    return Err(out_of_range())
+                }
+                Ok(words(&[0x94000000 | ((disp >> 2) as u32 & 0x3ffffff)]))
+            }
+            Arch::RISCV32 | Arch::RISCV64 => {
+                check_alignment(vaddr, 2)?;
+                let mut bytes = vec![];
+                if !vaddr.is_multiple_of(4) {
+                    bytes.extend(&[0x01, 0x00]); //c.nop
+                }
+                //auipc ra, %hi(entry - .); jalr ra, %lo(entry - .)(ra)
+                let auipc = vaddr + bytes.len() as u64;
+                let disp = entry.wrapping_sub(auipc);
+                if self.xlen() == 8 && ((disp as i64) < i32::MIN as i64
+                    || disp as i64 > i32::MAX as i64 - 0x800) {
+                    return Err(out_of_range())
+                }
+                let hi = (disp as u32).wrapping_add(0x800) >> 12;
+                let lo = (disp as u32).wrapping_sub(hi << 12);
+                bytes.extend(words(&[hi << 12 | 1 << 7 | 0x17, itype(lo as i32, 1, 0, 1, 0x67)]));
+                Ok(bytes)
+            }
+        }
+    }
+
+    /* Largest possible call, including any alignment padding */
+    fn max_call_size(&self) -> usize {
+        match self.arch {
+            Arch::X86 => 11,
+            Arch::X86_64 => 5,
+            Arch::ARM => 20,
+            Arch::THUMB => 18,
+            Arch::AARCH64 => 4,
+            Arch::RISCV32 | Arch::RISCV64 => 10,
+        }
+    }
+
+    /* Largest possible epilogue, including any alignment padding */
+    fn max_epilogue_size(&self) -> usize {
+        match self.arch {
+            Arch::X86 => 7,
+            Arch::X86_64 => 29,
+            Arch::ARM => 20,
+            Arch::THUMB => 18,
+            Arch::AARCH64 => 68,
+            Arch::RISCV32 | Arch::RISCV64 => 2 + (riscv_saved_regs().len() + 3) * 4,
+        }
+    }
+
+    fn xlen(&self) -> u32 {
+        match self.arch {
+            Arch::RISCV64 => 8,
+            _ => 4,
+        }
+    }
+}
+
+/* RISC-V registers saved by the trampoline: all but zero and sp */
+fn riscv_saved_regs() -> Vec<u32> {
+    (1..32).filter(|&reg| reg != 2).collect()
+}
+
+/* RISC-V I-type instruction */
+fn itype(imm: i32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (imm as u32 & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+/* RISC-V S-type instruction */
+fn stype(imm: i32, rs2: u32, rs1: u32, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm as u32 & 0xfff;
+    (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | opcode
+}
+
+fn words(insns: &[u32]) -> Vec<u8> {
+    insns.iter().flat_map(|insn| insn.to_le_bytes().to_vec()).collect()
+}
+
+fn halfwords(insns: &[u32]) -> Vec<u8> {
+    insns.iter().flat_map(|insn| (*insn as u16).to_le_bytes().to_vec()).collect()
+}
+
+fn check_alignment(vaddr: u64, align: u64) -> Result<(), std::io::Error> {
+    if !vaddr.is_multiple_of(align) {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                       "Injection bytes are not a multiple of the instruction \
+                                       size, cannot append a trampoline"))
+    }
+    Ok(())
+}
+
+fn out_of_range() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other,
+                        "Jump or call target is out of range of the trampoline")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Arch; 7] = [Arch::X86, Arch::X86_64, Arch::ARM, Arch::THUMB, Arch::AARCH64,
+                            Arch::RISCV32, Arch::RISCV64];
+
+    fn tramp(arch: Arch) -> Trampoline {
+        Trampoline {
+            arch: arch,
+        }
+    }
+
+    fn tail(bytes: &[u8], len: usize) -> &[u8] {
+        &bytes[bytes.len() - len..]
+    }
+
+    #[test]
+    fn x86_64_jumps() {
+        let t = tramp(Arch::X86_64);
+        let pops = [0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x41, 0x5b, 0x41, 0x5a,
+                    0x41, 0x59, 0x41, 0x58, 0x5f, 0x5e, 0x5d, 0x5b, 0x5a, 0x59, 0x58, 0x9d];
+        //forward: the jmp ends at 0x1000 + 24 + 5
+        let epilogue = t.epilogue(0x1000, 0x2000).unwrap();
+        assert_eq!(&epilogue[..24], &pops[..]);
+        assert_eq!(&epilogue[24..], &[0xe9, 0xe3, 0x0f, 0x00, 0x00]);
+        //backward
+        let epilogue = t.epilogue(0x1000, 0x400).unwrap();
+        assert_eq!(&epilogue[24..], &[0xe9, 0xe3, 0xf3, 0xff, 0xff]);
+        assert_eq!(t.call(0x1000, 0x1000).unwrap(), vec![0xe8, 0xfb, 0xff, 0xff, 0xff]);
+        assert_eq!(t.call(0x1000, 0x2005).unwrap(), vec![0xe8, 0x00, 0x10, 0x00, 0x00]);
+        //out of rel32 range
+        assert!(t.epilogue(0x1000, 0x1_0000_2000).is_err());
+        assert!(t.call(0x1_0000_2000, 0x1000).is_err());
+    }
+
+    #[test]
+    fn x86_jumps() {
+        let t = tramp(Arch::X86);
+        assert_eq!(t.epilogue(0x8048000, 0x8049000).unwrap(),
+                   vec![0x61, 0x9d, 0xe9, 0xf9, 0x0f, 0x00, 0x00]);
+        assert_eq!(t.epilogue(0x8048000, 0x8047000).unwrap(),
+                   vec![0x61, 0x9d, 0xe9, 0xf9, 0xef, 0xff, 0xff]);
+        //addresses wrap around on 32 bit, so every target is in range
+        assert_eq!(t.epilogue(0xfffff000, 0x100).unwrap(),
+                   vec![0x61, 0x9d, 0xe9, 0xf9, 0x10, 0x00, 0x00]);
+        assert_eq!(t.call(0x8048000, 0x8048100).unwrap(),
+                   vec![0x83, 0xec, 0x0c, 0xe8, 0xf8, 0x00, 0x00, 0x00, 0x83, 0xc4, 0x0c]);
+    }
+
+    #[test]
+    fn arm_jumps() {
+        let t = tramp(Arch::ARM);
+        //pop {r0-r12, lr}; ldr ip, [pc, #4]; add ip, pc, ip; bx ip; .word target - (vaddr + 16)
+        assert_eq!(t.epilogue(0x10000, 0x20000).unwrap(),
+                   words(&[0xe8bd5fff, 0xe59fc004, 0xe08fc00c, 0xe12fff1c, 0xfff0]));
+        assert_eq!(t.epilogue(0x10000, 0x8000).unwrap(),
+                   words(&[0xe8bd5fff, 0xe59fc004, 0xe08fc00c, 0xe12fff1c, 0xffff7ff0]));
+        assert_eq!(t.call(0x10000, 0x8000).unwrap(),
+                   words(&[0xe59fc008, 0xe08fc00c, 0xe12fff3c, 0xea000000, 0xffff7ff4]));
+        assert!(t.epilogue(0x10002, 0x8000).is_err());
+    }
+
+    #[test]
+    fn thumb_jumps() {
+        let t = tramp(Arch::THUMB);
+        //word aligned literal without padding: ldr at 0x10004, literal relative to 0x1000c
+        assert_eq!(t.epilogue(0x10000, 0x20001).unwrap(),
+                   halfwords(&[0xe8bd, 0x5fff, 0xf8df, 0xc004, 0x44fc, 0x4760, 0xfff5, 0x0000]));
+        //a nop aligns the literal: ldr at 0x10008, literal relative to 0x10010
+        assert_eq!(t.epilogue(0x10002, 0x8001).unwrap(),
+                   halfwords(&[0xe8bd, 0x5fff, 0xbf00, 0xf8df, 0xc004, 0x44fc, 0x4760,
+                               0x7ff1, 0xffff]));
+        //ldr at 0x10004, literal relative to 0x1000c
+        assert_eq!(t.call(0x10002, 0x20001).unwrap(),
+                   halfwords(&[0xbf00, 0xf8df, 0xc008, 0x44fc, 0x47e0, 0xe002, 0xbf00,
+                               0xfff5, 0x0000]));
+        assert!(t.epilogue(0x10001, 0x8001).is_err());
+    }
+
+    #[test]
+    fn aarch64_jumps() {
+        let t = tramp(Arch::AARCH64);
+        let epilogue = t.epilogue(0x400000, 0x401040).unwrap();
+        //ldp x30, xzr, [sp], #16 first and ldp x0, x1, [sp], #16 last
+        assert_eq!(&epilogue[..4], &[0xfe, 0x7f, 0xc1, 0xa8]);
+        assert_eq!(&epilogue[60..64], &words(&[0xa8c107e0])[..]);
+        //b at 0x400040
+        assert_eq!(&epilogue[64..], &words(&[0x14000400])[..]);
+        assert_eq!(tail(&t.epilogue(0x400000, 0x40003c).unwrap(), 4), &[0xff, 0xff, 0xff, 0x17]);
+        //+-128MiB from the b at 0x10000040
+        assert_eq!(tail(&t.epilogue(0x10000000, 0x10000040 - (1 << 27)).unwrap(), 4),
+                   &words(&[0x16000000])[..]);
+        assert_eq!(tail(&t.epilogue(0x10000000, 0x10000040 + (1 << 27) - 4).unwrap(), 4),
+                   &words(&[0x15ffffff])[..]);
+        assert!(t.epilogue(0x10000000, 0x10000040 + (1 << 27)).is_err());
+        assert!(t.epilogue(0x400002, 0x400000).is_err());
+        assert_eq!(t.call(0x400000, 0x3ff000).unwrap(), vec![0x00, 0xfc, 0xff, 0x97]);
+        assert!(t.call(0x10000000, 0x10000000 - (1 << 27) - 4).is_err());
+    }
+
+    #[test]
+    fn riscv_jumps() {
+        let t = tramp(Arch::RISCV64);
+        let epilogue = t.epilogue(0x10000, 0x20000).unwrap();
+        //ld ra, 8(sp) first, then addi sp, sp, 256 after the 30 loads
+        assert_eq!(&epilogue[..4], &[0x83, 0x30, 0x81, 0x00]);
+        assert_eq!(&epilogue[120..124], &[0x13, 0x01, 0x01, 0x10]);
+        //auipc t1, 0x10; jr -124(t1) at 0x1007c
+        assert_eq!(&epilogue[124..], &words(&[0x00010317, 0xf8430067])[..]);
+        //backward, from a 2 byte aligned vaddr padded with c.nop
+        let epilogue = t.epilogue(0x10002, 0x1000).unwrap();
+        assert_eq!(&epilogue[..2], &[0x01, 0x00]);
+        //auipc at 0x10080: -0xf080 is hi -0xf, lo -0x80
+        assert_eq!(tail(&epilogue, 8), &words(&[0xffff1317, 0xf8030067])[..]);
+        assert!(t.epilogue(0x10000, 0x1007c + 0x8000_0000).is_err());
+        assert!(t.epilogue(0x10001, 0x1000).is_err());
+        //auipc ra, 0x1; jalr ra, -0x800(ra)
+        assert_eq!(t.call(0x10000, 0x10800).unwrap(), words(&[0x00001097, 0x800080e7]));
+
+        //the whole address space is in range on RV32
+        let t = tramp(Arch::RISCV32);
+        assert!(t.epilogue(0x10000, 0x8010_0000).is_ok());
+    }
+
+    #[test]
+    fn sizes() {
+        for &arch in &ALL {
+            let t = tramp(arch);
+            for &vaddr in &[0x10000u64, 0x10002] {
+                if vaddr % 4 != 0 && (arch == Arch::ARM || arch == Arch::AARCH64) {
+                    continue
+                }
+                let wrapped = t.wrap(&[0; 4], vaddr, 0x20000).unwrap();
+                assert_eq!(wrapped.len(), t.wrapped_size(4), "{:?}", arch);
+                let stub = t.call_stub(vaddr, 0x30000, 0x20000).unwrap();
+                assert_eq!(stub.len(), t.call_stub_size(), "{:?}", arch);
+                assert!(t.epilogue(vaddr, 0x20000).unwrap().len() <= t.max_epilogue_size());
+                assert!(t.call(vaddr, 0x30000).unwrap().len() <= t.max_call_size());
+            }
+        }
+    }
+
+    #[test]
+    fn wrap_x86_64() {
+        let t = tramp(Arch::X86_64);
+        let wrapped = t.wrap(&[0x90], 0x1000, 0x2000).unwrap();
+        assert_eq!(wrapped[..24], t.prologue()[..]);
+        assert_eq!(wrapped[24], 0x90);
+        //the epilogue starts at 0x1019
+        assert_eq!(wrapped[25..], t.epilogue(0x1019, 0x2000).unwrap()[..]);
+    }
+}
@@ -0,0 +1,5 @@
+extern int target_func(const char *);
+
+int payload(const char *str){
+	return target_func(str);
+}
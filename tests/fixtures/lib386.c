@@ -0,0 +1,5 @@
+extern int target_func(const char *);
+
+int lib_entry(void){
+	return target_func("lib");
+}
@@ -0,0 +1,12 @@
+#include <stdio.h>
+
+int counter = 1;
+
+int vers_get(void){
+	return counter;
+}
+
+int vers_call(void){
+	puts("vers_call");
+	return vers_get() + counter;
+}
@@ -0,0 +1,6 @@
+extern int target_func(const char *);
+
+int payload(void){
+	target_func("first");
+	return target_func("payload");
+}
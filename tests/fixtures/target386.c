@@ -0,0 +1,7 @@
+int target_func(const char *str){
+	return str[0];
+}
+
+void _start(void){
+	for (;;);
+}